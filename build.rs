@@ -0,0 +1,14 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // Only the `grpc` feature has code generated, with the protoc that
+    // protoc-bin-vendored ships, so that nothing has to be installed.
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/weather_crawl.proto");
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        tonic_build::configure()
+            .build_client(false)
+            .compile(&["proto/weather_crawl.proto"], &["proto"])
+            .unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+#ifndef WEATHER_CRAWL_H
+#define WEATHER_CRAWL_H
+
+/* Generated with cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdint.h>
+
+/**
+ * `html` or `json_out` was null.
+ */
+#define WEATHER_CRAWL_INVALID_ARGUMENT -1
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Parse the AWS page `html` into the JSON `index.json` holds.
+ *
+ * Returns 0 with the document in `*json_out`, or an exit code of the
+ * binary with the error message there instead. Either string is the
+ * caller's to release with `weather_crawl_free`.
+ *
+ * # Safety
+ *
+ * `html` must be a NUL-terminated string and `json_out` writable.
+ */
+int weather_crawl_parse(const char *html, char **json_out);
+
+/**
+ * Release a string `weather_crawl_parse` returned; null is ignored.
+ *
+ * # Safety
+ *
+ * `s` must come from `weather_crawl_parse` and not be released twice.
+ */
+void weather_crawl_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WEATHER_CRAWL_H */
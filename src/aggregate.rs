@@ -0,0 +1,213 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use rust_decimal::Decimal;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::region::{spread, Spread};
+use crate::{archive, atomic, compact, export};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Per-station summary of the snapshots in a window.
+#[derive(Serialize)]
+struct Product {
+    window: String,
+    /// First minute of the window.
+    from: String,
+    /// Last minute of the window.
+    to: String,
+    snapshots: usize,
+    /// Units of the snapshots, when not metric.
+    #[serde(skip_serializing_if = "Value::is_null")]
+    units: Value,
+    stations: Vec<StationSummary>,
+}
+
+#[derive(Serialize)]
+struct StationSummary {
+    id: u64,
+    name: Value,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    name_en: Value,
+    samples: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<Spread>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    humidity: Option<Spread>,
+    /// 10-minute mean wind.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wind: Option<Spread>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    atmospheric: Option<Spread>,
+    /// Rain in the window, from how `rainday` grew between snapshots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    precipitation: Option<Decimal>,
+}
+
+/// Values of one station, in the order the snapshots were read.
+#[derive(Default)]
+struct Series {
+    name: Value,
+    name_en: Value,
+    samples: usize,
+    temperature: Vec<Decimal>,
+    humidity: Vec<Decimal>,
+    wind: Vec<Decimal>,
+    atmospheric: Vec<Decimal>,
+    rainday: Vec<(i64, Decimal)>,
+}
+
+pub fn command() -> Command {
+    Command::new("aggregate")
+        .about("summarize each station over the last hour or day of retained snapshots")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--window <WINDOW> "period to summarize")
+                .value_parser(["1h", "24h"])
+                .default_value("1h"),
+        )
+        .arg(arg!(--end <TIME> "last observation of the window [default: the latest one]"))
+        .arg(
+            arg!(--out <PATH> "where to write the product, or `-` for stdout [default: <base>/aggregate-<WINDOW>.json]")
+                .value_parser(value_parser!(PathBuf)),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let window = matches.get_one::<String>("window").unwrap();
+    let minutes = if window == "24h" { 24 * 60 } else { 60 };
+    let end = match matches.get_one::<String>("end") {
+        Some(time) => archive::minute_of(time)
+            .ok_or_else(|| format!("`{}` is not a YYYY-MM-DDTHH:MM time", time))?,
+        None => *crate::gaps::observed_minutes(base)?
+            .last()
+            .ok_or_else(|| format!("no observations under {}", base.display()))?,
+    };
+    let start = end - minutes + 1;
+
+    let mut stations: BTreeMap<u64, Series> = BTreeMap::new();
+    let mut snapshots = 0;
+    let mut units = Value::Null;
+    for path in inputs(base, start, end)? {
+        export::each_snapshot(&path, &mut |snapshot| {
+            let minute = match snapshot["observed_at"]
+                .as_str()
+                .and_then(archive::minute_of)
+            {
+                Some(minute) if (start..=end).contains(&minute) => minute,
+                _ => return Ok(()),
+            };
+            snapshots += 1;
+            units = snapshot["units"].clone();
+            for record in snapshot["records"].as_array().into_iter().flatten() {
+                if let Some(id) = record["id"].as_u64() {
+                    add(stations.entry(id).or_default(), minute, record);
+                }
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    let product = Product {
+        window: window.clone(),
+        from: archive::format_minute(start),
+        to: archive::format_minute(end),
+        snapshots,
+        units,
+        stations: stations
+            .into_iter()
+            .map(|(id, series)| summarize(id, series))
+            .collect(),
+    };
+    let body = serde_json::to_vec(&product)?;
+    match matches.get_one::<PathBuf>("out") {
+        Some(out) if out.as_os_str() == "-" => println!("{}", String::from_utf8(body)?),
+        out => {
+            let out = out
+                .cloned()
+                .unwrap_or_else(|| base.join(format!("aggregate-{}.json", window)));
+            atomic::write(&out, &body)?;
+            eprintln!(
+                "{} stations over {} snapshots from {} to {} written to {}",
+                product.stations.len(),
+                product.snapshots,
+                product.from,
+                product.to,
+                out.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Snapshots and bundles under `base` that may hold minutes of `start..=end`.
+pub fn inputs(base: &Path, start: i64, end: i64) -> std::io::Result<Vec<PathBuf>> {
+    let mut inputs: Vec<PathBuf> = archive::list(base)?
+        .into_iter()
+        .filter(|s| archive::minute_of(&s.observed_at).is_some_and(|m| (start..=end).contains(&m)))
+        .map(|s| s.path)
+        .collect();
+    for bundle in compact::bundles(base)? {
+        let day = archive::minute_of(&format!("{}T00:00", bundle.day));
+        if day.is_some_and(|day| day <= end && start < day + 24 * 60) {
+            inputs.push(bundle.path);
+        }
+    }
+    Ok(inputs)
+}
+
+fn add(series: &mut Series, minute: i64, record: &Value) {
+    let value = |pointer: &str| {
+        record
+            .pointer(pointer)
+            .and_then(|v| serde_json::from_value::<Decimal>(v.clone()).ok())
+    };
+    series.name = record["name"].clone();
+    series.name_en = record["name_en"].clone();
+    series.samples += 1;
+    series.temperature.extend(value("/temperature"));
+    series.humidity.extend(value("/humidity"));
+    series.wind.extend(value("/wind10/velocity"));
+    series.atmospheric.extend(value("/atmospheric"));
+    series
+        .rainday
+        .extend(value("/rain/rainday").map(|mm| (minute, mm)));
+}
+
+fn summarize(id: u64, mut series: Series) -> StationSummary {
+    let precipitation = precipitation(&mut series.rainday);
+    StationSummary {
+        id,
+        name: series.name,
+        name_en: series.name_en,
+        samples: series.samples,
+        temperature: spread(&series.temperature),
+        humidity: spread(&series.humidity),
+        wind: spread(&series.wind),
+        atmospheric: spread(&series.atmospheric),
+        precipitation,
+    }
+}
+
+/// Rain over the minutes of `rainday` readings, from how much the daily total
+/// grew between them.
+pub fn precipitation(rainday: &mut [(i64, Decimal)]) -> Option<Decimal> {
+    rainday.sort_by_key(|(minute, _)| *minute);
+    // `rainday` restarts from zero at midnight KST.
+    (!rainday.is_empty()).then(|| {
+        rainday
+            .windows(2)
+            .map(|pair| match pair[1].1 - pair[0].1 {
+                grew if grew >= Decimal::ZERO => grew,
+                _ => pair[1].1,
+            })
+            .sum::<Decimal>()
+    })
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime};
+
+use serde_json::Value;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::CrawlError;
+use crate::pipeline::Outcome;
+use crate::source::{self, Source};
+use crate::{archive, openapi, region, timezone};
+use crate::{reading, AirRecord, CrawlResult, NearestAir, Record, SkippedRow, SCHEMA_VERSION};
+
+/// Hourly readings of every AirKorea station (`sidoName=전국`).
+pub const AIR_URL: &str = "https://apis.data.go.kr/B552584/ArpltnInforInqireSvc/getCtprvnRltmMesureDnsty?returnType=json&numOfRows=1000&pageNo=1&ver=1.0&sidoName=%EC%A0%84%EA%B5%AD";
+
+/// PM10 and PM2.5 of AirKorea's stations, from its Open API on data.go.kr.
+///
+/// The readings go to `air` of the result. AirKorea names its stations and
+/// does not say where they stand; `--air-stations` does.
+pub struct AirQuality;
+impl Source for AirQuality {
+    fn name(&self) -> &'static str {
+        "air"
+    }
+
+    fn default_url(&self) -> &'static str {
+        AIR_URL
+    }
+
+    fn needs_api_key(&self) -> bool {
+        true
+    }
+
+    /// The service only has the current readings.
+    fn timed_url(&self, _url: &str, _minute: i64) -> Option<String> {
+        None
+    }
+
+    fn problem(&self, body: &str) -> Option<Outcome> {
+        openapi::problem(body)
+    }
+
+    fn parse(&self, url: &str, body: &str) -> Result<CrawlResult, CrawlError> {
+        let items = openapi::items(body).map_err(CrawlError::Decode)?;
+        let mut observed_at = None;
+        let mut air = Vec::new();
+        let mut skipped = Vec::new();
+        for item in &items {
+            let field = |name: &str| item[name].as_str().unwrap_or_default().trim();
+            observed_at = observed_at.max(data_time(field("dataTime")));
+            match make_record(&field) {
+                Ok(record) => air.push(record),
+                Err(reason) => skipped.push(SkippedRow {
+                    reason,
+                    cells: [
+                        "dataTime",
+                        "sidoName",
+                        "stationName",
+                        "pm10Value",
+                        "pm25Value",
+                    ]
+                    .iter()
+                    .map(|name| field(name).to_string())
+                    .collect(),
+                }),
+            }
+        }
+        Ok(CrawlResult {
+            schema_version: SCHEMA_VERSION,
+            attribution: None,
+            instance: None,
+            units: None,
+            observed_at: observed_at
+                .ok_or_else(|| CrawlError::Decode("no readings in the response".into()))?,
+            // The key is the user's own and must not end up in the documents.
+            source: source::without_query(url, "serviceKey"),
+            records: Vec::new(),
+            marine: Vec::new(),
+            air,
+            regions: Vec::new(),
+            skipped,
+        })
+    }
+}
+
+/// `YYYY-MM-DD HH:MM`, where AirKorea writes midnight as `24:00` of the day
+/// before.
+fn data_time(stamp: &str) -> Option<DateTime<FixedOffset>> {
+    let (stamp, late) = match stamp.strip_suffix("24:00") {
+        Some(day) => (format!("{}00:00", day), Duration::days(1)),
+        None => (stamp.to_string(), Duration::zero()),
+    };
+    let naive = NaiveDateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M").ok()? + late;
+    naive.and_local_timezone(timezone::kst()).single()
+}
+
+fn make_record<'a>(field: &impl Fn(&str) -> &'a str) -> Result<AirRecord, String> {
+    let station = field("stationName");
+    if station.is_empty() {
+        return Err("no station name".into());
+    }
+    let mut quality = BTreeMap::new();
+    let mut read = |name: &str, key: &str, flag: &str| {
+        let value = reading(&mut quality, name, field(key));
+        // AirKorea says why a value is withheld, e.g. 통신장애.
+        if let Some(q) = quality.get_mut(name).filter(|_| !field(flag).is_empty()) {
+            q.reason = Some(field(flag).to_string());
+        }
+        value
+    };
+    let pm10 = read("pm10", "pm10Value", "pm10Flag");
+    let pm25 = read("pm25", "pm25Value", "pm25Flag");
+    Ok(AirRecord {
+        station: station.to_string(),
+        latitude: None,
+        longitude: None,
+        region: region::parse(field("sidoName"), None),
+        pm10,
+        pm25,
+        pm10_grade: field("pm10Grade").parse().ok(),
+        pm25_grade: field("pm25Grade").parse().ok(),
+        quality,
+    })
+}
+
+/// Where AirKorea's stations stand, by name.
+///
+/// Read from a saved response of `getMsrstnList` of its
+/// MsrstnInfoInqireSvc, or a JSON array of its items: `stationName`, `addr`,
+/// and the WGS84 `dmX` (latitude) and `dmY` (longitude).
+pub struct AirStations {
+    stations: HashMap<String, (f64, f64, String)>,
+}
+impl AirStations {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let body = std::fs::read_to_string(path)?;
+        let items = match serde_json::from_str::<Value>(&body)? {
+            Value::Array(items) => items,
+            _ => openapi::items(&body)?,
+        };
+        let coordinate = |v: &Value| match v {
+            Value::String(s) => s.trim().parse().ok(),
+            v => v.as_f64(),
+        };
+        let stations = items
+            .iter()
+            .filter_map(|item| {
+                let name = item["stationName"].as_str()?.trim().to_string();
+                let latitude = coordinate(&item["dmX"])?;
+                let longitude = coordinate(&item["dmY"])?;
+                let address = item["addr"].as_str().unwrap_or_default().to_string();
+                Some((name, (latitude, longitude, address)))
+            })
+            .collect();
+        Ok(AirStations { stations })
+    }
+
+    /// Add the location of every station of `air` the list knows; their
+    /// addresses narrow the region down to city and district.
+    pub fn locate(&self, air: &mut [AirRecord]) {
+        for record in air {
+            if let Some((latitude, longitude, address)) = self.stations.get(&record.station) {
+                record.latitude = Some(*latitude);
+                record.longitude = Some(*longitude);
+                record.region = region::parse(address, None).or(record.region.take());
+            }
+        }
+    }
+}
+
+/// Attach to every record with a known location the readings of the
+/// nearest station of the latest `air` crawl into `base`.
+pub fn join(records: &mut [Record], base: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let doc = archive::read_index(base)?;
+    let observed_at = doc["observed_at"]
+        .as_str()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .ok_or_else(|| format!("{} has no observation time", base.display()))?;
+    let air: Vec<AirRecord> = serde_json::from_value(doc["air"].clone()).unwrap_or_default();
+    let located: Vec<(&AirRecord, f64, f64)> = air
+        .iter()
+        .filter_map(|a| Some((a, a.latitude?, a.longitude?)))
+        .collect();
+    if located.is_empty() {
+        return Err(format!(
+            "no air-quality station under {} has known coordinates",
+            base.display()
+        )
+        .into());
+    }
+    for record in records {
+        let Some(station) = &record.station else {
+            continue;
+        };
+        record.air = located
+            .iter()
+            .map(|(a, lat, lon)| (a, station.distance_to(*lat, *lon)))
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .map(|(a, distance_km)| NearestAir {
+                station: a.station.clone(),
+                distance_km: (distance_km * 10.0).round() / 10.0,
+                observed_at,
+                pm10: a.pm10,
+                pm25: a.pm25,
+                pm10_grade: a.pm10_grade,
+                pm25_grade: a.pm25_grade,
+            });
+    }
+    Ok(())
+}
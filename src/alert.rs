@@ -0,0 +1,846 @@
+use rust_decimal::Decimal;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tracing::error;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{read_to_string, File};
+use std::io::BufReader;
+use std::path::Path;
+#[cfg(feature = "email")]
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[cfg(feature = "email")]
+use crate::archive;
+#[cfg(feature = "email")]
+use crate::email::Fields;
+use crate::notify::Chat;
+use crate::region::Region;
+use crate::sink::{Document, Filter};
+use crate::{atomic, Record};
+
+const STATE_FILE: &str = ".alerts";
+
+/// How long a rule stays quiet about a station after alerting, unless
+/// `cooldown=` says otherwise.
+const DEFAULT_COOLDOWN_MINUTES: i64 = 60;
+
+/// Minutes of observations a station that stopped reporting is remembered,
+/// a week: past the longest `offline N` or while a rule is firing on it.
+/// Then it is forgotten with what was sent about it, so that closed stations
+/// do not stay in `.alerts` for good.
+const RETENTION_MINUTES: i64 = 7 * 24 * 60;
+
+type Reading = fn(&Record) -> Option<Decimal>;
+
+/// Fields a rule can test, in the units of a metric crawl whatever `--units`
+/// the documents are written in. A station with none of them is offline.
+const FIELDS: [(&str, Reading); 15] = [
+    ("temperature", |r| r.temperature.map(|t| t.0)),
+    ("humidity", |r| r.humidity),
+    ("atmospheric", |r| r.atmospheric.map(|p| p.0)),
+    ("wind1", |r| r.wind1.velocity.map(|v| v.0)),
+    ("wind10", |r| r.wind10.velocity.map(|v| v.0)),
+    ("rain15", |r| r.rain.rain15.map(|mm| mm.0)),
+    ("rain60", |r| r.rain.rain60.map(|mm| mm.0)),
+    ("rain3h", |r| r.rain.rain3h.map(|mm| mm.0)),
+    ("rain6h", |r| r.rain.rain6h.map(|mm| mm.0)),
+    ("rain12h", |r| r.rain.rain12h.map(|mm| mm.0)),
+    ("rainday", |r| r.rain.rainday.map(|mm| mm.0)),
+    ("snow_depth", |r| r.snow_depth),
+    ("visibility", |r| r.visibility),
+    ("apparent_temperature", |r| {
+        r.derived
+            .as_ref()
+            .and_then(|d| d.apparent_temperature.map(|t| t.0))
+    }),
+    ("heat_index", |r| {
+        r.derived.as_ref().and_then(|d| d.heat_index.map(|t| t.0))
+    }),
+];
+
+/// The field of a record holding the reading of `field`, the metric
+/// `--sink-metrics` names it by.
+fn metric(field: &str) -> &str {
+    match field {
+        "rain15" | "rain60" | "rain3h" | "rain6h" | "rain12h" | "rainday" => "rain",
+        "apparent_temperature" | "heat_index" => "derived",
+        field => field,
+    }
+}
+
+/// A condition to alert on, parsed from a line of the rules file such as
+/// `rain60 > 20 region=서울 cooldown=3h`, `temperature < -10 stations=108,159`
+/// or `offline 3 stations=108`.
+pub struct Rule {
+    /// The line, which names the rule in alerts and in `.alerts`.
+    text: String,
+    field: &'static str,
+    test: Test,
+    op: Op,
+    threshold: Decimal,
+    stations: Option<BTreeSet<u32>>,
+    region: Option<String>,
+    cooldown: i64,
+}
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let text = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut rule = match words.next() {
+            // `offline N`: no record, or one without any reading, in each of
+            // the last N crawls.
+            Some("offline") => {
+                let count = words.next().unwrap_or_default();
+                let crawls = count
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("`{}` is not a count of crawls", count))?;
+                Rule {
+                    text,
+                    field: "offline",
+                    test: Test::Offline,
+                    op: Op::AtLeast,
+                    threshold: crawls.into(),
+                    stations: None,
+                    region: None,
+                    cooldown: DEFAULT_COOLDOWN_MINUTES,
+                }
+            }
+            field => {
+                let (Some(field), Some(op), Some(threshold)) = (field, words.next(), words.next())
+                else {
+                    return Err(format!("`{}` is not FIELD OP VALUE or offline N", s));
+                };
+                let (field, reading) = FIELDS
+                    .iter()
+                    .find(|(name, _)| *name == field)
+                    .copied()
+                    .ok_or_else(|| {
+                        let names: Vec<&str> = FIELDS.iter().map(|(name, _)| *name).collect();
+                        format!("`{}` is not offline or one of {}", field, names.join(", "))
+                    })?;
+                let op = match op {
+                    "<" => Op::Below,
+                    "<=" => Op::AtMost,
+                    ">" => Op::Above,
+                    ">=" => Op::AtLeast,
+                    _ => return Err(format!("`{}` is not one of <, <=, >, >=", op)),
+                };
+                let threshold = threshold
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a number", threshold))?;
+                Rule {
+                    text,
+                    field,
+                    test: Test::Reading(reading),
+                    op,
+                    threshold,
+                    stations: None,
+                    region: None,
+                    cooldown: DEFAULT_COOLDOWN_MINUTES,
+                }
+            }
+        };
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("`{}` is not key=value", word))?;
+            match key {
+                "stations" => {
+                    rule.stations = Some(
+                        value
+                            .split(',')
+                            .map(|id| {
+                                id.parse()
+                                    .map_err(|_| format!("`{}` is not a station id", id))
+                            })
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+                "region" => rule.region = Some(value.to_string()),
+                "cooldown" => {
+                    rule.cooldown = minutes(value).ok_or_else(|| {
+                        format!("`{}` is not a duration such as 30m, 3h or 1d", value)
+                    })?
+                }
+                _ => return Err(format!("unknown scope `{}`", key)),
+            }
+        }
+        Ok(rule)
+    }
+}
+impl Rule {
+    fn applies_to(&self, id: u32, region: Option<&Region>) -> bool {
+        self.stations.as_ref().is_none_or(|ids| ids.contains(&id))
+            && self
+                .region
+                .as_deref()
+                .is_none_or(|wanted| region.is_some_and(|region| region.within(wanted)))
+    }
+
+    fn holds(&self, value: Decimal) -> bool {
+        match self.op {
+            Op::Below => value < self.threshold,
+            Op::AtMost => value <= self.threshold,
+            Op::Above => value > self.threshold,
+            Op::AtLeast => value >= self.threshold,
+        }
+    }
+}
+
+/// What a rule compares with its threshold.
+enum Test {
+    Reading(Reading),
+    /// The crawls in a row a station was offline in.
+    Offline,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Below,
+    AtMost,
+    Above,
+    AtLeast,
+}
+
+/// Minutes of a duration such as `30m`, `3h` or `1d`; none for a count below
+/// one or one too large.
+fn minutes(duration: &str) -> Option<i64> {
+    let unit = duration.chars().last()?;
+    let count: i64 = duration[..duration.len() - unit.len_utf8()].parse().ok()?;
+    if count < 1 {
+        return None;
+    }
+    match unit {
+        'm' => Some(count),
+        'h' => count.checked_mul(60),
+        'd' => count.checked_mul(24 * 60),
+        _ => None,
+    }
+}
+
+/// The rules of `--alert-rules`, one per line; blank lines and `#` comments
+/// are skipped.
+pub struct Rules(Vec<Rule>);
+impl Rules {
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if !line.is_empty() {
+                rules.push(
+                    line.parse()
+                        .map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?,
+                );
+            }
+        }
+        Ok(Rules(rules))
+    }
+
+    /// The alerts `records`, observed at `minute`, raise, given what was sent
+    /// before according to `<base>/.alerts`, which is brought up to date.
+    /// With an `offline` rule, `.alerts` also keeps every station seen and
+    /// the crawls in a row it has been offline in, until it has been offline
+    /// longer than the longest such rule and `RETENTION_MINUTES`.
+    ///
+    /// A station meeting a rule is alerted on once, then again each cooldown
+    /// for as long as it keeps meeting it; one that stops gets a `resolved`
+    /// alert. A station meeting the rule again within the cooldown of its
+    /// last alert is not alerted on, so that a reading hovering about the
+    /// threshold alerts once.
+    pub fn evaluate(
+        &self,
+        base: &Path,
+        minute: i64,
+        observed_at: String,
+        records: &[Record],
+    ) -> Alerts {
+        let mut state = State::load(base);
+        state
+            .rules
+            .retain(|text, _| self.0.iter().any(|rule| rule.text == *text));
+        let offline: Vec<&Rule> = self
+            .0
+            .iter()
+            .filter(|rule| matches!(rule.test, Test::Offline))
+            .collect();
+        match offline.iter().map(|rule| rule.threshold).max() {
+            Some(longest) => {
+                state.track(minute, records);
+                state.stations.retain(|id, seen| {
+                    let kept = Decimal::from(seen.offline) < longest
+                        || seen
+                            .online_at
+                            .is_none_or(|at| minute - at <= RETENTION_MINUTES);
+                    if !kept {
+                        for sent in state.rules.values_mut() {
+                            sent.remove(id);
+                        }
+                    }
+                    kept
+                });
+            }
+            None => state.stations.clear(),
+        }
+        let mut alerts = Vec::new();
+        for rule in &self.0 {
+            let sent = state.rules.entry(rule.text.clone()).or_default();
+            // The stations the rule applies to, with what it tests of each.
+            let stations: Vec<_> = match rule.test {
+                Test::Reading(reading) => records
+                    .iter()
+                    .filter(|record| rule.applies_to(record.id, record.region.as_ref()))
+                    .map(|record| (record.id, &record.name, reading(record)))
+                    .collect(),
+                Test::Offline => state
+                    .stations
+                    .iter()
+                    .filter(|(id, seen)| rule.applies_to(**id, seen.region.as_ref()))
+                    .map(|(id, seen)| (*id, &seen.name, Some(seen.offline.into())))
+                    .collect(),
+            };
+            let reporting: BTreeSet<u32> = stations
+                .iter()
+                .filter(|(_, _, value)| value.is_some())
+                .map(|(id, _, _)| *id)
+                .collect();
+            for (id, name, value) in stations {
+                let Some(value) = value else {
+                    continue;
+                };
+                let status = match (sent.get_mut(&id), rule.holds(value)) {
+                    (None, true) => {
+                        sent.insert(
+                            id,
+                            Sent {
+                                at: minute,
+                                firing: true,
+                            },
+                        );
+                        Status::Firing
+                    }
+                    (Some(last), true) if minute - last.at >= rule.cooldown => {
+                        *last = Sent {
+                            at: minute,
+                            firing: true,
+                        };
+                        Status::Firing
+                    }
+                    (Some(last), false) if last.firing => {
+                        last.firing = false;
+                        Status::Resolved
+                    }
+                    _ => continue,
+                };
+                alerts.push(Alert {
+                    rule: rule.text.clone(),
+                    status,
+                    id,
+                    name: name.clone(),
+                    field: rule.field,
+                    value,
+                    threshold: rule.threshold,
+                    #[cfg(feature = "email")]
+                    record: serde_json::to_value(records.iter().find(|record| record.id == id))
+                        .unwrap_or_default(),
+                });
+            }
+            // A station the rule is firing on that stopped reporting is not
+            // resolved, and is forgotten after a while.
+            sent.retain(|id, last| {
+                if last.firing {
+                    reporting.contains(id) || minute - last.at <= RETENTION_MINUTES
+                } else {
+                    minute - last.at < rule.cooldown
+                }
+            });
+        }
+        if let Err(e) = state.save(base) {
+            error!(error = %e, "saving alert state failed");
+        }
+        Alerts {
+            #[cfg(feature = "email")]
+            snapshot: archive::snapshot_path(base, &observed_at),
+            observed_at,
+            alerts,
+        }
+    }
+}
+
+/// The last alert sent of each rule, by the text of the rule and station,
+/// stored in `<base>/.alerts`.
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    rules: HashMap<String, HashMap<u32, Sent>>,
+    #[serde(default)]
+    stations: HashMap<u32, Seen>,
+}
+impl State {
+    /// Count another crawl, observed at `minute`, for each station seen,
+    /// counting it offline unless it has a record with a reading in
+    /// `records`.
+    fn track(&mut self, minute: i64, records: &[Record]) {
+        for seen in self.stations.values_mut() {
+            seen.offline += 1;
+            // Stations tracked before `online_at` count from now.
+            seen.online_at.get_or_insert(minute);
+        }
+        for record in records {
+            let seen = self.stations.entry(record.id).or_insert_with(|| Seen {
+                name: String::new(),
+                region: None,
+                offline: 1,
+                online_at: Some(minute),
+            });
+            seen.name.clone_from(&record.name);
+            seen.region.clone_from(&record.region);
+            if FIELDS.iter().any(|(_, reading)| reading(record).is_some()) {
+                seen.offline = 0;
+                seen.online_at = Some(minute);
+            }
+        }
+    }
+
+    fn load(base: &Path) -> Self {
+        File::open(base.join(STATE_FILE))
+            .ok()
+            .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, base: &Path) -> std::io::Result<()> {
+        atomic::write(&base.join(STATE_FILE), &serde_json::to_vec(self)?)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sent {
+    /// Minute of the observation last alerted on as firing.
+    at: i64,
+    /// Whether it has not been resolved since.
+    firing: bool,
+}
+
+/// A station an `offline` rule may alert on, as last seen.
+#[derive(Serialize, Deserialize)]
+struct Seen {
+    name: String,
+    region: Option<Region>,
+    /// Crawls in a row without a record of the station, or with one without
+    /// any reading.
+    offline: u32,
+    /// Minute of the last observation with a reading of the station, or of
+    /// the first one without when it never had any.
+    #[serde(default)]
+    online_at: Option<i64>,
+}
+
+/// The alerts of one crawl, written to the sinks whenever there are any.
+#[derive(Serialize)]
+pub struct Alerts {
+    pub observed_at: String,
+    pub alerts: Vec<Alert>,
+    /// Where the crawl is kept with `--keep-snapshots`, for mails.
+    #[cfg(feature = "email")]
+    #[serde(skip)]
+    pub snapshot: PathBuf,
+}
+
+#[derive(Serialize)]
+pub struct Alert {
+    pub rule: String,
+    pub status: Status,
+    pub id: u32,
+    pub name: String,
+    pub field: &'static str,
+    /// The reading, in the units of a metric crawl.
+    pub value: Decimal,
+    pub threshold: Decimal,
+    /// The record of the station, for mails.
+    #[cfg(feature = "email")]
+    #[serde(skip)]
+    pub record: Value,
+}
+
+impl Alert {
+    /// Whether a sink with `filter` wants the alert; an offline station is
+    /// wanted whatever the metrics.
+    fn wanted(&self, filter: &Filter) -> bool {
+        filter.wants_station(self.id)
+            && (self.field == "offline"
+                || filter.wants_metric(self.field)
+                || filter.wants_metric(metric(self.field)))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The station meets the rule.
+    Firing,
+    /// The station met the rule when last alerted on and no longer does.
+    Resolved,
+}
+
+impl Document for Alerts {
+    const FILE: &'static str = "alerts.json";
+
+    /// Alerts go to an `alerts` table, next to the `observations` of the
+    /// crawl.
+    #[cfg(feature = "sqlite")]
+    fn to_sqlite(&self, path: &Path) -> rusqlite::Result<()> {
+        use rusqlite::params;
+        use rust_decimal::prelude::ToPrimitive;
+
+        let mut conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS alerts (observed_at TEXT, rule TEXT, status TEXT, \
+             id INTEGER, name TEXT, field TEXT, value REAL, threshold REAL)",
+        )?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare("INSERT INTO alerts VALUES (?, ?, ?, ?, ?, ?, ?, ?)")?;
+            for alert in &self.alerts {
+                insert.execute(params![
+                    self.observed_at,
+                    alert.rule,
+                    if alert.status == Status::Firing {
+                        "firing"
+                    } else {
+                        "resolved"
+                    },
+                    alert.id,
+                    alert.name,
+                    alert.field,
+                    alert.value.to_f64(),
+                    alert.threshold.to_f64(),
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// A heading, then a line on each alert.
+    fn filtered(&self, filter: &Filter) -> serde_json::Result<Option<Value>> {
+        let mut doc = serde_json::to_value(self)?;
+        if let Some(alerts) = doc["alerts"].as_array_mut() {
+            let mut wanted = self.alerts.iter().map(|alert| alert.wanted(filter));
+            alerts.retain(|_| wanted.next().unwrap_or_default());
+            if alerts.is_empty() {
+                return Ok(None);
+            }
+        }
+        Ok(Some(doc))
+    }
+
+    fn message(&self, chat: &Chat, filter: &Filter) -> Option<String> {
+        let mut alerts = self
+            .alerts
+            .iter()
+            .filter(|alert| alert.wanted(filter))
+            .peekable();
+        alerts.peek()?;
+        let mut text = chat.bold(&format!("Alerts at {}", self.observed_at));
+        for alert in alerts {
+            text.push_str(&format!(
+                "\n{} {} ({}): {} {}, `{}`",
+                if alert.status == Status::Firing {
+                    "🔴 firing"
+                } else {
+                    "🟢 resolved"
+                },
+                alert.name,
+                alert.id,
+                alert.field,
+                alert.value,
+                alert.rule
+            ));
+        }
+        Some(text)
+    }
+
+    /// A mail for each alert.
+    #[cfg(feature = "email")]
+    fn mails(&self, filter: &Filter) -> Vec<Fields> {
+        self.alerts
+            .iter()
+            .filter(|alert| alert.wanted(filter))
+            .map(|alert| {
+                vec![
+                    ("rule", alert.rule.clone()),
+                    (
+                        "status",
+                        if alert.status == Status::Firing {
+                            "firing"
+                        } else {
+                            "resolved"
+                        }
+                        .to_string(),
+                    ),
+                    ("id", alert.id.to_string()),
+                    ("name", alert.name.clone()),
+                    ("field", alert.field.to_string()),
+                    ("value", alert.value.to_string()),
+                    ("threshold", alert.threshold.to_string()),
+                    ("observed_at", self.observed_at.clone()),
+                    ("snapshot", self.snapshot.display().to_string()),
+                    (
+                        "record",
+                        serde_json::to_string_pretty(&alert.record).unwrap_or_default(),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{create_dir_all, remove_dir_all};
+    use std::path::PathBuf;
+
+    /// An empty directory for the `.alerts` of one test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "weather_crawl-alert-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn rules(text: &str) -> Rules {
+        Rules(text.lines().map(|line| line.parse().unwrap()).collect())
+    }
+
+    /// A record of station `id` with `temperature` as its only reading.
+    fn record(id: u32, temperature: Option<f64>) -> Record {
+        let wind = serde_json::json!({
+            "direction_code": null,
+            "direction_text": "Unavailable",
+            "velocity": null,
+        });
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id.to_string(),
+            "height": null,
+            "rain": { "is_raining": "Unavailable" },
+            "temperature": temperature,
+            "wind1": wind,
+            "wind10": wind,
+            "humidity": null,
+            "atmospheric": null,
+            "address": "",
+        }))
+        .unwrap()
+    }
+
+    /// The station and status of each alert `records` raise at `minute`.
+    fn evaluate(rules: &Rules, base: &Path, minute: i64, records: &[Record]) -> Vec<(u32, Status)> {
+        rules
+            .evaluate(base, minute, minute.to_string(), records)
+            .alerts
+            .iter()
+            .map(|alert| (alert.id, alert.status))
+            .collect()
+    }
+
+    #[test]
+    fn offline_after_n_crawls_and_back() {
+        let base = scratch("offline");
+        let rules = rules("offline 3");
+        let both = [record(108, Some(15.0)), record(159, Some(18.0))];
+        let seoul = [record(108, Some(15.0))];
+        assert_eq!(evaluate(&rules, &base, 0, &both), []);
+        assert_eq!(evaluate(&rules, &base, 1, &seoul), []);
+        // A record without any reading counts as offline too.
+        let blank = [record(108, Some(15.0)), record(159, None)];
+        assert_eq!(evaluate(&rules, &base, 2, &blank), []);
+        assert_eq!(evaluate(&rules, &base, 3, &seoul), [(159, Status::Firing)]);
+        assert_eq!(evaluate(&rules, &base, 4, &seoul), []);
+        assert_eq!(evaluate(&rules, &base, 5, &both), [(159, Status::Resolved)]);
+        remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn offline_stations_are_forgotten_after_a_week() {
+        let base = scratch("retention");
+        let rules = rules("offline 2");
+        let seoul = [record(108, Some(15.0))];
+        evaluate(
+            &rules,
+            &base,
+            0,
+            &[record(108, Some(15.0)), record(159, Some(18.0))],
+        );
+        evaluate(&rules, &base, 1, &seoul);
+        assert_eq!(evaluate(&rules, &base, 2, &seoul), [(159, Status::Firing)]);
+        evaluate(&rules, &base, RETENTION_MINUTES, &seoul);
+        assert!(State::load(&base).stations.contains_key(&159));
+        evaluate(&rules, &base, RETENTION_MINUTES + 1, &seoul);
+        let state = State::load(&base);
+        assert!(!state.stations.contains_key(&159));
+        assert!(!state.rules["offline 2"].contains_key(&159));
+        // Back again, it is a new station with nothing to resolve.
+        let both = [record(108, Some(15.0)), record(159, Some(18.0))];
+        assert_eq!(evaluate(&rules, &base, RETENTION_MINUTES + 2, &both), []);
+        remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn cooldown_between_alerts() {
+        let base = scratch("cooldown");
+        let rules = rules("temperature > 30 cooldown=2h");
+        let hot = [record(108, Some(31.0))];
+        let mild = [record(108, Some(25.0))];
+        assert_eq!(evaluate(&rules, &base, 0, &hot), [(108, Status::Firing)]);
+        assert_eq!(evaluate(&rules, &base, 60, &hot), []);
+        assert_eq!(evaluate(&rules, &base, 120, &hot), [(108, Status::Firing)]);
+        assert_eq!(
+            evaluate(&rules, &base, 130, &mild),
+            [(108, Status::Resolved)]
+        );
+        // Hovering about the threshold within the cooldown alerts once.
+        assert_eq!(evaluate(&rules, &base, 140, &hot), []);
+        assert_eq!(evaluate(&rules, &base, 150, &mild), []);
+        assert_eq!(evaluate(&rules, &base, 250, &hot), [(108, Status::Firing)]);
+        remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn firing_on_a_station_that_stops_reporting() {
+        let base = scratch("vanished");
+        let rules = rules("temperature > 30");
+        assert_eq!(
+            evaluate(&rules, &base, 0, &[record(108, Some(31.0))]),
+            [(108, Status::Firing)]
+        );
+        assert_eq!(evaluate(&rules, &base, 10, &[]), []);
+        assert_eq!(evaluate(&rules, &base, 20, &[record(108, None)]), []);
+        assert!(State::load(&base).rules["temperature > 30"].contains_key(&108));
+        evaluate(&rules, &base, RETENTION_MINUTES + 1, &[]);
+        assert!(!State::load(&base).rules["temperature > 30"].contains_key(&108));
+        assert_eq!(
+            evaluate(
+                &rules,
+                &base,
+                RETENTION_MINUTES + 2,
+                &[record(108, Some(31.0))]
+            ),
+            [(108, Status::Firing)]
+        );
+        remove_dir_all(base).unwrap();
+    }
+
+    fn error(line: &str) -> String {
+        line.parse::<Rule>().err().unwrap()
+    }
+
+    #[test]
+    fn rules_that_do_not_parse() {
+        assert_eq!(
+            error("temperature >"),
+            "`temperature >` is not FIELD OP VALUE or offline N"
+        );
+        assert!(error("pressure > 1000").starts_with("`pressure` is not offline or one of"));
+        assert_eq!(error("temperature ~ 30"), "`~` is not one of <, <=, >, >=");
+        assert_eq!(error("temperature > warm"), "`warm` is not a number");
+        assert_eq!(error("offline 0"), "`0` is not a count of crawls");
+        assert_eq!(error("offline"), "`` is not a count of crawls");
+        assert_eq!(
+            error("temperature > 30 region"),
+            "`region` is not key=value"
+        );
+        assert_eq!(error("temperature > 30 city=서울"), "unknown scope `city`");
+        assert_eq!(
+            error("temperature > 30 stations=108,seoul"),
+            "`seoul` is not a station id"
+        );
+    }
+
+    #[test]
+    fn cooldowns() {
+        assert_eq!(minutes("30m"), Some(30));
+        assert_eq!(minutes("3h"), Some(180));
+        assert_eq!(minutes("1d"), Some(1440));
+        for refused in [
+            "-5m",
+            "0h",
+            "0m",
+            "99999999999999999d",
+            "9223372036854775807h",
+            "1w",
+            "m",
+            "",
+            "3시",
+        ] {
+            assert_eq!(minutes(refused), None, "{}", refused);
+        }
+        assert_eq!(
+            "temperature > 30 cooldown=2h"
+                .parse::<Rule>()
+                .unwrap()
+                .cooldown,
+            120
+        );
+        assert_eq!(
+            error("temperature > 30 cooldown=0h"),
+            "`0h` is not a duration such as 30m, 3h or 1d"
+        );
+    }
+
+    #[test]
+    fn rules_scoped_to_stations_and_regions() {
+        let region = |code: &str| Region {
+            province: String::new(),
+            province_code: code.to_string(),
+            city: None,
+            district: None,
+            code: None,
+        };
+        let stations: Rule = "temperature > 30 stations=108,159".parse().unwrap();
+        assert!(stations.applies_to(108, None));
+        assert!(!stations.applies_to(400, None));
+        let seoul: Rule = "temperature > 30 region=11".parse().unwrap();
+        assert!(seoul.applies_to(108, Some(&region("11"))));
+        assert!(!seoul.applies_to(159, Some(&region("26"))));
+        assert!(!seoul.applies_to(184, None));
+    }
+
+    #[test]
+    fn alert_then_quiet_then_resolved() {
+        let base = scratch("sequence");
+        let rules = rules("temperature < -10");
+        let at = |t: f64| [record(108, Some(t))];
+        // At the threshold the rule does not hold.
+        assert_eq!(evaluate(&rules, &base, 0, &at(-10.0)), []);
+        assert_eq!(
+            evaluate(&rules, &base, 10, &at(-10.5)),
+            [(108, Status::Firing)]
+        );
+        assert_eq!(evaluate(&rules, &base, 20, &at(-12.0)), []);
+        assert_eq!(
+            evaluate(&rules, &base, 30, &at(-9.0)),
+            [(108, Status::Resolved)]
+        );
+        assert_eq!(evaluate(&rules, &base, 40, &at(-8.0)), []);
+        // Once the cooldown of the last alert is over, it alerts again.
+        assert_eq!(
+            evaluate(&rules, &base, 70, &at(-11.0)),
+            [(108, Status::Firing)]
+        );
+        remove_dir_all(base).unwrap();
+    }
+}
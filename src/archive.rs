@@ -0,0 +1,349 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fs::{read, read_dir, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "cli")]
+use crate::compact;
+
+pub const SNAPSHOT_EXT: &str = "json";
+pub const INDEX_FILE: &str = "index.json";
+
+#[derive(Deserialize)]
+struct Published {
+    observed_at: String,
+}
+
+/// `observed_at` of the document currently published as `index.json`.
+pub fn published_observed_at(base: &Path) -> Option<String> {
+    let file = File::open(base.join(INDEX_FILE)).ok()?;
+    let published: Published = serde_json::from_reader(BufReader::new(file)).ok()?;
+    Some(published.observed_at)
+}
+
+/// The document currently published as `index.json`.
+pub fn read_index(base: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let path = base.join(INDEX_FILE);
+    let file = File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Whether `candidate` was observed strictly before `current`.
+pub fn is_older(candidate: &str, current: &str) -> bool {
+    match (minute_of(candidate), minute_of(current)) {
+        (Some(a), Some(b)) => a < b,
+        _ => false,
+    }
+}
+
+/// How `index.json` is published.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum IndexMode {
+    /// A regular file holding the latest result.
+    Copy,
+    /// A symlink swapped to the latest snapshot, which is always kept.
+    Symlink,
+}
+impl IndexMode {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "copy" => Some(IndexMode::Copy),
+            "symlink" => Some(IndexMode::Symlink),
+            _ => None,
+        }
+    }
+}
+
+/// A retained crawl result, `<base>/<observed_at>.json` or one of the
+/// snapshots of a daily bundle.
+pub struct Snapshot {
+    pub observed_at: String,
+    /// The snapshot's file, or the bundle holding it.
+    pub path: PathBuf,
+    pub bundled: bool,
+}
+impl Snapshot {
+    /// The document of the snapshot.
+    pub fn read(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut body = Vec::new();
+        read_each([self], &mut |_, document| {
+            body = document;
+            Ok(())
+        })?;
+        Ok(body)
+    }
+}
+
+/// Call `f` with the document of each of `snapshots`, reading each bundle
+/// among them once.
+pub fn read_each<'a>(
+    snapshots: impl IntoIterator<Item = &'a Snapshot>,
+    f: &mut dyn FnMut(&'a Snapshot, Vec<u8>) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut bundled: BTreeMap<&Path, BTreeMap<&str, &Snapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        if snapshot.bundled {
+            bundled
+                .entry(&snapshot.path)
+                .or_default()
+                .insert(&snapshot.observed_at, snapshot);
+        } else {
+            let body =
+                read(&snapshot.path).map_err(|e| format!("{}: {}", snapshot.path.display(), e))?;
+            f(snapshot, body)?;
+        }
+    }
+    #[cfg(feature = "cli")]
+    for (path, mut wanted) in bundled {
+        compact::each_bundled(path, &mut |document| {
+            if let Some(snapshot) = document["observed_at"]
+                .as_str()
+                .and_then(|stamp| wanted.remove(stamp))
+            {
+                f(snapshot, serde_json::to_vec(&document)?)?;
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some(observed_at) = wanted.keys().next() {
+            return Err(format!("{} holds no snapshot of {}", path.display(), observed_at).into());
+        }
+    }
+    Ok(())
+}
+
+pub fn snapshot_path(base: &Path, observed_at: &str) -> PathBuf {
+    base.join(format!("{}.{}", observed_at, SNAPSHOT_EXT))
+}
+
+/// Every snapshot under `base`, oldest first.
+pub fn list(base: &Path) -> std::io::Result<Vec<Snapshot>> {
+    let mut snapshots = Vec::new();
+    for entry in read_dir(base)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(SNAPSHOT_EXT) {
+            continue;
+        }
+        let observed_at = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(stem) if minute_of(stem).is_some() => stem.to_string(),
+            _ => continue,
+        };
+        snapshots.push(Snapshot {
+            observed_at,
+            path,
+            bundled: false,
+        });
+    }
+    snapshots.sort_by_key(|s| minute_of(&s.observed_at));
+    Ok(snapshots)
+}
+
+/// Every snapshot under `base`, those of the daily bundles of `compact`
+/// included, oldest first.
+pub fn retained(base: &Path) -> Result<Vec<Snapshot>, Box<dyn std::error::Error>> {
+    let mut snapshots = list(base)?;
+    #[cfg(feature = "cli")]
+    for bundle in compact::bundles(base)? {
+        compact::each_bundled(&bundle.path, &mut |snapshot| {
+            if let Some(observed_at) = snapshot["observed_at"]
+                .as_str()
+                .filter(|stamp| minute_of(stamp).is_some())
+            {
+                snapshots.push(Snapshot {
+                    observed_at: observed_at.to_string(),
+                    path: bundle.path.clone(),
+                    bundled: true,
+                });
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("{}: {}", bundle.path.display(), e))?;
+    }
+    snapshots.sort_by_key(|s| minute_of(&s.observed_at));
+    Ok(snapshots)
+}
+
+/// Call `f` with every snapshot under `base` and its document, the loose ones
+/// first, reading each bundle once.
+pub fn each_retained(
+    base: &Path,
+    f: &mut dyn FnMut(Snapshot, Vec<u8>) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for snapshot in list(base)? {
+        let body =
+            read(&snapshot.path).map_err(|e| format!("{}: {}", snapshot.path.display(), e))?;
+        f(snapshot, body)?;
+    }
+    #[cfg(feature = "cli")]
+    for bundle in compact::bundles(base)? {
+        compact::each_bundled(&bundle.path, &mut |document| {
+            if let Some(observed_at) = document["observed_at"]
+                .as_str()
+                .filter(|stamp| minute_of(stamp).is_some())
+            {
+                let snapshot = Snapshot {
+                    observed_at: observed_at.to_string(),
+                    path: bundle.path.clone(),
+                    bundled: true,
+                };
+                f(snapshot, serde_json::to_vec(&document)?)?;
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("{}: {}", bundle.path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// A snapshot and its document.
+pub type Found = (Snapshot, Vec<u8>);
+
+/// The snapshot observed closest to `instant`, bundled or not, preferring the
+/// earlier one on ties, with its document.
+///
+/// Bundles are picked by the day in their name, so only those that can hold
+/// a closer snapshot are decompressed.
+pub fn closest(base: &Path, instant: &str) -> Result<Option<Found>, Box<dyn std::error::Error>> {
+    let target = match minute_of(instant) {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+    // Distance first, then the minute itself, so the earlier one wins ties.
+    let rank = |observed_at: &str| {
+        let minute = minute_of(observed_at).unwrap();
+        ((minute - target).abs(), minute)
+    };
+    #[cfg_attr(not(feature = "cli"), allow(unused_mut))]
+    let mut best = list(base)?
+        .into_iter()
+        .map(|snapshot| (rank(&snapshot.observed_at), snapshot, None))
+        .min_by_key(|(rank, ..)| *rank);
+    #[cfg(feature = "cli")]
+    {
+        let mut bundles: Vec<(i64, compact::Bundle)> = compact::bundles(base)?
+            .into_iter()
+            .map(|bundle| {
+                // A bundle holds one KST day; one not named after a day is read anyway.
+                let distance = minute_of(&format!("{}T00:00", bundle.day)).map_or(0, |start| {
+                    (start - target).max(target - start - 1439).max(0)
+                });
+                (distance, bundle)
+            })
+            .collect();
+        bundles.sort_by_key(|(distance, _)| *distance);
+        for (distance, bundle) in bundles {
+            if best
+                .as_ref()
+                .is_some_and(|((kept, _), ..)| distance > *kept)
+            {
+                break;
+            }
+            compact::each_bundled(&bundle.path, &mut |document| {
+                let Some(observed_at) = document["observed_at"]
+                    .as_str()
+                    .filter(|stamp| minute_of(stamp).is_some())
+                else {
+                    return Ok(());
+                };
+                let rank = rank(observed_at);
+                if best.as_ref().is_none_or(|(kept, ..)| rank < *kept) {
+                    let snapshot = Snapshot {
+                        observed_at: observed_at.to_string(),
+                        path: bundle.path.clone(),
+                        bundled: true,
+                    };
+                    best = Some((rank, snapshot, Some(serde_json::to_vec(&document)?)));
+                }
+                Ok(())
+            })
+            .map_err(|e| format!("{}: {}", bundle.path.display(), e))?;
+        }
+    }
+    match best {
+        None => Ok(None),
+        Some((_, snapshot, Some(body))) => Ok(Some((snapshot, body))),
+        Some((_, snapshot, None)) => {
+            let body = snapshot.read()?;
+            Ok(Some((snapshot, body)))
+        }
+    }
+}
+
+/// Minutes since the Unix epoch, on the KST wall clock, of a
+/// `YYYY-MM-DDTHH:MM[:SS][offset]` timestamp.
+///
+/// Timestamps without an offset are taken to be KST already. Seconds are
+/// ignored; observations are made on the minute.
+pub fn minute_of(stamp: &str) -> Option<i64> {
+    if let Some(instant) = DateTime::parse_from_rfc3339(stamp).ok().or_else(|| {
+        OFFSET_FORMATS
+            .iter()
+            .find_map(|format| DateTime::parse_from_str(stamp, format).ok())
+    }) {
+        return Some(minute_at(&instant));
+    }
+    let wall = NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(stamp, format).ok())?;
+    Some(wall.and_utc().timestamp().div_euclid(60))
+}
+
+/// Forms `minute_of` reads besides RFC 3339, such as `2024-05-01T12:34+09:00`
+/// and `2024-05-01T12:34:56+0900`.
+const OFFSET_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M%#z", "%Y-%m-%dT%H:%M:%S%.f%#z"];
+
+/// Forms of KST wall-clock times.
+const NAIVE_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S%.f"];
+
+const KST_MINUTES: i64 = 9 * 60;
+
+/// `minute_of` an instant.
+pub fn minute_at(instant: &DateTime<FixedOffset>) -> i64 {
+    instant.timestamp().div_euclid(60) + KST_MINUTES
+}
+
+/// KST `YYYY-MM-DDTHH:MM` of a minute counted by `minute_of`.
+pub fn format_minute(minute: i64) -> String {
+    DateTime::from_timestamp(minute * 60, 0)
+        .map(|wall| wall.format("%Y-%m-%dT%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minutes_of_timestamps() {
+        let kst = minute_of("2024-05-01T12:34").unwrap();
+        assert_eq!(format_minute(kst), "2024-05-01T12:34");
+        for stamp in [
+            "2024-05-01T12:34:00+09:00",
+            "2024-05-01T12:34:59+09:00",
+            "2024-05-01T12:34+09:00",
+            "2024-05-01T12:34+0900",
+            "2024-05-01T03:34:00Z",
+            "2024-05-01T03:34Z",
+            "2024-04-30T22:34:00-05:00",
+            "2024-05-01T12:34:56",
+        ] {
+            assert_eq!(minute_of(stamp), Some(kst), "{}", stamp);
+        }
+        assert_eq!(minute_of("2024-05-01"), None);
+        assert_eq!(minute_of("2024-02-30T12:34"), None);
+        assert_eq!(minute_of("index"), None);
+    }
+
+    #[test]
+    fn minutes_format_across_days() {
+        assert_eq!(format_minute(0), "1970-01-01T00:00");
+        assert_eq!(format_minute(-1), "1969-12-31T23:59");
+        let leap = minute_of("2024-02-29T23:59").unwrap();
+        assert_eq!(format_minute(leap + 1), "2024-03-01T00:00");
+    }
+}
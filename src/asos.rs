@@ -0,0 +1,241 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+
+use scraper::Html;
+
+use std::collections::BTreeMap;
+
+use crate::columns::{labelled_rows, LabelledRow};
+use crate::error::CrawlError;
+use crate::pipeline::Outcome;
+use crate::quantity::{Celsius, HectoPascals, MetersPerSecond, Millimeters};
+use crate::source::{with_query, Source, MAINTENANCE_MARKERS};
+use crate::{
+    archive, reading, timezone, CrawlResult, Rain, RainStatus, Record, SkippedRow, Wind,
+    WindDirectionText, SCHEMA_VERSION,
+};
+
+pub const ASOS_URL: &str = "https://www.kma.go.kr/weather/observation/currentweather.jsp";
+
+/// Compass points clockwise from north as the page writes them.
+const POINTS: [&str; 16] = [
+    "북",
+    "북북동",
+    "북동",
+    "동북동",
+    "동",
+    "동남동",
+    "남동",
+    "남남동",
+    "남",
+    "남남서",
+    "남서",
+    "서남서",
+    "서",
+    "서북서",
+    "북서",
+    "북북서",
+];
+
+/// Columns read from the table, found by their header labels.
+#[derive(Clone, Copy, PartialEq)]
+enum Column {
+    Name,
+    Visibility,
+    CloudCover,
+    Temperature,
+    RainDay,
+    Humidity,
+    WindDirection,
+    WindVelocity,
+    Atmospheric,
+    PressureSeaLevel,
+    Sunshine,
+    SnowDepth,
+}
+
+/// The column a header label belongs to. Labels of grouped columns are
+/// joined, e.g. `날씨시정km` or `기온현재기온`.
+fn column_of(label: &str) -> Option<Column> {
+    let column = if label.contains("지점") {
+        Column::Name
+    } else if label.contains("시정") {
+        Column::Visibility
+    } else if label.contains("운량") && !label.contains("중하") {
+        Column::CloudCover
+    } else if label.contains("현재기온") {
+        Column::Temperature
+    } else if label.contains("일강수") {
+        Column::RainDay
+    } else if label.contains("습도") {
+        Column::Humidity
+    } else if label.contains("풍향") {
+        Column::WindDirection
+    } else if label.contains("풍속") {
+        Column::WindVelocity
+    } else if label.contains("현지기압") {
+        Column::Atmospheric
+    } else if label.contains("해면기압") {
+        Column::PressureSeaLevel
+    } else if label.contains("일조") {
+        Column::Sunshine
+    } else if label.contains("적설") {
+        Column::SnowDepth
+    } else {
+        return None;
+    };
+    Some(column)
+}
+
+/// Hourly observations of the ASOS (종관) stations, `currentweather.jsp`.
+///
+/// Besides what AWS reports, ASOS stations observe visibility, cloud cover,
+/// sunshine and sea-level pressure.
+pub struct AsosHourly;
+impl Source for AsosHourly {
+    fn name(&self) -> &'static str {
+        "asos-hourly"
+    }
+
+    fn default_url(&self) -> &'static str {
+        ASOS_URL
+    }
+
+    /// The hour `minute` falls in, e.g. `tm=2024.05.01.12:00`.
+    fn timed_url(&self, url: &str, minute: i64) -> Option<String> {
+        let stamp = archive::format_minute(minute);
+        let tm = format!("{}.{}:00", stamp[..10].replace('-', "."), &stamp[11..13]);
+        Some(with_query(url, &[("tm", &tm)]))
+    }
+
+    fn problem(&self, html: &str) -> Option<Outcome> {
+        problem(html)
+    }
+
+    fn parse(&self, url: &str, html: &str) -> Result<CrawlResult, CrawlError> {
+        let document = Html::parse_document(html);
+        let observed_at = observed_at(&document)
+            .ok_or_else(|| CrawlError::Decode("no observation time on the page".into()))?;
+        let mut records = Vec::new();
+        let mut skipped = Vec::new();
+        for row in labelled_rows(&document, Column::Name, column_of) {
+            match make_record(&row) {
+                Ok(record) => records.push(record),
+                Err(reason) => skipped.push(SkippedRow {
+                    reason,
+                    cells: row.cells,
+                }),
+            }
+        }
+        Ok(CrawlResult {
+            schema_version: SCHEMA_VERSION,
+            attribution: None,
+            instance: None,
+            units: None,
+            observed_at,
+            source: url.to_owned(),
+            records,
+            marine: Vec::new(),
+            air: Vec::new(),
+            regions: Vec::new(),
+            skipped,
+        })
+    }
+}
+
+/// Why a page of hourly tables has none: no time means no table.
+pub fn problem(html: &str) -> Option<Outcome> {
+    let document = Html::parse_document(html);
+    if observed_at(&document).is_some() {
+        return None;
+    }
+    let text: String = document.root_element().text().collect();
+    if MAINTENANCE_MARKERS.iter().any(|m| text.contains(m)) {
+        Some(Outcome::Down("KMA maintenance notice".into()))
+    } else {
+        Some(Outcome::Retry("no observation time on page".into()))
+    }
+}
+
+/// A compass point written in Korean, `정온` for calm.
+pub fn compass(text: &str) -> WindDirectionText {
+    match text {
+        "정온" => WindDirectionText::No,
+        text => POINTS.iter().position(|p| *p == text).map_or(
+            WindDirectionText::Unavailable,
+            WindDirectionText::from_point,
+        ),
+    }
+}
+
+/// The first `YYYY.MM.DD.HH:MM` on the page, the hour the table shows.
+pub fn observed_at(document: &Html) -> Option<DateTime<FixedOffset>> {
+    let text: String = document.root_element().text().collect();
+    text.char_indices()
+        .filter(|(_, c)| c.is_ascii_digit())
+        .find_map(|(i, _)| {
+            let naive = NaiveDateTime::parse_from_str(text.get(i..i + 16)?, "%Y.%m.%d.%H:%M");
+            naive.ok()?.and_local_timezone(timezone::kst()).single()
+        })
+}
+
+fn make_record(row: &LabelledRow<Column>) -> Result<Record, String> {
+    let id = row.station.ok_or("no station number in the row")?;
+    let cell = |wanted: Column| row.get(wanted).unwrap_or_default();
+    let mut quality = BTreeMap::new();
+    // Not every page has every column; only those it has can be missing.
+    let mut read = |name: &str, wanted: Column| {
+        row.get(wanted)
+            .and_then(|text| reading(&mut quality, name, text))
+    };
+    let temperature = read("temperature", Column::Temperature).map(Celsius);
+    let rainday = read("rainday", Column::RainDay).map(Millimeters);
+    let humidity = read("humidity", Column::Humidity);
+    let velocity = read("wind10", Column::WindVelocity).map(MetersPerSecond);
+    let atmospheric = read("atmospheric", Column::Atmospheric).map(HectoPascals);
+    let visibility = read("visibility", Column::Visibility);
+    let cloud_cover = read("cloud_cover", Column::CloudCover);
+    let sunshine = read("sunshine", Column::Sunshine);
+    let pressure_sea_level = read("pressure_sea_level", Column::PressureSeaLevel).map(HectoPascals);
+    // Blank without snow on the ground.
+    let snow_depth = row
+        .get(Column::SnowDepth)
+        .filter(|text| !text.is_empty())
+        .and_then(|text| reading(&mut quality, "snow_depth", text));
+    let direction = compass(cell(Column::WindDirection));
+    Ok(Record {
+        id,
+        name: cell(Column::Name).to_string(),
+        name_en: None,
+        station: None,
+        height: None,
+        rain: Rain {
+            is_raining: RainStatus::Unknown,
+            rain15: None,
+            rain60: None,
+            rain3h: None,
+            rain6h: None,
+            rain12h: None,
+            rainday,
+            intensity: None,
+        },
+        temperature,
+        wind1: Wind::new(None, WindDirectionText::Unavailable, None),
+        wind10: Wind::new(None, direction, velocity),
+        humidity,
+        atmospheric,
+        sunshine,
+        solar_radiation: None,
+        snow_depth,
+        visibility,
+        cloud_cover,
+        pressure_sea_level,
+        address: String::new(),
+        region: None,
+        spatial_flags: Vec::new(),
+        quality,
+        derived: None,
+        trend: None,
+        astro: None,
+        air: None,
+    })
+}
@@ -0,0 +1,180 @@
+use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone, Utc};
+
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::PI;
+
+use crate::stations::Station;
+use crate::timezone;
+
+/// Julian date of 2000-01-01 12:00 UTC.
+const J2000: f64 = 2451545.0;
+/// Julian date of the Unix epoch.
+const UNIX_EPOCH: f64 = 2440587.5;
+/// Obliquity of the ecliptic in degrees.
+const OBLIQUITY: f64 = 23.4397;
+/// A new moon, 2000-01-06 18:14 UTC, as a Julian date.
+const NEW_MOON: f64 = 2451550.26;
+/// Mean length of the lunar month in days.
+const SYNODIC_MONTH: f64 = 29.530588853;
+/// Altitude of the sun's center at sunrise and sunset, allowing for
+/// refraction and its radius.
+const SUNRISE_ALTITUDE: f64 = -0.833;
+/// Altitude of the sun's center at the ends of civil twilight.
+const CIVIL_ALTITUDE: f64 = -6.0;
+
+/// The sun and moon at a station at the time of the observation, computed
+/// rather than fetched; times are good to about a minute.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Astro {
+    /// Start of civil twilight on the day (KST) of the observation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub civil_dawn: Option<DateTime<FixedOffset>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunrise: Option<DateTime<FixedOffset>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<DateTime<FixedOffset>>,
+    /// End of civil twilight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub civil_dusk: Option<DateTime<FixedOffset>>,
+    /// Altitude of the sun's center above the horizon in degrees, without
+    /// refraction.
+    pub sun_altitude: Decimal,
+    pub daylight: Daylight,
+    /// Age of the moon as a fraction of the lunar month: 0 at new moon, 0.5
+    /// at full moon.
+    pub moon_phase: Decimal,
+    /// Fraction of the moon's disc that is lit.
+    pub moon_illumination: Decimal,
+    pub moon: MoonPhase,
+}
+
+/// How light it is outside by the sun's altitude.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum Daylight {
+    /// Between sunrise and sunset.
+    Day,
+    /// Between sunset and the end of civil twilight, or the reverse in the
+    /// morning; outdoor work is still possible without lights.
+    CivilTwilight,
+    Night,
+}
+
+/// The eight named phases of the moon.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+impl MoonPhase {
+    /// The phase a fraction of the lunar month falls in, each centered on
+    /// its eighth.
+    fn of(phase: f64) -> Self {
+        match ((phase * 8.0).round() as usize) % 8 {
+            0 => MoonPhase::New,
+            1 => MoonPhase::WaxingCrescent,
+            2 => MoonPhase::FirstQuarter,
+            3 => MoonPhase::WaxingGibbous,
+            4 => MoonPhase::Full,
+            5 => MoonPhase::WaningGibbous,
+            6 => MoonPhase::LastQuarter,
+            _ => MoonPhase::WaningCrescent,
+        }
+    }
+}
+
+/// The sun and moon at `station` at `observed_at`, with times in the offset
+/// of `observed_at`.
+pub fn astro(station: &Station, observed_at: &DateTime<FixedOffset>) -> Option<Astro> {
+    let (lat, lon) = (station.latitude, station.longitude);
+    let jd = julian(observed_at.with_timezone(&Utc));
+    let day = observed_at.with_timezone(&timezone::kst()).date_naive();
+    let noon = julian(Utc.from_utc_datetime(&day.and_time(NaiveTime::from_hms_opt(12, 0, 0)?)));
+    let (rise, set) = crossings(noon, lat, lon, SUNRISE_ALTITUDE).unzip();
+    let (dawn, dusk) = crossings(noon, lat, lon, CIVIL_ALTITUDE).unzip();
+    let local = |jd: Option<f64>| jd.and_then(|jd| instant(jd, observed_at.offset()));
+    let altitude = sun_altitude(jd, lat, lon);
+    let phase = ((jd - NEW_MOON) / SYNODIC_MONTH).rem_euclid(1.0);
+    Some(Astro {
+        civil_dawn: local(dawn),
+        sunrise: local(rise),
+        sunset: local(set),
+        civil_dusk: local(dusk),
+        sun_altitude: Decimal::from_f64(altitude)?.round_dp(1),
+        daylight: if altitude >= SUNRISE_ALTITUDE {
+            Daylight::Day
+        } else if altitude >= CIVIL_ALTITUDE {
+            Daylight::CivilTwilight
+        } else {
+            Daylight::Night
+        },
+        moon_phase: Decimal::from_f64(phase)?.round_dp(2),
+        moon_illumination: Decimal::from_f64((1.0 - (2.0 * PI * phase).cos()) / 2.0)?.round_dp(2),
+        moon: MoonPhase::of(phase),
+    })
+}
+
+fn julian(at: DateTime<Utc>) -> f64 {
+    at.timestamp() as f64 / 86400.0 + UNIX_EPOCH
+}
+
+/// The instant of a Julian date to the minute.
+fn instant(jd: f64, offset: &FixedOffset) -> Option<DateTime<FixedOffset>> {
+    let seconds = ((jd - UNIX_EPOCH) * 86400.0 / 60.0).round() as i64 * 60;
+    Some(DateTime::from_timestamp(seconds, 0)?.with_timezone(offset))
+}
+
+/// Mean anomaly and ecliptic longitude of the sun in radians, `d` days
+/// after J2000.
+fn sun_longitude(d: f64) -> (f64, f64) {
+    let m = (357.5291 + 0.98560028 * d).to_radians();
+    let c = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let lambda = (m.to_degrees() + c + 180.0 + 102.9372).to_radians();
+    (m, lambda)
+}
+
+fn declination(lambda: f64) -> f64 {
+    (lambda.sin() * OBLIQUITY.to_radians().sin()).asin()
+}
+
+/// When the sun's center passes `altitude` rising and setting on the day
+/// whose noon UTC is `noon`, or `None` when it stays above or below it.
+fn crossings(noon: f64, lat: f64, lon: f64, altitude: f64) -> Option<(f64, f64)> {
+    // Solar noon nearest the day, by the sunrise equation.
+    let n = (noon - J2000 + 0.0008).round();
+    let mean_noon = n - lon / 360.0;
+    let (m, lambda) = sun_longitude(mean_noon);
+    let transit = J2000 + mean_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * lambda).sin();
+    let delta = declination(lambda);
+    let phi = lat.to_radians();
+    let cos_omega =
+        (altitude.to_radians().sin() - phi.sin() * delta.sin()) / (phi.cos() * delta.cos());
+    if !(-1.0..=1.0).contains(&cos_omega) {
+        return None;
+    }
+    let half_day = cos_omega.acos().to_degrees() / 360.0;
+    Some((transit - half_day, transit + half_day))
+}
+
+/// Altitude of the sun's center in degrees at Julian date `jd`.
+fn sun_altitude(jd: f64, lat: f64, lon: f64) -> f64 {
+    let d = jd - J2000;
+    let (_, lambda) = sun_longitude(d);
+    let delta = declination(lambda);
+    let alpha = (OBLIQUITY.to_radians().cos() * lambda.sin()).atan2(lambda.cos());
+    let sidereal = (280.1470 + 360.9856235 * d + lon).to_radians();
+    let hour_angle = sidereal - alpha;
+    let phi = lat.to_radians();
+    (phi.sin() * delta.sin() + phi.cos() * delta.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
@@ -0,0 +1,50 @@
+use std::fs::{remove_file, rename, File};
+use std::io::Write;
+use std::path::Path;
+
+/// Replace `path` with `contents` so that readers only ever see the old or
+/// the new file, and the swap survives a crash once this returns.
+///
+/// The bytes go to `<path>.tmp` first, which is fsynced and renamed over
+/// `path`; the directory is fsynced last so the rename itself is durable.
+pub fn write(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    rename(&tmp, path)?;
+    sync_parent(path)
+}
+
+/// Point `link` at `target` (relative to the link's directory), replacing
+/// whatever `link` was, without a moment where it does not exist.
+pub fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    let mut tmp = link.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let _ = remove_file(&tmp);
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, &tmp)?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(target, &tmp)?;
+    rename(&tmp, link)?;
+    sync_parent(link)
+}
+
+fn sync_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => sync_dir(dir),
+        _ => sync_dir(Path::new(".")),
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing here; NTFS journals the rename.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
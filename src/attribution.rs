@@ -0,0 +1,38 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "fetch")]
+use crate::http::default_user_agent;
+
+pub const SOURCE: &str = "Korea Meteorological Administration (KMA)";
+pub const LICENSE: &str =
+    "Korea Open Government License Type 1 (KOGL-1): source attribution required";
+
+/// Who the data comes from and under which terms it may be redistributed.
+///
+/// Embedded in every written document so mirrors carry the notice along.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Attribution {
+    pub source: String,
+    pub license: String,
+    /// Page the observations were retrieved from.
+    pub url: String,
+    pub crawler: String,
+}
+#[cfg(feature = "fetch")]
+impl Attribution {
+    pub fn new(source: &str, license: &str, url: &str) -> Self {
+        Attribution {
+            source: source.into(),
+            license: license.into(),
+            url: url.into(),
+            crawler: default_user_agent(),
+        }
+    }
+
+    /// The attribution of a crawl of `url` that carries none, as written
+    /// before documents had one.
+    pub fn default_for(url: &str) -> Self {
+        Attribution::new(SOURCE, LICENSE, url)
+    }
+}
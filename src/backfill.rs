@@ -0,0 +1,124 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use reqwest::Client;
+
+use tracing::{info, info_span, warn, Instrument};
+
+use std::fs::create_dir_all;
+use std::time::Duration;
+
+use crate::pipeline::{self, Fetched, Outcome, Settings};
+use crate::state::State;
+use crate::stats::CrawlStats;
+use crate::{archive, gaps, lock, logging};
+
+pub fn command() -> Command {
+    Command::new("backfill")
+        .about("fetch past observations from KMA's time-parameterized page into snapshots")
+        .args(crate::cli::crawl_args())
+        .arg(arg!(--from <TIME> "first observation to fetch, e.g. 2024-05-01T00:00").required(true))
+        .arg(arg!(--to <TIME> "last observation to fetch").required(true))
+        .arg(
+            arg!(--step <MINUTES> "minutes between fetched observations")
+                .value_parser(value_parser!(i64).range(1..))
+                .default_value("1"),
+        )
+        .arg(
+            arg!(--interval <SECONDS> "pause between requests to KMA")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("2"),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let bound = |name: &str| {
+        let time = matches.get_one::<String>(name).unwrap();
+        archive::minute_of(time).ok_or_else(|| format!("`{}` is not a YYYY-MM-DDTHH:MM time", time))
+    };
+    let (from, to) = (bound("from")?, bound("to")?);
+    if to < from {
+        return Err("--to is before --from".into());
+    }
+    let step = *matches.get_one::<i64>("step").unwrap();
+    let mut settings = crate::cli::settings_from(matches)?;
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let client = crate::cli::client_from(matches)?;
+    let urls = crate::cli::urls_from(matches, settings.source);
+    let lock_wait = Duration::from_secs(*matches.get_one::<u64>("lock-wait").unwrap());
+    let mut pace = tokio::time::interval(Duration::from_secs(
+        *matches.get_one::<u64>("interval").unwrap(),
+    ));
+
+    create_dir_all(&settings.base)?;
+    let archived = gaps::observed_minutes(&settings.base)?;
+    let (mut written, mut present, mut failed) = (0, 0, 0);
+    for minute in (from..=to).step_by(step as usize) {
+        if archived.contains(&minute) {
+            present += 1;
+            continue;
+        }
+        settings.backfill = Some(minute);
+        let span = info_span!("backfill", observed_at = %archive::format_minute(minute));
+        if backfill_minute(&client, &urls, &settings, lock_wait, &mut pace)
+            .instrument(span)
+            .await?
+        {
+            written += 1;
+        } else {
+            failed += 1;
+        }
+    }
+    info!(written, present, failed, "backfill finished");
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} observations could not be backfilled",
+            failed,
+            written + present + failed
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Fetch the page of `settings.backfill` from each URL in turn until one is
+/// written. Returns whether any was.
+async fn backfill_minute(
+    client: &Client,
+    urls: &[String],
+    settings: &Settings,
+    lock_wait: Duration,
+    pace: &mut tokio::time::Interval,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let minute = settings.backfill.unwrap();
+    for url in urls {
+        let url = settings
+            .source
+            .timed_url(url, minute)
+            .ok_or_else(|| format!("{} has no past pages to backfill", settings.source.name()))?;
+        pace.tick().await;
+        let fetched = pipeline::fetch(client, &url, &State::default(), settings.fetch_options())
+            .instrument(info_span!("fetch", %url))
+            .await?;
+        let page = match fetched {
+            Fetched::Page(page) => page,
+            Fetched::Unavailable(reason) => {
+                warn!(%url, %reason, "request failed");
+                continue;
+            }
+            Fetched::NotModified => continue,
+        };
+        // Hold the lock only while writing, so live crawls keep running.
+        let _lock = lock::acquire(&settings.base, lock_wait).await?;
+        match pipeline::process_page(settings, &page, None, &mut CrawlStats::default()).await? {
+            Outcome::Done(_) | Outcome::Unchanged => return Ok(true),
+            Outcome::Down(reason) | Outcome::Retry(reason) => {
+                warn!(%url, %reason, "unusable page");
+            }
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,254 @@
+use clap::parser::ValueSource;
+use clap::{arg, value_parser, ArgAction, ArgMatches, Command};
+
+#[cfg(feature = "serve")]
+use futures_util::StreamExt;
+
+use reqwest::Client;
+
+#[cfg(feature = "serve")]
+use tokio::sync::watch;
+#[cfg(feature = "serve")]
+use tokio_tungstenite::tungstenite::Message;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::archive;
+
+pub fn command() -> Command {
+    let command = Command::new("bench-serve")
+        .about("drive synthetic client load against HTTP endpoints and report latency")
+        .arg(arg!(<target> "base URL of the deployment, e.g. http://localhost:8080"))
+        .arg(
+            arg!(--path <PATH> "endpoint path to request, rotated per request; repeatable")
+                .action(ArgAction::Append)
+                .default_value("/index.json"),
+        )
+        .arg(
+            arg!(--replay <BASE> "request /as-of/<time> of each snapshot retained under BASE")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(--concurrency <N> "number of simultaneous clients")
+                .value_parser(value_parser!(usize))
+                .default_value("8"),
+        )
+        .arg(
+            arg!(--requests <N> "total number of requests to send")
+                .value_parser(value_parser!(usize))
+                .default_value("1000"),
+        );
+    #[cfg(feature = "serve")]
+    let command = command
+        .arg(
+            arg!(--subscribers <N> "WebSocket clients to hold on /ws while the requests run")
+                .value_parser(value_parser!(usize))
+                .default_value("0"),
+        )
+        .arg(arg!(--stations <IDS> "comma-separated station ids the subscribers subscribe to"));
+    command
+}
+
+struct Sample {
+    latency: Duration,
+    ok: bool,
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let target = matches
+        .get_one::<String>("target")
+        .unwrap()
+        .trim_end_matches('/');
+    let mut paths: Vec<String> = Vec::new();
+    if let Some(base) = matches.get_one::<PathBuf>("replay") {
+        for snapshot in archive::retained(base)? {
+            let minute = archive::minute_of(&snapshot.observed_at).unwrap();
+            paths.push(format!("/as-of/{}", archive::format_minute(minute)));
+        }
+        if paths.is_empty() {
+            return Err(format!("no snapshots retained under {}", base.display()).into());
+        }
+        println!("replaying {} snapshots", paths.len());
+    }
+    // The default path only stands in for paths not given.
+    if paths.is_empty() || matches.value_source("path") != Some(ValueSource::DefaultValue) {
+        paths.extend(
+            matches
+                .get_many::<String>("path")
+                .unwrap_or_default()
+                .cloned(),
+        );
+    }
+    let urls: Arc<Vec<String>> =
+        Arc::new(paths.iter().map(|p| format!("{}{}", target, p)).collect());
+    let concurrency = *matches.get_one::<usize>("concurrency").unwrap();
+    let total = *matches.get_one::<usize>("requests").unwrap();
+    #[cfg(feature = "serve")]
+    let (done, subscribers) = {
+        let (done, stop) = watch::channel(false);
+        let url = live_url(target, matches.get_one::<String>("stations"));
+        let count = *matches.get_one::<usize>("subscribers").unwrap();
+        let subscribers: Vec<_> = (0..count)
+            .map(|_| tokio::spawn(subscribe(url.clone(), stop.clone())))
+            .collect();
+        (done, subscribers)
+    };
+    let client = Client::new();
+    let next = Arc::new(AtomicUsize::new(0));
+    let started = Instant::now();
+    let mut workers = Vec::new();
+    for _ in 0..concurrency.max(1) {
+        let (client, urls, next) = (client.clone(), urls.clone(), next.clone());
+        workers.push(tokio::spawn(async move {
+            let mut samples = Vec::new();
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= total {
+                    break;
+                }
+                let begin = Instant::now();
+                let ok = match client.get(&urls[i % urls.len()]).send().await {
+                    Ok(r) => r.status().is_success() && r.bytes().await.is_ok(),
+                    Err(_) => false,
+                };
+                samples.push(Sample {
+                    latency: begin.elapsed(),
+                    ok,
+                });
+            }
+            samples
+        }));
+    }
+    let mut samples = Vec::new();
+    for worker in workers {
+        samples.extend(worker.await?);
+    }
+    report(&mut samples, started.elapsed());
+    #[cfg(feature = "serve")]
+    if !subscribers.is_empty() {
+        done.send_replace(true);
+        let mut subscriptions = Vec::new();
+        for subscriber in subscribers {
+            subscriptions.push(subscriber.await?);
+        }
+        report_subscriptions(&subscriptions);
+    }
+    Ok(())
+}
+
+fn report(samples: &mut [Sample], elapsed: Duration) {
+    let failed = samples.iter().filter(|s| !s.ok).count();
+    println!(
+        "requests: {}, failed: {}, elapsed: {:.2}s, throughput: {:.1} req/s",
+        samples.len(),
+        failed,
+        elapsed.as_secs_f64(),
+        samples.len() as f64 / elapsed.as_secs_f64(),
+    );
+    samples.sort_by_key(|s| s.latency);
+    let latencies: Vec<Duration> = samples.iter().map(|s| s.latency).collect();
+    percentiles("", &latencies);
+}
+
+/// Print the percentiles of `latencies`, sorted, each line led by `label`.
+fn percentiles(label: &str, latencies: &[Duration]) {
+    if latencies.is_empty() {
+        return;
+    }
+    for p in [50, 90, 99, 100] {
+        let i = ((latencies.len() * p).div_ceil(100)).max(1) - 1;
+        println!(
+            "{}p{}: {:.1}ms",
+            label,
+            p,
+            latencies[i].as_secs_f64() * 1000.0
+        );
+    }
+}
+
+/// What one WebSocket client saw.
+#[cfg(feature = "serve")]
+struct Subscription {
+    /// From connecting to the first snapshot, if one came.
+    snapshot: Option<Duration>,
+    observations: usize,
+    ok: bool,
+}
+
+/// The `/ws` URL of `target`, subscribing to `stations` when given.
+#[cfg(feature = "serve")]
+fn live_url(target: &str, stations: Option<&String>) -> String {
+    let target = if let Some(rest) = target.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else {
+        format!("ws://{}", target.trim_start_matches("http://"))
+    };
+    match stations {
+        Some(stations) => format!("{}/ws?stations={}", target, stations),
+        None => format!("{}/ws", target),
+    }
+}
+
+/// Hold a WebSocket client on `url` until `stop` turns true, counting what
+/// the server pushes.
+#[cfg(feature = "serve")]
+async fn subscribe(url: String, mut stop: watch::Receiver<bool>) -> Subscription {
+    let mut subscription = Subscription {
+        snapshot: None,
+        observations: 0,
+        ok: false,
+    };
+    let begin = Instant::now();
+    let Ok((mut socket, _)) = tokio_tungstenite::connect_async(&url).await else {
+        return subscription;
+    };
+    subscription.ok = true;
+    loop {
+        let message = tokio::select! {
+            _ = stop.wait_for(|stop| *stop) => break,
+            message = socket.next() => message,
+        };
+        let text = match message {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Close(_))) | None => break,
+            Some(Ok(_)) => continue,
+            Some(Err(_)) => {
+                subscription.ok = false;
+                break;
+            }
+        };
+        let kind = serde_json::from_str::<serde_json::Value>(&text)
+            .ok()
+            .and_then(|message| message["type"].as_str().map(str::to_string));
+        match kind.as_deref() {
+            Some("snapshot") if subscription.snapshot.is_none() => {
+                subscription.snapshot = Some(begin.elapsed())
+            }
+            Some("observation") => subscription.observations += 1,
+            _ => {}
+        }
+    }
+    let _ = socket.close(None).await;
+    subscription
+}
+
+#[cfg(feature = "serve")]
+fn report_subscriptions(subscriptions: &[Subscription]) {
+    let failed = subscriptions.iter().filter(|s| !s.ok).count();
+    println!(
+        "subscribers: {}, failed: {}, without a snapshot: {}, observations pushed: {}",
+        subscriptions.len(),
+        failed,
+        subscriptions
+            .iter()
+            .filter(|s| s.ok && s.snapshot.is_none())
+            .count(),
+        subscriptions.iter().map(|s| s.observations).sum::<usize>(),
+    );
+    let mut latencies: Vec<Duration> = subscriptions.iter().filter_map(|s| s.snapshot).collect();
+    latencies.sort();
+    percentiles("snapshot ", &latencies);
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Limits a single crawl cycle must stay within.
+///
+/// The cycle limit is enforced at await points, so it bounds network time
+/// rather than interrupting parsing midway.
+#[derive(Clone, Copy, Default)]
+pub struct Budget {
+    /// Wall time for fetch, parse and write together.
+    pub cycle: Option<Duration>,
+    /// Largest response body accepted; a rough cap on the memory a cycle uses.
+    pub body_bytes: Option<usize>,
+    /// Time the output writes may take.
+    pub write: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub enum BudgetExceeded {
+    Cycle(Duration),
+    BodyBytes(usize),
+    Write(Duration, Duration),
+}
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetExceeded::Cycle(limit) => {
+                write!(f, "cycle aborted after exceeding {:?} budget", limit)
+            }
+            BudgetExceeded::BodyBytes(limit) => {
+                write!(f, "response body exceeded {} byte budget", limit)
+            }
+            BudgetExceeded::Write(took, limit) => {
+                write!(f, "writing took {:?}, over the {:?} budget", took, limit)
+            }
+        }
+    }
+}
+impl std::error::Error for BudgetExceeded {}
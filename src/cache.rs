@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+
+use reqwest::header::{HeaderMap, CACHE_CONTROL, EXPIRES};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::{create_dir_all, read};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::atomic;
+use crate::manifest::sha256_hex;
+use crate::pipeline::Page;
+
+/// Responses kept on disk by URL, so that runs close together share one
+/// download whatever the source.
+///
+/// A response is `<sha256 of the URL>.body`, described by the `.json` of the
+/// same name, and is reused until the time its `Cache-Control: max-age` or
+/// `Expires` gives, or for `ttl` when it has neither.
+#[derive(Clone)]
+pub struct HttpCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    fresh_until: DateTime<Utc>,
+    /// Of the body, which is written separately.
+    sha256: String,
+    content_type: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl HttpCache {
+    /// The stored response to `url` while it is fresh.
+    pub fn get(&self, url: &str) -> Option<Page> {
+        let entry: Entry = serde_json::from_slice(&read(self.path(url, "json")).ok()?).ok()?;
+        if entry.url != url || entry.fresh_until <= Utc::now() {
+            return None;
+        }
+        let body = read(self.path(url, "body")).ok()?;
+        if sha256_hex(&body) != entry.sha256 {
+            return None;
+        }
+        Some(Page {
+            url: entry.url,
+            body,
+            content_type: entry.content_type,
+            etag: entry.etag,
+            last_modified: entry.last_modified,
+        })
+    }
+
+    /// How long a response with `headers` may be reused; `None` when it is
+    /// not to be kept at all.
+    pub fn lifetime(&self, headers: &HeaderMap) -> Option<Duration> {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        if let Some(control) = header(CACHE_CONTROL) {
+            let mut max_age = None;
+            for directive in control.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+                if directive == "no-store" || directive == "no-cache" {
+                    return None;
+                }
+                if let Some(seconds) = directive.strip_prefix("max-age=") {
+                    max_age = seconds.trim_matches('"').parse().ok();
+                }
+            }
+            if let Some(seconds) = max_age {
+                return (seconds > 0).then(|| Duration::from_secs(seconds));
+            }
+        }
+        if let Some(expires) = header(EXPIRES) {
+            // An unparsable date, such as `0`, means already expired.
+            let expires = DateTime::parse_from_rfc2822(expires).ok()?;
+            return (expires.with_timezone(&Utc) - Utc::now()).to_std().ok();
+        }
+        Some(self.ttl)
+    }
+
+    pub fn put(&self, page: &Page, lifetime: Duration) -> std::io::Result<()> {
+        create_dir_all(&self.dir)?;
+        let entry = Entry {
+            url: page.url.clone(),
+            fresh_until: Utc::now() + lifetime,
+            sha256: sha256_hex(&page.body),
+            content_type: page.content_type.clone(),
+            etag: page.etag.clone(),
+            last_modified: page.last_modified.clone(),
+        };
+        atomic::write(&self.path(&page.url, "body"), &page.body)?;
+        atomic::write(&self.path(&page.url, "json"), &serde_json::to_vec(&entry)?)
+    }
+
+    fn path(&self, url: &str, extension: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}", sha256_hex(url.as_bytes()), extension))
+    }
+}
@@ -0,0 +1,66 @@
+use encoding::all::{UTF_8, WINDOWS_949};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::{DecoderTrap, EncodingRef};
+
+/// How far into the document to look for a `<meta charset>` declaration.
+const SNIFF_BYTES: usize = 1024;
+
+/// Decode a fetched page.
+///
+/// The charset declared by the `Content-Type` header wins over the one in the
+/// HTML `<meta>` tag; without either, EUC-KR (as CP949, its superset) is
+/// assumed as KMA has always served. If the bytes are not valid in that
+/// encoding, CP949 and UTF-8 are tried in turn. When nothing decodes cleanly,
+/// `strict` fails instead of substituting U+FFFD for the bad bytes.
+pub fn decode(body: &[u8], content_type: Option<&str>, strict: bool) -> Result<String, String> {
+    let declared = content_type
+        .and_then(charset_param)
+        .or_else(|| sniff_meta(body))
+        .and_then(|label| encoding_from_whatwg_label(&label));
+    let mut candidates: Vec<EncodingRef> = Vec::new();
+    candidates.extend(declared);
+    candidates.push(WINDOWS_949);
+    candidates.push(UTF_8);
+    for encoding in candidates.iter() {
+        if let Ok(text) = encoding.decode(body, DecoderTrap::Strict) {
+            return Ok(text);
+        }
+    }
+    if strict {
+        return Err(format!(
+            "page is not valid {}",
+            candidates
+                .iter()
+                .map(|e| e.name())
+                .collect::<Vec<_>>()
+                .join(", nor ")
+        ));
+    }
+    candidates[0]
+        .decode(body, DecoderTrap::Replace)
+        .map_err(|e| e.into_owned())
+}
+
+/// The `charset` parameter of a `Content-Type` value.
+fn charset_param(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// The charset from `<meta charset=..>` or `<meta http-equiv .. content="..; charset=..">`.
+fn sniff_meta(body: &[u8]) -> Option<String> {
+    let head = String::from_utf8_lossy(&body[..body.len().min(SNIFF_BYTES)]).to_ascii_lowercase();
+    let start = head.find("charset=")? + "charset=".len();
+    let label: String = head[start..]
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    (!label.is_empty()).then_some(label)
+}
@@ -0,0 +1,276 @@
+use chrono::{DateTime, FixedOffset};
+
+use futures_util::stream::{self, Stream};
+
+use rust_decimal::Decimal;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use thiserror::Error;
+
+use crate::region::Spread;
+use crate::units::Units;
+use crate::{CrawlResult, Record};
+
+/// Everything that can go wrong asking a server.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("requesting {url}: {source}")]
+    Request { url: String, source: reqwest::Error },
+    #[error("{url} answered HTTP {status}: {message}")]
+    Status {
+        url: String,
+        status: u16,
+        message: String,
+    },
+    #[error("reading the answer of {url}: {source}")]
+    Decode {
+        url: String,
+        source: serde_json::Error,
+    },
+}
+
+/// The records of an observation: the answer of `/stations`, and each event
+/// of `/events`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Observation {
+    pub observed_at: DateTime<FixedOffset>,
+    pub records: Vec<Record>,
+}
+
+/// The answer of `/stations/:id`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StationObservation {
+    pub observed_at: DateTime<FixedOffset>,
+    pub record: Record,
+}
+
+/// The observations of one station over a range, a point per step: the
+/// answer of `/stations/:id/history`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct History {
+    pub id: u32,
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_en: Option<String>,
+    /// First KST minute of the range, e.g. `2024-05-01T00:00`.
+    pub from: String,
+    /// Last KST minute of the range.
+    pub to: String,
+    pub step: String,
+    /// Units of the snapshots, when not metric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub units: Option<Units>,
+    pub points: Vec<Point>,
+}
+
+/// The samples of one step, which starts at `at`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Point {
+    pub at: String,
+    pub samples: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Spread>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<Spread>,
+    /// 10-minute mean wind.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wind: Option<Spread>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atmospheric: Option<Spread>,
+    /// Rain in the step, from how `rainday` grew between samples.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precipitation: Option<Decimal>,
+}
+
+/// A typed client of the HTTP API of `serve`.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), weather_crawl::client::ClientError> {
+/// let client = weather_crawl::client::Client::new("http://localhost:8080");
+/// let seoul = client.station(108).await?;
+/// println!("{:?} at {}", seoul.record.temperature, seoul.observed_at);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Client {
+    http: reqwest::Client,
+    base: String,
+}
+impl Client {
+    /// A client of the server at `base`, such as `http://localhost:8080`.
+    pub fn new(base: &str) -> Self {
+        Client::with_client(reqwest::Client::new(), base)
+    }
+
+    /// A client of the server at `base` sending its requests with `http`.
+    pub fn with_client(http: reqwest::Client, base: &str) -> Self {
+        Client {
+            http,
+            base: base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// `index.json` as the crawler last wrote it.
+    pub async fn latest(&self) -> Result<CrawlResult, ClientError> {
+        self.get("/latest", &[]).await
+    }
+
+    /// The crawl retained closest to the KST minute `at`, e.g.
+    /// `2024-05-01T12:34`.
+    pub async fn as_of(&self, at: &str) -> Result<CrawlResult, ClientError> {
+        self.get("/latest", &[("at", at)]).await
+    }
+
+    /// The latest record of station `id`.
+    pub async fn station(&self, id: u32) -> Result<StationObservation, ClientError> {
+        self.get(&format!("/stations/{}", id), &[]).await
+    }
+
+    /// The latest records of the stations in `region`, a province, city or
+    /// district such as `서울`, or of all of them.
+    pub async fn stations(&self, region: Option<&str>) -> Result<Observation, ClientError> {
+        let query: Vec<(&str, &str)> = region
+            .map(|region| ("region", region))
+            .into_iter()
+            .collect();
+        self.get("/stations", &query).await
+    }
+
+    /// The time series of station `id` from `from` to `to`, KST minutes, a
+    /// point per `step` such as `10m`, `1h` or `1d`; what is not given is
+    /// left to the server.
+    pub async fn history(
+        &self,
+        id: u32,
+        from: Option<&str>,
+        to: Option<&str>,
+        step: Option<&str>,
+    ) -> Result<History, ClientError> {
+        let query: Vec<(&str, &str)> = [("from", from), ("to", to), ("step", step)]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)))
+            .collect();
+        self.get(&format!("/stations/{}/history", id), &query).await
+    }
+
+    /// Each new observation as it is crawled, from `/events`, with only the
+    /// records of `station` or of `region` when given. The stream ends when
+    /// the server closes the connection.
+    pub async fn watch(
+        &self,
+        station: Option<u32>,
+        region: Option<&str>,
+    ) -> Result<impl Stream<Item = Result<Observation, ClientError>>, ClientError> {
+        let station = station.map(|id| id.to_string());
+        let query: Vec<(&str, &str)> = [("station", station.as_deref()), ("region", region)]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)))
+            .collect();
+        let url = format!("{}/events", self.base);
+        let response = self.send(&url, &query).await?;
+        Ok(stream::unfold(
+            (Some(response), Vec::new(), url),
+            |(response, mut buffer, url)| async move {
+                let mut response = response?;
+                loop {
+                    // Events end with a blank line.
+                    if let Some(end) = buffer.windows(2).position(|pair| pair == b"\n\n") {
+                        let event: Vec<u8> = buffer.drain(..end + 2).collect();
+                        let Some(data) = observation(&String::from_utf8_lossy(&event)) else {
+                            continue;
+                        };
+                        let parsed =
+                            serde_json::from_str(&data).map_err(|source| ClientError::Decode {
+                                url: url.clone(),
+                                source,
+                            });
+                        return Some((parsed, (Some(response), buffer, url)));
+                    }
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => buffer.extend(chunk.iter().filter(|b| **b != b'\r')),
+                        Ok(None) => return None,
+                        // Nothing more comes after a broken connection.
+                        Err(source) => {
+                            let error = ClientError::Request {
+                                url: url.clone(),
+                                source,
+                            };
+                            return Some((Err(error), (None, buffer, url)));
+                        }
+                    }
+                }
+            },
+        ))
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, ClientError> {
+        let url = format!("{}{}", self.base, path);
+        let response = self.send(&url, query).await?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(|source| ClientError::Request {
+                url: url.clone(),
+                source,
+            })?;
+        serde_json::from_slice(&body).map_err(|source| ClientError::Decode { url, source })
+    }
+
+    /// The response to a GET of `url`, when successful.
+    async fn send(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<reqwest::Response, ClientError> {
+        let request = |source| ClientError::Request {
+            url: url.to_string(),
+            source,
+        };
+        let response = self
+            .http
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(request)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        // Errors of the API are `{"error": "..."}`.
+        let body = response.text().await.map_err(request)?;
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|error| error["error"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        Err(ClientError::Status {
+            url: url.to_string(),
+            status: status.as_u16(),
+            message,
+        })
+    }
+}
+
+/// The data of `event`, a Server-Sent Event, if it is an observation.
+fn observation(event: &str) -> Option<String> {
+    let mut name = "message";
+    let mut data = Vec::new();
+    for line in event.lines() {
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => name = value,
+            "data" => data.push(value),
+            // Comments such as the keep-alives, and the ids.
+            _ => {}
+        }
+    }
+    (name == "observation" && !data.is_empty()).then(|| data.join("\n"))
+}
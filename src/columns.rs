@@ -0,0 +1,432 @@
+use scraper::{ElementRef, Html, Selector};
+
+/// Every column `make_record` reads, in the order of the legacy layout.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Field {
+    Id,
+    Name,
+    Height,
+    IsRaining,
+    Rain15,
+    Rain60,
+    Rain3h,
+    Rain6h,
+    Rain12h,
+    RainDay,
+    Temperature,
+    Wind1Code,
+    Wind1Text,
+    Wind1Velocity,
+    Wind10Code,
+    Wind10Text,
+    Wind10Velocity,
+    Humidity,
+    Atmospheric,
+    Address,
+}
+const FIELDS: [Field; 20] = [
+    Field::Id,
+    Field::Name,
+    Field::Height,
+    Field::IsRaining,
+    Field::Rain15,
+    Field::Rain60,
+    Field::Rain3h,
+    Field::Rain6h,
+    Field::Rain12h,
+    Field::RainDay,
+    Field::Temperature,
+    Field::Wind1Code,
+    Field::Wind1Text,
+    Field::Wind1Velocity,
+    Field::Wind10Code,
+    Field::Wind10Text,
+    Field::Wind10Velocity,
+    Field::Humidity,
+    Field::Atmospheric,
+    Field::Address,
+];
+
+/// Where each field lives in a data row.
+#[derive(Clone)]
+pub struct ColumnMap {
+    index: [usize; 20],
+    /// Snow depth, which the page only has in winter.
+    snow: Option<usize>,
+}
+impl ColumnMap {
+    /// The fixed layout the page has used historically.
+    pub fn legacy() -> Self {
+        let mut index = [0; 20];
+        for (i, slot) in index.iter_mut().enumerate() {
+            *slot = i;
+        }
+        ColumnMap { index, snow: None }
+    }
+
+    /// Build the map from the labels of the header rows above the data.
+    ///
+    /// Fails with the names of the fields no header label accounts for.
+    pub fn from_header(rows: &[Vec<HeaderCell>]) -> Result<Self, String> {
+        let labels = column_labels(rows);
+        let mut found: [Option<usize>; 20] = [None; 20];
+        let mut directions: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+        let mut speeds: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+        let mut unmarked_winds: Vec<(usize, bool)> = Vec::new();
+        let mut snow = None;
+        for (i, segments) in labels.iter().enumerate() {
+            let joined = segments.concat();
+            let any = |needle: &str| segments.iter().any(|s| s.contains(needle));
+            let exact = |needle: &str| segments.iter().any(|s| s == needle);
+            let field = if any("적설") {
+                snow.get_or_insert(i);
+                None
+            } else if any("지점명") {
+                Some(Field::Name)
+            } else if exact("지점") || exact("번호") || any("지점번호") {
+                Some(Field::Id)
+            } else if any("고도") || any("해발") {
+                Some(Field::Height)
+            } else if any("기온") {
+                Some(Field::Temperature)
+            } else if any("습도") {
+                Some(Field::Humidity)
+            } else if any("기압") {
+                Some(Field::Atmospheric)
+            } else if any("주소") {
+                Some(Field::Address)
+            } else if any("풍향") || any("풍속") {
+                let group = if joined.contains("10분") {
+                    Some(1)
+                } else if joined.contains("1분") {
+                    Some(0)
+                } else {
+                    None
+                };
+                let is_direction = any("풍향");
+                match (group, is_direction) {
+                    (Some(g), true) => directions[g].push(i),
+                    (Some(g), false) => speeds[g].push(i),
+                    (None, d) => unmarked_winds.push((i, d)),
+                }
+                None
+            } else if any("15분") {
+                Some(Field::Rain15)
+            } else if any("60분") || any("1시간") {
+                Some(Field::Rain60)
+            } else if any("12시간") {
+                Some(Field::Rain12h)
+            } else if any("3시간") {
+                Some(Field::Rain3h)
+            } else if any("6시간") {
+                Some(Field::Rain6h)
+            } else if joined.contains("강수") || joined.contains("강우") {
+                if segments.iter().any(|s| s.starts_with('일')) {
+                    Some(Field::RainDay)
+                } else {
+                    Some(Field::IsRaining)
+                }
+            } else {
+                None
+            };
+            if let Some(field) = field {
+                found[field as usize].get_or_insert(i);
+            }
+        }
+        // Without 1/10 minute markers the first wind block is the 1 minute one.
+        for (i, is_direction) in unmarked_winds {
+            let g = if directions[0].len() + speeds[0].len() < 3 {
+                0
+            } else {
+                1
+            };
+            if is_direction {
+                directions[g].push(i);
+            } else {
+                speeds[g].push(i);
+            }
+        }
+        let winds = [
+            (Field::Wind1Code, Field::Wind1Text, Field::Wind1Velocity),
+            (Field::Wind10Code, Field::Wind10Text, Field::Wind10Velocity),
+        ];
+        for (g, (code, text, velocity)) in winds.iter().enumerate() {
+            found[*code as usize] = directions[g].first().copied();
+            found[*text as usize] = directions[g].get(1).copied();
+            found[*velocity as usize] = speeds[g].first().copied();
+        }
+
+        let missing: Vec<String> = FIELDS
+            .iter()
+            .filter(|f| found[**f as usize].is_none())
+            .map(|f| format!("{:?}", f))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "unrecognized table header, no column for {}",
+                missing.join(", ")
+            ));
+        }
+        let mut index = [0; 20];
+        for (slot, column) in index.iter_mut().zip(found.iter()) {
+            *slot = column.unwrap();
+        }
+        Ok(ColumnMap { index, snow })
+    }
+
+    /// Number of cells a data row needs for every field to be present.
+    pub fn width(&self) -> usize {
+        self.index.iter().max().map_or(0, |m| m + 1)
+    }
+
+    pub fn get<'a>(&self, cells: &[&'a str], field: Field) -> &'a str {
+        cells[self.index[field as usize]]
+    }
+
+    /// Snow depth cell, when the page has the column and the row the cell.
+    pub fn snow_depth<'a>(&self, cells: &[&'a str]) -> Option<&'a str> {
+        cells.get(self.snow?).copied()
+    }
+}
+
+pub struct HeaderCell {
+    label: String,
+    colspan: usize,
+    rowspan: usize,
+}
+impl HeaderCell {
+    pub fn from_element(el: ElementRef) -> Self {
+        let span = |name| {
+            el.value()
+                .attr(name)
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or(1)
+                .max(1)
+        };
+        HeaderCell {
+            label: el.text().collect::<String>().split_whitespace().collect(),
+            colspan: span("colspan"),
+            rowspan: span("rowspan"),
+        }
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.label.is_empty()
+    }
+}
+
+/// Lay the header rows out on a grid and collect the labels stacked above
+/// every column, top to bottom.
+fn column_labels(rows: &[Vec<HeaderCell>]) -> Vec<Vec<String>> {
+    let mut grid: Vec<Vec<Option<&str>>> = vec![Vec::new(); rows.len()];
+    for (r, row) in rows.iter().enumerate() {
+        let mut col = 0;
+        for cell in row {
+            while grid[r].get(col).is_some_and(|c| c.is_some()) {
+                col += 1;
+            }
+            for line in grid.iter_mut().skip(r).take(cell.rowspan) {
+                if line.len() < col + cell.colspan {
+                    line.resize(col + cell.colspan, None);
+                }
+                for slot in line.iter_mut().skip(col).take(cell.colspan) {
+                    *slot = Some(&cell.label);
+                }
+            }
+            col += cell.colspan;
+        }
+    }
+    let width = grid.iter().map(Vec::len).max().unwrap_or(0);
+    (0..width)
+        .map(|c| {
+            let mut labels: Vec<String> = Vec::new();
+            for line in grid.iter() {
+                if let Some(Some(label)) = line.get(c) {
+                    if !label.is_empty() && labels.last().map(String::as_str) != Some(*label) {
+                        labels.push(label.to_string());
+                    }
+                }
+            }
+            labels
+        })
+        .collect()
+}
+
+/// A data row of a table whose columns are told apart by their header labels
+/// alone, as on KMA's hourly ASOS and marine pages.
+pub struct LabelledRow<C> {
+    columns: Vec<Option<C>>,
+    /// Trimmed text of every cell.
+    pub cells: Vec<String>,
+    /// Station number of the first link in the row, e.g. `...?stn=108`.
+    pub station: Option<u32>,
+}
+impl<C: Copy + PartialEq> LabelledRow<C> {
+    /// Text of `column`, or `None` when the table has no such column.
+    pub fn get(&self, column: C) -> Option<&str> {
+        let i = self.columns.iter().position(|c| *c == Some(column))?;
+        self.cells.get(i).map(String::as_str)
+    }
+}
+
+/// Data rows of every table with a `key` column, naming columns with
+/// `column_of` over the joined labels stacked above them, e.g. `기온현재기온`.
+pub fn labelled_rows<C: Copy + PartialEq>(
+    document: &Html,
+    key: C,
+    column_of: impl Fn(&str) -> Option<C>,
+) -> Vec<LabelledRow<C>> {
+    let table_selector = Selector::parse("table").unwrap();
+    let row_selector = Selector::parse("tr").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let mut rows = Vec::new();
+    for table in document.select(&table_selector) {
+        let mut header: Vec<Vec<HeaderCell>> = Vec::new();
+        let mut columns: Vec<Option<C>> = Vec::new();
+        for row in table.select(&row_selector) {
+            let cells: Vec<ElementRef> = row.children().filter_map(ElementRef::wrap).collect();
+            if cells.iter().all(|c| c.value().name() == "th") {
+                header.push(cells.into_iter().map(HeaderCell::from_element).collect());
+                continue;
+            }
+            if !header.is_empty() {
+                columns = column_labels(&header)
+                    .iter()
+                    .map(|labels| column_of(&labels.concat()))
+                    .collect();
+                header.clear();
+            }
+            if !columns.contains(&Some(key)) {
+                continue;
+            }
+            rows.push(LabelledRow {
+                columns: columns.clone(),
+                cells: cells
+                    .iter()
+                    .map(|c| c.text().collect::<String>().trim().to_string())
+                    .collect(),
+                station: row
+                    .select(&link_selector)
+                    .find_map(|a| station_number(a.value().attr("href").unwrap_or_default())),
+            });
+        }
+    }
+    rows
+}
+
+fn station_number(href: &str) -> Option<u32> {
+    let (_, query) = href.split_once('?')?;
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| matches!(*key, "stn" | "stnId"))
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The header rows of the table in `html`.
+    fn header(html: &str) -> Vec<Vec<HeaderCell>> {
+        let document = Html::parse_fragment(html);
+        let row_selector = Selector::parse("tr").unwrap();
+        document
+            .select(&row_selector)
+            .map(|row| {
+                row.children()
+                    .filter_map(ElementRef::wrap)
+                    .map(HeaderCell::from_element)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn two_row_header_with_spans() {
+        let rows = header(
+            "<table>
+            <tr><th rowspan=2>지점</th><th rowspan=2>지점명</th><th rowspan=2>고도</th>
+                <th rowspan=2>강수</th><th colspan=6>강수량</th><th rowspan=2>기온</th>
+                <th colspan=3>1분 평균</th><th colspan=3>10분 평균</th>
+                <th rowspan=2>습도</th><th rowspan=2>기압</th><th rowspan=2>주소</th></tr>
+            <tr><th>15분</th><th>60분</th><th>3시간</th><th>6시간</th><th>12시간</th>
+                <th>일강수</th><th>풍향</th><th>풍향</th><th>풍속</th><th>풍향</th>
+                <th>풍향</th><th>풍속</th></tr>
+            </table>",
+        );
+        let map = ColumnMap::from_header(&rows).unwrap();
+        assert_eq!(map.index, ColumnMap::legacy().index);
+        assert_eq!(map.snow, None);
+        assert_eq!(map.width(), 20);
+    }
+
+    #[test]
+    fn reordered_columns() {
+        let columns = [
+            ("주소", "address"),
+            ("적설", "snow"),
+            ("10분풍향", "wind10 code"),
+            ("10분풍향", "wind10 text"),
+            ("10분풍속", "wind10 velocity"),
+            ("기온", "temperature"),
+            ("지점명", "name"),
+            ("지점", "id"),
+            ("일강수", "rain day"),
+            ("12시간", "rain 12h"),
+            ("6시간", "rain 6h"),
+            ("3시간", "rain 3h"),
+            ("60분", "rain 60"),
+            ("15분", "rain 15"),
+            ("강수", "is raining"),
+            ("1분풍향", "wind1 code"),
+            ("1분풍향", "wind1 text"),
+            ("1분풍속", "wind1 velocity"),
+            ("기압", "atmospheric"),
+            ("습도", "humidity"),
+            ("고도", "height"),
+        ];
+        let labels: String = columns
+            .iter()
+            .map(|(label, _)| format!("<th>{}</th>", label))
+            .collect();
+        let map = ColumnMap::from_header(&header(&format!("<table><tr>{}</tr></table>", labels)))
+            .unwrap();
+        let cells: Vec<&str> = columns.iter().map(|(_, cell)| *cell).collect();
+        let expected = [
+            (Field::Id, "id"),
+            (Field::Name, "name"),
+            (Field::Height, "height"),
+            (Field::IsRaining, "is raining"),
+            (Field::Rain15, "rain 15"),
+            (Field::Rain60, "rain 60"),
+            (Field::Rain3h, "rain 3h"),
+            (Field::Rain6h, "rain 6h"),
+            (Field::Rain12h, "rain 12h"),
+            (Field::RainDay, "rain day"),
+            (Field::Temperature, "temperature"),
+            (Field::Wind1Code, "wind1 code"),
+            (Field::Wind1Text, "wind1 text"),
+            (Field::Wind1Velocity, "wind1 velocity"),
+            (Field::Wind10Code, "wind10 code"),
+            (Field::Wind10Text, "wind10 text"),
+            (Field::Wind10Velocity, "wind10 velocity"),
+            (Field::Humidity, "humidity"),
+            (Field::Atmospheric, "atmospheric"),
+            (Field::Address, "address"),
+        ];
+        for (field, cell) in expected {
+            assert_eq!(map.get(&cells, field), cell, "{:?}", field);
+        }
+        assert_eq!(map.snow_depth(&cells), Some("snow"));
+        assert_eq!(map.width(), columns.len());
+    }
+
+    #[test]
+    fn header_without_a_field() {
+        let rows = header("<table><tr><th>지점</th><th>지점명</th><th>기온</th></tr></table>");
+        let error = ColumnMap::from_header(&rows).err().unwrap();
+        assert!(error.contains("Height"), "{}", error);
+        assert!(!error.contains("Temperature"), "{}", error);
+    }
+}
@@ -0,0 +1,372 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::Deserializer;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{read, read_dir, read_link, remove_file, rename, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::archive::{self, Snapshot};
+use crate::manifest::{count_records, Counted, Entry, Manifest};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BundleFormat {
+    /// One snapshot per line.
+    Ndjson,
+    /// A single JSON array of snapshots.
+    Json,
+}
+impl BundleFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            BundleFormat::Ndjson => "ndjson.gz",
+            BundleFormat::Json => "json.gz",
+        }
+    }
+
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        [BundleFormat::Ndjson, BundleFormat::Json]
+            .into_iter()
+            .find(|f| name.ends_with(&format!(".{}", f.extension())))
+    }
+}
+
+/// A daily bundle, `<base>/<day>.ndjson.gz` or `<base>/<day>.json.gz`.
+pub struct Bundle {
+    pub day: String,
+    pub path: PathBuf,
+}
+
+/// Every bundle under `base`, oldest first.
+pub fn bundles(base: &Path) -> std::io::Result<Vec<Bundle>> {
+    let mut bundles = Vec::new();
+    for entry in read_dir(base)? {
+        let path = entry?.path();
+        if let Some(format) = BundleFormat::of(&path) {
+            let name = path.file_name().unwrap().to_string_lossy();
+            let day = name[..name.len() - format.extension().len() - 1].to_string();
+            bundles.push(Bundle { day, path });
+        }
+    }
+    bundles.sort_by(|a, b| a.day.cmp(&b.day));
+    Ok(bundles)
+}
+
+pub fn is_bundle(path: &Path) -> bool {
+    BundleFormat::of(path).is_some()
+}
+
+/// Call `f` with every snapshot in the bundle at `path`, holding one at a time.
+pub fn each_bundled(
+    path: &Path,
+    f: &mut dyn FnMut(Value) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+    match BundleFormat::of(path) {
+        Some(BundleFormat::Ndjson) => {
+            for line in reader.lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    f(serde_json::from_str(&line)?)?;
+                }
+            }
+        }
+        Some(BundleFormat::Json) => {
+            serde_json::Deserializer::from_reader(reader).deserialize_seq(EachElement(f))?
+        }
+        None => return Err(format!("{} is not a bundle", path.display()).into()),
+    }
+    Ok(())
+}
+
+/// Pass every snapshot of the bundle at `path` through `f` and replace the
+/// bundle with the result, returning how many records it holds.
+pub fn rewrite_bundle(
+    path: &Path,
+    f: &mut dyn FnMut(&mut Value),
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let format =
+        BundleFormat::of(path).ok_or_else(|| format!("{} is not a bundle", path.display()))?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut out = GzEncoder::new(File::create(&tmp)?, Compression::default());
+    let (mut snapshots, mut records) = (0, 0);
+    if format == BundleFormat::Json {
+        out.write_all(b"[")?;
+    }
+    each_bundled(path, &mut |mut snapshot| {
+        f(&mut snapshot);
+        records += snapshot["records"].as_array().map_or(0, Vec::len);
+        if format == BundleFormat::Json && snapshots > 0 {
+            out.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut out, &snapshot)?;
+        if format == BundleFormat::Ndjson {
+            out.write_all(b"\n")?;
+        }
+        snapshots += 1;
+        Ok(())
+    })?;
+    if format == BundleFormat::Json {
+        out.write_all(b"]")?;
+    }
+    out.finish()?.sync_all()?;
+    rename(&tmp, path)?;
+    Ok(records)
+}
+
+/// Feeds the elements of a JSON array to a callback instead of collecting them.
+struct EachElement<'a>(&'a mut dyn FnMut(Value) -> Result<(), Box<dyn std::error::Error>>);
+
+impl<'de> Visitor<'de> for EachElement<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of crawl results")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(snapshot) = seq.next_element::<Value>()? {
+            (self.0)(snapshot).map_err(de::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn command() -> Command {
+    Command::new("compact")
+        .about("merge the snapshots of each past day into one compressed daily bundle")
+        .arg(
+            arg!(<base> "base path the crawler writes to")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(--format <FORMAT> "layout of the bundle")
+                .value_parser(["ndjson", "json"])
+                .default_value("ndjson"),
+        )
+        .arg(arg!(--before <DATE> "only compact days before this YYYY-MM-DD [default: the latest day]"))
+        .arg(arg!(--"keep-originals" "do not delete the snapshots once bundled"))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let format = match matches.get_one::<String>("format").unwrap().as_str() {
+        "json" => BundleFormat::Json,
+        _ => BundleFormat::Ndjson,
+    };
+    let mut days: BTreeMap<String, Vec<Snapshot>> = BTreeMap::new();
+    for snapshot in archive::list(base)? {
+        days.entry(snapshot.observed_at[..10].to_string())
+            .or_default()
+            .push(snapshot);
+    }
+    // The latest day is most likely still being written to.
+    let before = match matches.get_one::<String>("before") {
+        Some(date) => date.clone(),
+        None => days.keys().next_back().cloned().unwrap_or_default(),
+    };
+    // With `--index-mode symlink`, index.json must keep pointing at a file.
+    let linked = read_link(base.join(archive::INDEX_FILE))
+        .ok()
+        .map(|target| base.join(target));
+    for (day, snapshots) in days.range(..before) {
+        let bundle = base.join(format!("{}.{}", day, format.extension()));
+        if bundle.exists() {
+            println!("skipped  {}: {} already exists", day, bundle.display());
+            continue;
+        }
+        let records = write_bundle(&bundle, format, snapshots)?;
+        let (found_snapshots, found_records) = count_bundle(&bundle, format)?;
+        if (found_snapshots, found_records) != (snapshots.len(), records) {
+            remove_file(&bundle)?;
+            return Err(format!(
+                "{}: bundle holds {} snapshots / {} records, expected {} / {}",
+                day,
+                found_snapshots,
+                found_records,
+                snapshots.len(),
+                records
+            )
+            .into());
+        }
+        let mut manifest = Manifest::load(base);
+        let file_name = |p: &Path| p.file_name().unwrap().to_string_lossy().into_owned();
+        manifest.put_bundle(Entry::new(
+            &file_name(&bundle),
+            day,
+            &read(&bundle)?,
+            records,
+        ));
+        if !matches.get_flag("keep-originals") {
+            for snapshot in snapshots {
+                if linked.as_ref() != Some(&snapshot.path) {
+                    remove_file(&snapshot.path)?;
+                    manifest.remove_snapshot(&file_name(&snapshot.path));
+                }
+            }
+        }
+        manifest.save(base)?;
+        println!(
+            "compacted {}: {} snapshots, {} records",
+            day,
+            snapshots.len(),
+            records
+        );
+    }
+    Ok(())
+}
+
+/// Stream `snapshots` into `bundle`, returning how many records they hold.
+fn write_bundle(
+    bundle: &Path,
+    format: BundleFormat,
+    snapshots: &[Snapshot],
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut tmp = bundle.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut out = GzEncoder::new(File::create(&tmp)?, Compression::default());
+    let mut records = 0;
+    if format == BundleFormat::Json {
+        out.write_all(b"[")?;
+    }
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        let mut body = read(&snapshot.path)?;
+        records +=
+            count_records(&body).map_err(|e| format!("{}: {}", snapshot.path.display(), e))?;
+        // A pretty-printed snapshot has to fit on its line.
+        if format == BundleFormat::Ndjson && body.trim_ascii().contains(&b'\n') {
+            body = serde_json::to_vec(&serde_json::from_slice::<Value>(&body)?)?;
+        }
+        if format == BundleFormat::Json && i > 0 {
+            out.write_all(b",")?;
+        }
+        out.write_all(body.trim_ascii())?;
+        if format == BundleFormat::Ndjson {
+            out.write_all(b"\n")?;
+        }
+    }
+    if format == BundleFormat::Json {
+        out.write_all(b"]")?;
+    }
+    out.finish()?.sync_all()?;
+    rename(&tmp, bundle)?;
+    Ok(records)
+}
+
+/// Snapshots and records found when reading `bundle` back.
+fn count_bundle(
+    bundle: &Path,
+    format: BundleFormat,
+) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+    let reader = BufReader::new(GzDecoder::new(File::open(bundle)?));
+    let counted: Vec<Counted> = match format {
+        BundleFormat::Ndjson => reader
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<Result<_, Box<dyn std::error::Error>>>()?,
+        BundleFormat::Json => serde_json::from_reader(reader)?,
+    };
+    Ok((counted.len(), counted.iter().map(|c| c.records.len()).sum()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    /// An empty base path for one test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "weather_crawl-compact-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn snapshot(observed_at: &str, ids: &[u32]) -> Value {
+        let records: Vec<Value> = ids
+            .iter()
+            .map(|id| serde_json::json!({ "id": id }))
+            .collect();
+        serde_json::json!({
+            "observed_at": observed_at,
+            "source": "https://www.weather.go.kr/",
+            "records": records,
+        })
+    }
+
+    fn compact(base: &Path, format: &str) {
+        let base = base.to_str().unwrap();
+        run(&command().get_matches_from(["compact", base, "--format", format])).unwrap();
+    }
+
+    #[test]
+    fn compacted_days_read_back_the_same() {
+        for format in ["ndjson", "json"] {
+            let base = scratch(format);
+            let written = [
+                snapshot("2024-05-01T12:34:00+09:00", &[108, 159]),
+                snapshot("2024-05-01T23:59:00+09:00", &[108]),
+                snapshot("2024-05-02T00:00:00+09:00", &[108, 159, 184]),
+            ];
+            for doc in &written {
+                let path = archive::snapshot_path(&base, doc["observed_at"].as_str().unwrap());
+                write(path, serde_json::to_vec_pretty(doc).unwrap()).unwrap();
+            }
+            compact(&base, format);
+            // A second run finds the bundle there and leaves it be.
+            compact(&base, format);
+
+            let bundles = bundles(&base).unwrap();
+            assert_eq!(bundles.len(), 1, "{}", format);
+            assert_eq!(bundles[0].day, "2024-05-01");
+            let mut bundled = Vec::new();
+            each_bundled(&bundles[0].path, &mut |doc| {
+                bundled.push(doc);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(bundled, written[..2], "{}", format);
+
+            // The latest day is left to the crawler.
+            let loose: Vec<String> = archive::list(&base)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.observed_at)
+                .collect();
+            assert_eq!(loose, ["2024-05-02T00:00:00+09:00"]);
+
+            let retained: Vec<(bool, Value)> = archive::retained(&base)
+                .unwrap()
+                .iter()
+                .map(|s| {
+                    (
+                        s.bundled,
+                        serde_json::from_slice(&s.read().unwrap()).unwrap(),
+                    )
+                })
+                .collect();
+            let expected: Vec<(bool, Value)> = written
+                .iter()
+                .enumerate()
+                .map(|(i, doc)| (i < 2, doc.clone()))
+                .collect();
+            assert_eq!(retained, expected, "{}", format);
+            remove_dir_all(&base).unwrap();
+        }
+    }
+}
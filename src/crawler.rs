@@ -0,0 +1,415 @@
+use reqwest::Client;
+
+use tokio::time::MissedTickBehavior;
+
+use tracing::{error, info, info_span, warn, Instrument};
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::alert::Alerts;
+use crate::archive::{self, IndexMode};
+use crate::budget::{Budget, BudgetExceeded};
+use crate::error::CrawlError;
+use crate::heartbeat::Heartbeat;
+use crate::http::{self, HttpOptions};
+use crate::names::NameTable;
+use crate::pipeline::{fetch, process_page, Fetched, Outcome, Settings};
+use crate::polite::HostLimit;
+use crate::publish::Profile;
+use crate::sink::{self, Sink, Target};
+use crate::source::SOURCES;
+use crate::state::State;
+use crate::stations::Catalog;
+use crate::stats::CrawlStats;
+use crate::timezone::{self, OutputTz};
+use crate::{attribution, lock, units};
+
+/// How often a cycle goes through its URLs before giving up.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    pub attempts: usize,
+    /// Pause before every attempt after the first.
+    pub delay: Duration,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 5,
+            delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A configured crawl into one base path, for embedding the crawler.
+///
+/// ```no_run
+/// # async fn crawl() -> Result<(), Box<dyn std::error::Error>> {
+/// let crawler = weather_crawl::Crawler::builder("/var/lib/weather")
+///     .timeout(std::time::Duration::from_secs(10))
+///     .only_stations([108, 112])
+///     .build()?;
+/// crawler.run_once().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Crawler {
+    settings: Settings,
+    client: Client,
+    urls: Vec<String>,
+    retry: RetryPolicy,
+    lock_wait: Duration,
+}
+impl Crawler {
+    /// A crawler writing to `base`, configured as the binary is by default.
+    pub fn builder(base: impl Into<PathBuf>) -> CrawlerBuilder {
+        CrawlerBuilder {
+            settings: defaults(base.into()),
+            urls: Vec::new(),
+            http: HttpOptions {
+                timeout: Some(Duration::from_secs(30)),
+                connect_timeout: Some(Duration::from_secs(10)),
+                proxy: None,
+                no_proxy: false,
+                insecure: false,
+                ca_cert: None,
+                user_agent: None,
+                headers: Vec::new(),
+            },
+            retry: RetryPolicy::default(),
+            lock_wait: Duration::ZERO,
+        }
+    }
+
+    /// Crawl once, holding the lock on the base path meanwhile.
+    ///
+    /// A page that has not advanced since the last write, or that the server
+    /// answered was not modified, is `CrawlError::Unchanged`.
+    pub async fn run_once(&self) -> Result<(), CrawlError> {
+        let _lock = lock::acquire(&self.settings.base, self.lock_wait).await?;
+        let mut stats = CrawlStats::default();
+        cycle(
+            &self.client,
+            &self.urls,
+            &self.settings,
+            self.retry,
+            &mut stats,
+        )
+        .instrument(info_span!("crawl"))
+        .await
+    }
+
+    /// Crawl every `every` until writing to disk fails, returning that
+    /// failure.
+    ///
+    /// Any other failure, such as KMA being unreachable or a sink refusing
+    /// the result, is logged and the next crawl goes ahead as planned.
+    pub async fn run_forever(&self, every: Duration) -> CrawlError {
+        let mut ticks = tokio::time::interval(every);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            match self.run_once().await {
+                Ok(()) | Err(CrawlError::Unchanged { .. }) => {}
+                Err(e @ (CrawlError::Write { .. } | CrawlError::Io(_))) => return e,
+                Err(e) => warn!(error = %e, "crawl failed"),
+            }
+        }
+    }
+}
+
+pub struct CrawlerBuilder {
+    settings: Settings,
+    urls: Vec<String>,
+    http: HttpOptions,
+    retry: RetryPolicy,
+    lock_wait: Duration,
+}
+impl CrawlerBuilder {
+    /// Crawl `url` instead of KMA's page; every further one is a fallback
+    /// tried in order.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.urls.push(url.into());
+        self
+    }
+
+    /// Total time allowed for one request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = Some(timeout);
+        self
+    }
+
+    /// Time allowed to establish a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.http.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Leave at least `interval` between requests to KMA's site, across every
+    /// process on the host; zero turns the limit off.
+    pub fn min_interval(mut self, interval: Duration) -> Self {
+        self.settings.host_limit = Some(HostLimit::new(interval));
+        self
+    }
+
+    /// Abort a crawl after this much wall time.
+    pub fn max_cycle_time(mut self, limit: Duration) -> Self {
+        self.settings.budget.cycle = Some(limit);
+        self
+    }
+
+    /// Wait this long for another crawl into the base path to finish.
+    pub fn lock_wait(mut self, wait: Duration) -> Self {
+        self.lock_wait = wait;
+        self
+    }
+
+    /// Keep only the records of these stations.
+    pub fn only_stations(mut self, ids: impl IntoIterator<Item = u32>) -> Self {
+        self.settings.only_stations = Some(ids.into_iter().collect());
+        self
+    }
+
+    /// Treat pages with fewer records, after filtering, as a failed attempt.
+    pub fn min_records(mut self, min_records: usize) -> Self {
+        self.settings.min_records = min_records;
+        self
+    }
+
+    /// Write index.json and the rest under the base path, which is what
+    /// happens when no sink is added at all.
+    pub fn file_sink(mut self) -> Self {
+        self.settings.sinks.push(Target::File);
+        self
+    }
+
+    /// Print every result as a line of JSON on stdout.
+    pub fn stdout_sink(mut self) -> Self {
+        self.settings.sinks.push(Target::Stdout);
+        self
+    }
+
+    /// Hand every result to `sink`.
+    pub fn sink(mut self, sink: impl Sink + Send + 'static) -> Self {
+        self.settings
+            .sinks
+            .push(Target::Custom(Arc::new(tokio::sync::Mutex::new(sink))));
+        self
+    }
+
+    pub fn build(mut self) -> Result<Crawler, Box<dyn std::error::Error>> {
+        if self.settings.sinks.is_empty() {
+            self.settings.sinks.push(Target::File);
+        }
+        if self.urls.is_empty() {
+            self.urls
+                .push(self.settings.source.default_url().to_string());
+        }
+        Ok(Crawler {
+            client: http::build_client(&self.http)?,
+            settings: self.settings,
+            urls: self.urls,
+            retry: self.retry,
+            lock_wait: self.lock_wait,
+        })
+    }
+}
+
+/// The settings the binary runs with when given no flags.
+fn defaults(base: PathBuf) -> Settings {
+    Settings {
+        base,
+        secondary: None,
+        profile: Profile::Full,
+        keep_snapshots: false,
+        index_mode: IndexMode::Copy,
+        patch: None,
+        deltas: None,
+        source: SOURCES[0],
+        request_gap: None,
+        host_limit: Some(HostLimit::new(Duration::from_secs(5))),
+        cache: None,
+        sinks: Vec::new(),
+        only_stations: None,
+        strict: false,
+        strict_encoding: false,
+        force: false,
+        min_records: 1,
+        record_fixture: None,
+        attribution_source: attribution::SOURCE.to_string(),
+        license: attribution::LICENSE.to_string(),
+        instance: None,
+        names: NameTable::bundled(),
+        romanize: false,
+        stations: Catalog::bundled(),
+        spatial_qc: None,
+        validation: None,
+        derive: false,
+        astro: false,
+        trends: false,
+        aggregate_regions: false,
+        alerts: None,
+        air_stations: None,
+        join_air: None,
+        budget: Budget::default(),
+        fault: None,
+        tz: OutputTz::Fixed(timezone::kst()),
+        units: units::METRIC,
+        backfill: None,
+    }
+}
+
+/// One crawl cycle within the cycle budget of `settings`.
+pub async fn cycle(
+    client: &Client,
+    urls: &[String],
+    settings: &Settings,
+    retry: RetryPolicy,
+    stats: &mut CrawlStats,
+) -> Result<(), CrawlError> {
+    let cycle = run_cycle(client, urls, settings, retry, stats);
+    match settings.budget.cycle {
+        Some(limit) => match tokio::time::timeout(limit, cycle).await {
+            Ok(result) => result,
+            Err(_) => Err(BudgetExceeded::Cycle(limit).into()),
+        },
+        None => cycle.await,
+    }
+}
+
+/// Go through `urls` until one yields a page, retrying them all as `retry`
+/// allows.
+async fn run_cycle(
+    client: &Client,
+    urls: &[String],
+    settings: &Settings,
+    retry: RetryPolicy,
+    stats: &mut CrawlStats,
+) -> Result<(), CrawlError> {
+    let mut state = State::load(&settings.base);
+    let mut failure = CrawlError::Unreachable {
+        attempts: retry.attempts,
+        reason: "no URL to crawl".into(),
+    };
+    let mut last_request: Option<Instant> = None;
+    for attempt in 0..retry.attempts {
+        if attempt > 0 {
+            tokio::time::sleep(retry.delay).await;
+            info!(attempt = attempt + 1, of = retry.attempts, "retrying");
+        }
+        for url in urls {
+            if let (Some(gap), Some(last)) = (settings.request_gap, last_request) {
+                tokio::time::sleep_until((last + gap).into()).await;
+            }
+            let started = Instant::now();
+            last_request = Some(started);
+            let fetched = fetch(
+                client,
+                &settings.source.latest_url(url),
+                &state,
+                settings.fetch_options(),
+            )
+            .instrument(info_span!("fetch", %url))
+            .await?;
+            let bytes = match &fetched {
+                Fetched::Page(page) => page.body.len(),
+                _ => 0,
+            };
+            stats.fetched(started.elapsed(), bytes);
+            let page = match fetched {
+                Fetched::Unavailable(reason) => {
+                    warn!(%url, %reason, "request failed");
+                    failure = CrawlError::Unreachable {
+                        attempts: retry.attempts,
+                        reason: format!("{}: {}", url, reason),
+                    };
+                    continue;
+                }
+                Fetched::NotModified => {
+                    info!(%url, "not modified");
+                    stats.not_modified();
+                    let observed_at = archive::published_observed_at(&settings.base);
+                    beat(settings, observed_at.clone());
+                    return Err(CrawlError::Unchanged {
+                        observed_at: observed_at.unwrap_or_default(),
+                    });
+                }
+                Fetched::Page(page) => page,
+            };
+            match process_page(settings, &page, state.observed_at.as_deref(), stats).await? {
+                Outcome::Down(reason) => {
+                    warn!(%url, %reason, "KMA is not serving observations");
+                    failure = CrawlError::Unreachable {
+                        attempts: retry.attempts,
+                        reason: format!("{}: {}", url, reason),
+                    };
+                    continue;
+                }
+                Outcome::Retry(reason) => {
+                    warn!(%url, %reason, "unusable page");
+                    failure = CrawlError::NoUsablePage {
+                        attempts: retry.attempts,
+                        reason: format!("{}: {}", url, reason),
+                    };
+                    continue;
+                }
+                Outcome::Unchanged => {
+                    info!(%url, observed_at = stats.observed_at.as_deref(), "page has not advanced");
+                    beat(settings, stats.observed_at.clone());
+                    return Err(CrawlError::Unchanged {
+                        observed_at: stats.observed_at.clone().unwrap_or_default(),
+                    });
+                }
+                Outcome::Done(alerts) => {
+                    state.source = Some(page.url);
+                    state.observed_at = stats.observed_at.clone();
+                    state.etag = page.etag;
+                    state.last_modified = page.last_modified;
+                    if let Err(e) = state.save(&settings.base) {
+                        error!(error = %e, "saving crawl state failed");
+                    }
+                    beat(settings, stats.observed_at.clone());
+                    if let Some(alerts) = alerts.filter(|a| !a.alerts.is_empty()) {
+                        alert(settings, &alerts).await;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Err(failure)
+}
+
+/// Log each alert and write them to the sinks. A sink that fails does not
+/// fail the crawl, which is written already.
+async fn alert(settings: &Settings, alerts: &Alerts) {
+    for alert in &alerts.alerts {
+        warn!(
+            rule = %alert.rule,
+            status = ?alert.status,
+            station = alert.id,
+            value = %alert.value,
+            "alert"
+        );
+    }
+    if let Err(e) = sink::write_document(settings, alerts).await {
+        error!(error = %e, "writing alerts failed");
+    }
+}
+
+fn beat(settings: &Settings, observed_at: Option<String>) {
+    if let Err(e) = Heartbeat::now(observed_at).save(&settings.base) {
+        error!(error = %e, "writing heartbeat failed");
+    }
+}
@@ -0,0 +1,71 @@
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::atomic;
+
+/// Written next to `index.json` with `--emit deltas`.
+pub const DELTA_FILE: &str = "index.delta.json";
+
+/// Write the stations of `current` that changed since `previous`.
+///
+/// Every `full_every`th delta, and any without a previous document, carries
+/// all records instead so consumers can resynchronize. Deltas are numbered
+/// by `sequence` for consumers to notice ones they missed.
+pub fn write_delta(
+    base: &Path,
+    full_every: u64,
+    previous: Option<&Value>,
+    current: &Value,
+) -> std::io::Result<()> {
+    let path = base.join(DELTA_FILE);
+    let sequence = File::open(&path)
+        .ok()
+        .and_then(|f| serde_json::from_reader::<_, Value>(BufReader::new(f)).ok())
+        .and_then(|delta| delta["sequence"].as_u64())
+        .map_or(0, |sequence| sequence + 1);
+    let records = |doc: &Value| -> HashMap<u64, Value> {
+        doc["records"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|r| Some((r["id"].as_u64()?, r.clone())))
+            .collect()
+    };
+    let delta = match previous {
+        Some(previous) if !sequence.is_multiple_of(full_every) => {
+            let before = records(previous);
+            let after = records(current);
+            let changed: Vec<&Value> = current["records"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|r| r["id"].as_u64().and_then(|id| before.get(&id)) != Some(*r))
+                .collect();
+            let mut removed: Vec<u64> = before
+                .keys()
+                .filter(|id| !after.contains_key(id))
+                .copied()
+                .collect();
+            removed.sort_unstable();
+            json!({
+                "observed_at": current["observed_at"],
+                "since": previous["observed_at"],
+                "sequence": sequence,
+                "full": false,
+                "records": changed,
+                "removed": removed,
+            })
+        }
+        _ => json!({
+            "observed_at": current["observed_at"],
+            "sequence": sequence,
+            "full": true,
+            "records": current["records"],
+        }),
+    };
+    atomic::write(&path, &serde_json::to_vec(&delta)?)
+}
@@ -0,0 +1,269 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::quantity::{Celsius, HectoPascals};
+use crate::timezone;
+use crate::Record;
+
+/// Quantities computed from a record's own observations.
+///
+/// Each is only present when its inputs were observed and the formula
+/// applies to the conditions.
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Derived {
+    /// Magnus formula over temperature and humidity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dew_point: Option<Celsius>,
+    /// NWS heat index, from 26.7°C (80°F) up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heat_index: Option<Celsius>,
+    /// JAG/TI wind chill over the 10-minute mean wind, at 10°C and below
+    /// with wind of at least 4.8km/h.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wind_chill: Option<Celsius>,
+    /// 불쾌지수, unitless; KMA calls 68 and up uncomfortable for about half
+    /// of people and 80 and up for nearly everyone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discomfort_index: Option<Decimal>,
+    /// 체감온도 the way KMA defines it: from the wet-bulb temperature between
+    /// May and September, and as the wind chill the rest of the year.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apparent_temperature: Option<Celsius>,
+    /// Station pressure reduced to mean sea level, over the station height
+    /// and temperature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure_sea_level: Option<HectoPascals>,
+}
+
+/// Everything derivable from `record` observed at `observed_at`, or `None`
+/// when nothing is.
+pub fn derive(record: &Record, observed_at: &DateTime<FixedOffset>) -> Option<Derived> {
+    let t = record.temperature.and_then(Celsius::to_f64);
+    let rh = record.humidity.and_then(|d| d.to_f64());
+    let wind = record.wind10.velocity.and_then(|v| v.to_f64());
+    let pressure = record.atmospheric.and_then(HectoPascals::to_f64);
+    let meters = record
+        .height
+        .as_ref()
+        .filter(|h| h.unit == "m")
+        .map(|h| f64::from(h.value));
+    let derived = Derived {
+        dew_point: t
+            .zip(rh)
+            .and_then(|(t, rh)| dew_point(t, rh))
+            .and_then(Celsius::from_f64),
+        heat_index: t
+            .zip(rh)
+            .and_then(|(t, rh)| heat_index(t, rh))
+            .and_then(Celsius::from_f64),
+        wind_chill: t
+            .zip(wind)
+            .and_then(|(t, v)| wind_chill(t, v))
+            .and_then(Celsius::from_f64),
+        discomfort_index: t
+            .zip(rh)
+            .map(|(t, rh)| discomfort_index(t, rh))
+            .and_then(round),
+        apparent_temperature: if (5..=9)
+            .contains(&observed_at.with_timezone(&timezone::kst()).month())
+        {
+            t.zip(rh)
+                .map(|(t, rh)| summer_apparent(t, rh))
+                .and_then(Celsius::from_f64)
+        } else {
+            t.zip(wind)
+                .and_then(|(t, v)| wind_chill(t, v))
+                .and_then(Celsius::from_f64)
+        },
+        pressure_sea_level: pressure
+            .zip(meters)
+            .zip(t)
+            .map(|((p, h), t)| sea_level_pressure(p, h, t))
+            .and_then(HectoPascals::from_f64),
+    };
+    let any = derived.dew_point.is_some()
+        || derived.heat_index.is_some()
+        || derived.wind_chill.is_some()
+        || derived.discomfort_index.is_some()
+        || derived.apparent_temperature.is_some()
+        || derived.pressure_sea_level.is_some();
+    any.then_some(derived)
+}
+
+/// Tenths, as KMA reports its measurements.
+fn round(value: f64) -> Option<Decimal> {
+    Decimal::from_f64(value).map(|d| d.round_dp(1))
+}
+
+/// Dew point from temperature (°C) and relative humidity (%).
+fn dew_point(t: f64, rh: f64) -> Option<f64> {
+    const A: f64 = 17.62;
+    const B: f64 = 243.12;
+    if rh <= 0.0 || rh > 100.0 {
+        return None;
+    }
+    let gamma = (rh / 100.0).ln() + A * t / (B + t);
+    Some(B * gamma / (A - gamma))
+}
+
+/// Heat index from temperature (°C) and relative humidity (%).
+fn heat_index(t: f64, rh: f64) -> Option<f64> {
+    let f = t * 9.0 / 5.0 + 32.0;
+    if f < 80.0 {
+        return None;
+    }
+    let simple = 0.5 * (f + 61.0 + (f - 68.0) * 1.2 + rh * 0.094);
+    let hi = if (simple + f) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.04901523 * f + 10.14333127 * rh
+            - 0.22475541 * f * rh
+            - 0.00683783 * f * f
+            - 0.05481717 * rh * rh
+            + 0.00122874 * f * f * rh
+            + 0.00085282 * f * rh * rh
+            - 0.00000199 * f * f * rh * rh;
+        if rh < 13.0 && f <= 112.0 {
+            hi -= (13.0 - rh) / 4.0 * ((17.0 - (f - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && f <= 87.0 {
+            hi += (rh - 85.0) / 10.0 * ((87.0 - f) / 5.0);
+        }
+        hi
+    };
+    Some((hi - 32.0) * 5.0 / 9.0)
+}
+
+/// Wind chill from temperature (°C) and wind speed (m/s).
+fn wind_chill(t: f64, wind: f64) -> Option<f64> {
+    let kmh = wind * 3.6;
+    if t > 10.0 || kmh < 4.8 {
+        return None;
+    }
+    let v = kmh.powf(0.16);
+    Some(13.12 + 0.6215 * t - 11.37 * v + 0.3965 * t * v)
+}
+
+/// Discomfort index from temperature (°C) and relative humidity (%).
+fn discomfort_index(t: f64, rh: f64) -> f64 {
+    0.81 * t + 0.01 * rh * (0.99 * t - 14.3) + 46.3
+}
+
+/// KMA summer apparent temperature from temperature (°C) and relative
+/// humidity (%), through Stull's wet-bulb approximation.
+fn summer_apparent(t: f64, rh: f64) -> f64 {
+    let tw = t * (0.151977 * (rh + 8.313659).sqrt()).atan() + (t + rh).atan()
+        - (rh - 1.676331).atan()
+        + 0.00391838 * rh.powf(1.5) * (0.023101 * rh).atan()
+        - 4.686035;
+    -0.2442 + 0.55399 * tw + 0.45535 * t - 0.0022 * tw * tw + 0.00278 * tw * t + 3.0
+}
+
+/// Standard lapse rate of the atmosphere in °C per meter.
+pub(crate) const LAPSE_RATE: f64 = 0.0065;
+
+/// Barometric reduction of station pressure (hPa) at `height` meters with
+/// temperature `t` (°C), assuming the standard lapse rate.
+pub(crate) fn sea_level_pressure(p: f64, height: f64, t: f64) -> f64 {
+    p / pressure_ratio(height, t)
+}
+
+/// Station pressure (hPa) at `height` meters with temperature `t` (°C) from
+/// sea-level pressure `p0`, the inverse of `sea_level_pressure`.
+pub(crate) fn station_pressure(p0: f64, height: f64, t: f64) -> f64 {
+    p0 * pressure_ratio(height, t)
+}
+
+fn pressure_ratio(height: f64, t: f64) -> f64 {
+    let lapse = LAPSE_RATE * height;
+    (1.0 - lapse / (t + lapse + 273.15)).powf(5.257)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    fn celsius(fahrenheit: f64) -> f64 {
+        (fahrenheit - 32.0) * 5.0 / 9.0
+    }
+
+    #[test]
+    fn dew_point_of_the_magnus_formula() {
+        assert_close(dew_point(20.0, 50.0).unwrap(), 9.3, 0.05);
+        assert_close(dew_point(30.0, 70.0).unwrap(), 23.9, 0.05);
+        // Saturated air is at its dew point.
+        assert_close(dew_point(0.0, 100.0).unwrap(), 0.0, 1e-9);
+        assert_eq!(dew_point(20.0, 0.0), None);
+        assert_eq!(dew_point(20.0, 101.0), None);
+    }
+
+    #[test]
+    fn heat_index_of_the_nws_table() {
+        // 90 °F at 70% is 106 °F in the table of the National Weather
+        // Service, 80 °F at 40% is 80 °F.
+        assert_close(
+            heat_index(celsius(90.0), 70.0).unwrap(),
+            celsius(106.0),
+            0.1,
+        );
+        assert_close(heat_index(celsius(80.0), 40.0).unwrap(), celsius(80.0), 0.3);
+        // The dry adjustment takes 100 °F at 10% down to 94 °F.
+        assert_close(
+            heat_index(celsius(100.0), 10.0).unwrap(),
+            celsius(94.1),
+            0.1,
+        );
+        // Below 80 °F there is no heat index, however humid.
+        assert_eq!(heat_index(26.0, 90.0), None);
+    }
+
+    #[test]
+    fn wind_chill_of_the_canadian_table() {
+        // -20 °C in a 30 km/h wind feels like -33 °C, -10 °C in 20 km/h like
+        // -18 °C.
+        assert_close(wind_chill(-20.0, 30.0 / 3.6).unwrap(), -32.6, 0.05);
+        assert_close(wind_chill(-10.0, 20.0 / 3.6).unwrap(), -17.9, 0.05);
+        // Not defined above 10 °C nor in winds below 4.8 km/h.
+        assert_eq!(wind_chill(11.0, 10.0), None);
+        assert_eq!(wind_chill(0.0, 1.0), None);
+    }
+
+    #[test]
+    fn discomfort_index_of_kma() {
+        // KMA calls 80 and over very high.
+        assert_close(discomfort_index(30.0, 80.0), 82.9, 0.05);
+        assert_close(discomfort_index(20.0, 50.0), 65.25, 1e-9);
+    }
+
+    #[test]
+    fn summer_apparent_through_stull_wet_bulb() {
+        // Through Stull's wet-bulb temperature of 13.7 °C for 20 °C at 50%.
+        assert_close(summer_apparent(20.0, 50.0), 19.8, 0.05);
+        assert_close(summer_apparent(33.0, 60.0), 33.5, 0.05);
+    }
+
+    #[test]
+    fn sea_level_pressure_of_the_standard_atmosphere() {
+        // The standard atmosphere has 898.76 hPa and 8.5 °C at 1000 m.
+        assert_close(sea_level_pressure(898.76, 1000.0, 8.5), 1013.25, 0.1);
+        assert_close(sea_level_pressure(1013.25, 0.0, 15.0), 1013.25, 1e-9);
+        assert_close(
+            station_pressure(sea_level_pressure(925.5, 772.57, 10.0), 772.57, 10.0),
+            925.5,
+            1e-9,
+        );
+    }
+}
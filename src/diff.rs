@@ -0,0 +1,184 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use rust_decimal::Decimal;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::nearest::{label, unit};
+
+#[derive(Serialize)]
+struct Diff {
+    from: Value,
+    to: Value,
+    /// Stations only in the second snapshot.
+    appeared: Vec<Station>,
+    /// Stations only in the first snapshot.
+    disappeared: Vec<Station>,
+    changed: Vec<Change>,
+}
+
+#[derive(Serialize)]
+struct Station {
+    id: u64,
+    name: String,
+}
+
+/// How one station differs between the snapshots; unchanged fields are left out.
+#[derive(Serialize)]
+struct Change {
+    id: u64,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature_delta: Option<Decimal>,
+    /// Rain that fell in between, from the growth of `rainday`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_rain: Option<Decimal>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    rain_started: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    rain_stopped: bool,
+    /// 10-minute wind direction before and after, when it turned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wind_shift: Option<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wind_delta: Option<Decimal>,
+}
+
+pub fn command() -> Command {
+    Command::new("diff")
+        .about("report how the stations changed between two snapshots")
+        .arg(arg!(<a> "earlier snapshot").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(<b> "later snapshot").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--json "print the changes as JSON"))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let a = read(matches.get_one::<PathBuf>("a").unwrap())?;
+    let b = read(matches.get_one::<PathBuf>("b").unwrap())?;
+    let (before, after) = (by_id(&a), by_id(&b));
+
+    let station = |id: &u64, record: &Value| Station {
+        id: *id,
+        name: label(record),
+    };
+    let diff = Diff {
+        from: a["observed_at"].clone(),
+        to: b["observed_at"].clone(),
+        appeared: after
+            .iter()
+            .filter(|(id, _)| !before.contains_key(id))
+            .map(|(id, r)| station(id, r))
+            .collect(),
+        disappeared: before
+            .iter()
+            .filter(|(id, _)| !after.contains_key(id))
+            .map(|(id, r)| station(id, r))
+            .collect(),
+        changed: after
+            .iter()
+            .filter_map(|(id, now)| compare(*id, before.get(id)?, now))
+            .collect(),
+    };
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string(&diff)?);
+        return Ok(());
+    }
+    println!(
+        "{} -> {}",
+        diff.from.as_str().unwrap_or("?"),
+        diff.to.as_str().unwrap_or("?")
+    );
+    for s in &diff.appeared {
+        println!("+ {:>4} {}", s.id, s.name);
+    }
+    for s in &diff.disappeared {
+        println!("- {:>4} {}", s.id, s.name);
+    }
+    let (temperature, wind, rain) = (unit(&b, "temperature"), unit(&b, "wind"), unit(&b, "rain"));
+    for c in &diff.changed {
+        let mut parts = Vec::new();
+        if let Some(delta) = c.temperature_delta {
+            parts.push(format!("temperature {:+}{}", delta, temperature));
+        }
+        if c.rain_started {
+            parts.push("rain started".to_string());
+        }
+        if c.rain_stopped {
+            parts.push("rain stopped".to_string());
+        }
+        if let Some(amount) = c.new_rain {
+            parts.push(format!("{}{} new rain", amount, rain));
+        }
+        if let Some((from, to)) = &c.wind_shift {
+            parts.push(format!("wind {} -> {}", from, to));
+        }
+        if let Some(delta) = c.wind_delta {
+            parts.push(format!("wind {:+}{}", delta, wind));
+        }
+        println!("~ {:>4} {}: {}", c.id, c.name, parts.join(", "));
+    }
+    println!(
+        "{} appeared, {} disappeared, {} changed",
+        diff.appeared.len(),
+        diff.disappeared.len(),
+        diff.changed.len()
+    );
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+fn by_id(doc: &Value) -> BTreeMap<u64, &Value> {
+    doc["records"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| Some((r["id"].as_u64()?, r)))
+        .collect()
+}
+
+/// The change of a station, or `None` when nothing compared changed.
+fn compare(id: u64, before: &Value, now: &Value) -> Option<Change> {
+    let delta = |pointer: &str| {
+        let value = |doc: &Value| {
+            doc.pointer(pointer)
+                .and_then(|v| serde_json::from_value::<Decimal>(v.clone()).ok())
+        };
+        Some(value(now)? - value(before)?).filter(|d| !d.is_zero())
+    };
+    let raining = |doc: &Value| doc["rain"]["is_raining"] == "Rain";
+    let direction = |doc: &Value| doc["wind10"]["direction_text"].as_str().map(String::from);
+    let change = Change {
+        id,
+        name: label(now),
+        temperature_delta: delta("/temperature"),
+        // A drop means `rainday` restarted at midnight.
+        new_rain: delta("/rain/rainday").map(|d| match d {
+            d if d > Decimal::ZERO => d,
+            _ => serde_json::from_value(now["rain"]["rainday"].clone()).unwrap_or_default(),
+        }),
+        rain_started: !raining(before) && raining(now),
+        rain_stopped: raining(before) && !raining(now),
+        wind_shift: direction(before)
+            .zip(direction(now))
+            .filter(|(from, to)| from != to),
+        wind_delta: delta("/wind10/velocity"),
+    };
+    let changed = change.temperature_delta.is_some()
+        || change.new_rain.is_some_and(|d| !d.is_zero())
+        || change.rain_started
+        || change.rain_stopped
+        || change.wind_shift.is_some()
+        || change.wind_delta.is_some();
+    changed.then_some(change)
+}
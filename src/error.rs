@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::budget::BudgetExceeded;
+
+/// Everything that can end a crawl cycle.
+#[derive(Debug, Error)]
+pub enum CrawlError {
+    #[cfg(feature = "fetch")]
+    #[error("fetching {url}: {source}")]
+    Fetch { url: String, source: reqwest::Error },
+    #[error("undecodable page: {0}")]
+    Decode(String),
+    #[error("invalid selector `{0}`")]
+    Selector(&'static str),
+    #[error("no observation time (span.ehead) on the page")]
+    MissingTimestamp,
+    #[error("observation time `{0}` is not in YYYY.MM.DD.HH:MM form")]
+    Timestamp(String),
+    #[error("strict mode: {0} data rows could not be parsed")]
+    Strict(usize),
+    #[error("KMA unreachable after {attempts} attempts, last: {reason}")]
+    Unreachable { attempts: usize, reason: String },
+    #[error("no usable page after {attempts} attempts, last: {reason}")]
+    NoUsablePage { attempts: usize, reason: String },
+    #[error("observation {observed_at} is older than the published {published}, not writing")]
+    Stale {
+        observed_at: String,
+        published: String,
+    },
+    #[error("writing to {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("the page still shows {observed_at}, which was already written")]
+    Unchanged { observed_at: String },
+    #[error("{sink} sink: {reason}")]
+    Sink { sink: &'static str, reason: String },
+    #[error("another crawl holds the lock on {}", .0.display())]
+    Locked(PathBuf),
+    #[error(transparent)]
+    Budget(#[from] BudgetExceeded),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Exit status of a crawl, so orchestrators can tell "KMA down" from "disk full".
+pub const EXIT_NETWORK: i32 = 1;
+pub const EXIT_PARSE: i32 = 2;
+pub const EXIT_WRITE: i32 = 3;
+pub const EXIT_STALE: i32 = 4;
+pub const EXIT_LOCKED: i32 = 5;
+pub const EXIT_UNCHANGED: i32 = 6;
+/// Bad flags or configuration, or a subcommand that failed otherwise; the
+/// `EX_USAGE` of sysexits.h, clear of the codes of a crawl.
+pub const EXIT_USAGE: i32 = 64;
+
+impl CrawlError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            #[cfg(feature = "fetch")]
+            CrawlError::Fetch { .. } => EXIT_NETWORK,
+            CrawlError::Unreachable { .. } => EXIT_NETWORK,
+            CrawlError::Decode(_)
+            | CrawlError::Selector(_)
+            | CrawlError::MissingTimestamp
+            | CrawlError::Timestamp(_)
+            | CrawlError::Strict(_)
+            | CrawlError::NoUsablePage { .. } => EXIT_PARSE,
+            CrawlError::Write { .. } | CrawlError::Sink { .. } | CrawlError::Io(_) => EXIT_WRITE,
+            CrawlError::Stale { .. } => EXIT_STALE,
+            CrawlError::Locked(_) => EXIT_LOCKED,
+            CrawlError::Unchanged { .. } => EXIT_UNCHANGED,
+            CrawlError::Budget(BudgetExceeded::Write(..)) => EXIT_WRITE,
+            CrawlError::Budget(_) => EXIT_NETWORK,
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use clap::builder::PossibleValuesParser;
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use serde_json::Value;
+
+use std::fs::File;
+use std::io::{stdout, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{archive, compact};
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Int,
+    Real,
+    Text,
+}
+
+/// An output column and where its value sits in the document.
+struct Column {
+    name: &'static str,
+    pointer: &'static str,
+    kind: Kind,
+}
+
+const fn column(name: &'static str, pointer: &'static str, kind: Kind) -> Column {
+    Column {
+        name,
+        pointer,
+        kind,
+    }
+}
+
+/// Columns taken from the snapshot itself.
+const SNAPSHOT_COLUMNS: [Column; 2] = [
+    column("observed_at", "/observed_at", Kind::Text),
+    column("source", "/source", Kind::Text),
+];
+
+/// Columns taken from each record. Fields a profile leaves out are null.
+const RECORD_COLUMNS: [Column; 49] = [
+    column("id", "/id", Kind::Int),
+    column("name", "/name", Kind::Text),
+    column("name_en", "/name_en", Kind::Text),
+    column("latitude", "/station/latitude", Kind::Real),
+    column("longitude", "/station/longitude", Kind::Real),
+    column("elevation", "/station/elevation", Kind::Real),
+    column("admin_code", "/station/admin_code", Kind::Text),
+    column("height", "/height/value", Kind::Int),
+    column("is_raining", "/rain/is_raining", Kind::Text),
+    column("rain15", "/rain/rain15", Kind::Real),
+    column("rain60", "/rain/rain60", Kind::Real),
+    column("rain3h", "/rain/rain3h", Kind::Real),
+    column("rain6h", "/rain/rain6h", Kind::Real),
+    column("rain12h", "/rain/rain12h", Kind::Real),
+    column("rainday", "/rain/rainday", Kind::Real),
+    column("rain_intensity", "/rain/intensity", Kind::Text),
+    column("temperature", "/temperature", Kind::Real),
+    column("wind1_direction_code", "/wind1/direction_code", Kind::Real),
+    column("wind1_direction_text", "/wind1/direction_text", Kind::Text),
+    column("wind1_velocity", "/wind1/velocity", Kind::Real),
+    column("wind1_beaufort", "/wind1/beaufort", Kind::Int),
+    column("wind1_bearing", "/wind1/bearing", Kind::Real),
+    column(
+        "wind10_direction_code",
+        "/wind10/direction_code",
+        Kind::Real,
+    ),
+    column(
+        "wind10_direction_text",
+        "/wind10/direction_text",
+        Kind::Text,
+    ),
+    column("wind10_velocity", "/wind10/velocity", Kind::Real),
+    column("wind10_beaufort", "/wind10/beaufort", Kind::Int),
+    column("wind10_bearing", "/wind10/bearing", Kind::Real),
+    column("humidity", "/humidity", Kind::Real),
+    column("atmospheric", "/atmospheric", Kind::Real),
+    column("address", "/address", Kind::Text),
+    column("province", "/region/province", Kind::Text),
+    column("province_code", "/region/province_code", Kind::Text),
+    column("city", "/region/city", Kind::Text),
+    column("district", "/region/district", Kind::Text),
+    column("region_code", "/region/code", Kind::Text),
+    column("spatial_flags", "/spatial_flags", Kind::Text),
+    column("quality", "/quality", Kind::Text),
+    column("dew_point", "/derived/dew_point", Kind::Real),
+    column("heat_index", "/derived/heat_index", Kind::Real),
+    column("wind_chill", "/derived/wind_chill", Kind::Real),
+    column("discomfort_index", "/derived/discomfort_index", Kind::Real),
+    column(
+        "apparent_temperature",
+        "/derived/apparent_temperature",
+        Kind::Real,
+    ),
+    column(
+        "pressure_sea_level",
+        "/derived/pressure_sea_level",
+        Kind::Real,
+    ),
+    column("pressure_tendency", "/trend/pressure_tendency", Kind::Real),
+    column("rain_started", "/trend/rain_started", Kind::Text),
+    column("rain_stopped", "/trend/rain_stopped", Kind::Text),
+    column("sunrise", "/astro/sunrise", Kind::Text),
+    column("sunset", "/astro/sunset", Kind::Text),
+    column("daylight", "/astro/daylight", Kind::Text),
+];
+
+fn columns() -> impl Iterator<Item = &'static Column> {
+    SNAPSHOT_COLUMNS.iter().chain(RECORD_COLUMNS.iter())
+}
+
+/// Destination of the flattened rows, one per record of every snapshot.
+pub trait Sink {
+    fn write_row(&mut self, row: &[Value]) -> Result<(), Error>;
+    fn finish(self: Box<Self>) -> Result<(), Error>;
+}
+
+fn formats() -> Vec<&'static str> {
+    #[cfg_attr(not(any(feature = "sqlite", feature = "parquet")), allow(unused_mut))]
+    let mut formats = vec!["csv"];
+    #[cfg(feature = "sqlite")]
+    formats.push("sqlite");
+    #[cfg(feature = "parquet")]
+    formats.push("parquet");
+    formats
+}
+
+pub fn command() -> Command {
+    Command::new("export")
+        .about("flatten retained snapshots and bundles into one table, one row per record")
+        .arg(
+            arg!(<base> "directory of crawl results, e.g. the base path the crawler writes to")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(arg!(<out> "file to write, or - for CSV on stdout"))
+        .arg(
+            arg!(--format <FORMAT> "table format; sqlite and parquet need the features of the same name")
+                .value_parser(PossibleValuesParser::new(formats()))
+                .default_value("csv"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let out = matches.get_one::<String>("out").unwrap();
+    let mut sink: Box<dyn Sink> = match matches.get_one::<String>("format").unwrap().as_str() {
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Box::new(to_sqlite::SqliteSink::create(Path::new(out))?),
+        #[cfg(feature = "parquet")]
+        "parquet" => Box::new(to_parquet::ParquetSink::create(Path::new(out))?),
+        _ if out == "-" => Box::new(CsvSink::new(Box::new(stdout().lock()))?),
+        _ => Box::new(CsvSink::new(Box::new(File::create(out)?))?),
+    };
+    let (mut snapshots, mut records) = (0, 0);
+    for input in inputs(base)? {
+        each_snapshot(&input, &mut |snapshot| {
+            snapshots += 1;
+            records += write_snapshot(sink.as_mut(), &snapshot)?;
+            Ok(())
+        })
+        .map_err(|e| format!("{}: {}", input.display(), e))?;
+    }
+    sink.finish()?;
+    eprintln!(
+        "exported {} records from {} snapshots to {}",
+        records, snapshots, out
+    );
+    Ok(())
+}
+
+/// Snapshots and daily bundles under `base`, oldest first.
+fn inputs(base: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut inputs: Vec<(String, PathBuf)> = archive::list(base)?
+        .into_iter()
+        .map(|s| (s.observed_at, s.path))
+        .collect();
+    // A bundle sorts before the snapshots of its day, which are all later.
+    inputs.extend(compact::bundles(base)?.into_iter().map(|b| (b.day, b.path)));
+    inputs.sort();
+    Ok(inputs.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Call `f` with every snapshot in `path`, holding one at a time.
+pub fn each_snapshot(
+    path: &Path,
+    f: &mut dyn FnMut(Value) -> Result<(), Error>,
+) -> Result<(), Error> {
+    if compact::is_bundle(path) {
+        compact::each_bundled(path, f)
+    } else {
+        f(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+/// Write a row per record of `snapshot`, returning how many there were.
+pub fn write_snapshot(sink: &mut dyn Sink, snapshot: &Value) -> Result<usize, Error> {
+    let records = snapshot
+        .get("records")
+        .and_then(Value::as_array)
+        .ok_or("not a crawl result: no records")?;
+    let mut row = Vec::with_capacity(SNAPSHOT_COLUMNS.len() + RECORD_COLUMNS.len());
+    for record in records {
+        row.clear();
+        row.extend(SNAPSHOT_COLUMNS.iter().map(|c| cell(snapshot, c)));
+        row.extend(RECORD_COLUMNS.iter().map(|c| cell(record, c)));
+        sink.write_row(&row)?;
+    }
+    Ok(records.len())
+}
+
+/// The value of `column` in `doc`, as a scalar of the column's kind or null.
+fn cell(doc: &Value, column: &Column) -> Value {
+    match (doc.pointer(column.pointer), column.kind) {
+        (None | Some(Value::Null), _) => Value::Null,
+        (Some(Value::Array(a)), _) if a.is_empty() => Value::Null,
+        (Some(v @ Value::Number(_)), Kind::Int | Kind::Real) => v.clone(),
+        (Some(Value::String(s)), Kind::Text) => Value::String(s.clone()),
+        (Some(v), _) => Value::String(v.to_string()),
+    }
+}
+
+struct CsvSink {
+    writer: csv::Writer<Box<dyn Write>>,
+}
+impl CsvSink {
+    fn new(out: Box<dyn Write>) -> Result<Self, Error> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(columns().map(|c| c.name))?;
+        Ok(CsvSink { writer })
+    }
+}
+impl Sink for CsvSink {
+    fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+        self.writer.write_record(row.iter().map(|v| match v {
+            Value::Null => String::new(),
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        }))?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub mod to_sqlite {
+    use rusqlite::types::Value as SqlValue;
+    use rusqlite::{params_from_iter, Connection};
+
+    use serde_json::Value;
+
+    use std::path::Path;
+
+    use super::{columns, Error, Kind, Sink};
+
+    /// Rows committed per transaction.
+    const BATCH_ROWS: usize = 10_000;
+
+    /// Rows go to an `observations` table keyed by time and station, so
+    /// exporting the same snapshots again replaces them instead of
+    /// duplicating them.
+    pub struct SqliteSink {
+        conn: Connection,
+        insert: String,
+        pending: usize,
+    }
+    impl SqliteSink {
+        pub fn create(path: &Path) -> Result<Self, Error> {
+            let conn = Connection::open(path)?;
+            let definitions: Vec<String> = columns()
+                .map(|c| {
+                    let kind = match c.kind {
+                        Kind::Int => "INTEGER",
+                        Kind::Real => "REAL",
+                        Kind::Text => "TEXT",
+                    };
+                    format!("{} {}", c.name, kind)
+                })
+                .collect();
+            conn.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS observations ({}, PRIMARY KEY (observed_at, id)); BEGIN",
+                definitions.join(", ")
+            ))?;
+            let names: Vec<&str> = columns().map(|c| c.name).collect();
+            let insert = format!(
+                "INSERT OR REPLACE INTO observations ({}) VALUES ({})",
+                names.join(", "),
+                vec!["?"; names.len()].join(", ")
+            );
+            Ok(SqliteSink {
+                conn,
+                insert,
+                pending: 0,
+            })
+        }
+    }
+    impl Sink for SqliteSink {
+        fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+            let values = row.iter().map(|v| match v {
+                Value::Null => SqlValue::Null,
+                Value::Number(n) => n
+                    .as_i64()
+                    .map(SqlValue::Integer)
+                    .unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap_or(f64::NAN))),
+                Value::String(s) => SqlValue::Text(s.clone()),
+                v => SqlValue::Text(v.to_string()),
+            });
+            self.conn
+                .prepare_cached(&self.insert)?
+                .execute(params_from_iter(values))?;
+            self.pending += 1;
+            if self.pending == BATCH_ROWS {
+                self.conn.execute_batch("COMMIT; BEGIN")?;
+                self.pending = 0;
+            }
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>) -> Result<(), Error> {
+            self.conn.execute_batch("COMMIT")?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod to_parquet {
+    use arrow_array::builder::{Float64Builder, Int64Builder, StringBuilder};
+    use arrow_array::{ArrayRef, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+
+    use serde_json::Value;
+
+    use std::fs::File;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::{columns, Error, Kind, Sink};
+
+    /// Rows buffered before they are written out as one record batch.
+    const BATCH_ROWS: usize = 8192;
+
+    enum Builder {
+        Int(Int64Builder),
+        Real(Float64Builder),
+        Text(StringBuilder),
+    }
+    impl Builder {
+        fn append(&mut self, value: &Value) {
+            match self {
+                Builder::Int(b) => b.append_option(value.as_i64()),
+                Builder::Real(b) => b.append_option(value.as_f64()),
+                Builder::Text(b) => b.append_option(value.as_str()),
+            }
+        }
+
+        fn finish(&mut self) -> ArrayRef {
+            match self {
+                Builder::Int(b) => Arc::new(b.finish()),
+                Builder::Real(b) => Arc::new(b.finish()),
+                Builder::Text(b) => Arc::new(b.finish()),
+            }
+        }
+    }
+
+    pub struct ParquetSink {
+        schema: SchemaRef,
+        writer: ArrowWriter<File>,
+        builders: Vec<Builder>,
+        pending: usize,
+    }
+    impl ParquetSink {
+        pub fn create(path: &Path) -> Result<Self, Error> {
+            let fields: Vec<Field> = columns()
+                .map(|c| {
+                    let kind = match c.kind {
+                        Kind::Int => DataType::Int64,
+                        Kind::Real => DataType::Float64,
+                        Kind::Text => DataType::Utf8,
+                    };
+                    Field::new(c.name, kind, true)
+                })
+                .collect();
+            let schema = Arc::new(Schema::new(fields));
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let writer =
+                ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))?;
+            let builders = columns()
+                .map(|c| match c.kind {
+                    Kind::Int => Builder::Int(Int64Builder::new()),
+                    Kind::Real => Builder::Real(Float64Builder::new()),
+                    Kind::Text => Builder::Text(StringBuilder::new()),
+                })
+                .collect();
+            Ok(ParquetSink {
+                schema,
+                writer,
+                builders,
+                pending: 0,
+            })
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            if self.pending > 0 {
+                let arrays = self.builders.iter_mut().map(Builder::finish).collect();
+                let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
+                self.writer.write(&batch)?;
+                self.pending = 0;
+            }
+            Ok(())
+        }
+    }
+    impl Sink for ParquetSink {
+        fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+            for (builder, value) in self.builders.iter_mut().zip(row) {
+                builder.append(value);
+            }
+            self.pending += 1;
+            if self.pending == BATCH_ROWS {
+                self.flush()?;
+            }
+            Ok(())
+        }
+
+        fn finish(mut self: Box<Self>) -> Result<(), Error> {
+            self.flush()?;
+            self.writer.close()?;
+            Ok(())
+        }
+    }
+}
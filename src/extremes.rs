@@ -0,0 +1,85 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use serde_json::{json, Map, Value};
+
+use std::path::PathBuf;
+
+use crate::archive;
+use crate::nearest::{label, show, unit};
+
+/// Ranked categories: name, value, unit field, and whether higher ranks first.
+const CATEGORIES: [(&str, &str, &str, bool); 4] = [
+    ("hottest", "/temperature", "temperature", true),
+    ("coldest", "/temperature", "temperature", false),
+    ("wettest", "/rain/rainday", "rain", true),
+    ("windiest", "/wind10/velocity", "wind", true),
+];
+
+pub fn command() -> Command {
+    Command::new("extremes")
+        .about("list the hottest, coldest, wettest and windiest stations of the latest crawl")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--n <N> "stations to list per category")
+                .value_parser(value_parser!(usize))
+                .default_value("5"),
+        )
+        .arg(arg!(--json "print the lists as JSON"))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let n = *matches.get_one::<usize>("n").unwrap();
+    let doc = archive::read_index(base)?;
+    let records = doc["records"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+
+    let mut report = Map::new();
+    report.insert("observed_at".to_string(), doc["observed_at"].clone());
+    if !doc["units"].is_null() {
+        report.insert("units".to_string(), doc["units"].clone());
+    }
+    if !matches.get_flag("json") {
+        println!("as of {}", doc["observed_at"].as_str().unwrap_or("?"));
+    }
+    for (category, pointer, field, descending) in CATEGORIES {
+        let mut ranked: Vec<(f64, &Value)> = records
+            .iter()
+            .filter_map(|r| Some((r.pointer(pointer)?.as_f64()?, r)))
+            .collect();
+        ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if descending {
+            ranked.reverse();
+        }
+        ranked.truncate(n);
+        if !matches.get_flag("json") {
+            println!("{}", category);
+            let unit = unit(&doc, field);
+            for (rank, (_, record)) in ranked.iter().enumerate() {
+                let value = show(record.pointer(pointer).unwrap(), &unit);
+                println!(
+                    "{:>3}. {:>8}  {:>4} {}",
+                    rank + 1,
+                    value,
+                    record["id"],
+                    label(record)
+                );
+            }
+        }
+        let ranked: Vec<Value> = ranked
+            .iter()
+            .map(|(value, record)| {
+                json!({
+                    "id": record["id"],
+                    "name": record["name"],
+                    "name_en": record["name_en"],
+                    "value": value,
+                })
+            })
+            .collect();
+        report.insert(category.to_string(), Value::from(ranked));
+    }
+    if matches.get_flag("json") {
+        println!("{}", Value::Object(report));
+    }
+    Ok(())
+}
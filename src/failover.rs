@@ -0,0 +1,44 @@
+use std::fs::{read, read_to_string, remove_file, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::archive::snapshot_path;
+use crate::{atomic, manifest};
+
+/// Observations written to the secondary path while the primary was down.
+const PENDING_FILE: &str = ".pending-reconcile";
+
+/// Remember that `observed_at` only made it to the secondary path.
+pub fn mark_pending(secondary: &Path, observed_at: &str) -> std::io::Result<()> {
+    let mut pending = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(secondary.join(PENDING_FILE))?;
+    writeln!(pending, "{}", observed_at)
+}
+
+/// Copy snapshots written during a failover back to the primary path.
+///
+/// Returns how many snapshots were copied. Snapshots the primary already has
+/// are left alone, and the pending list is cleared once everything is back.
+pub fn reconcile(secondary: &Path, primary: &Path) -> std::io::Result<usize> {
+    let pending = match read_to_string(secondary.join(PENDING_FILE)) {
+        Ok(pending) => pending,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut copied = 0;
+    for observed_at in pending.lines().filter(|l| !l.is_empty()) {
+        let from = snapshot_path(secondary, observed_at);
+        let to = snapshot_path(primary, observed_at);
+        if from.exists() && !to.exists() {
+            let body = read(&from)?;
+            atomic::write(&to, &body)?;
+            let file = to.file_name().unwrap().to_string_lossy();
+            manifest::add_snapshot(primary, &file, observed_at, &body)?;
+            copied += 1;
+        }
+    }
+    remove_file(secondary.join(PENDING_FILE))?;
+    Ok(copied)
+}
@@ -0,0 +1,97 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{CrawlResult, SkippedRow};
+
+/// Failures to inject on purpose, parsed from
+/// `fail=0.2,slow=0.1:3,corrupt=0.05` (rates in 0..=1, delay in seconds).
+pub struct FaultPlan {
+    fail: f64,
+    slow: f64,
+    slow_by: Duration,
+    corrupt: f64,
+    rng: AtomicU64,
+}
+impl Clone for FaultPlan {
+    fn clone(&self) -> Self {
+        FaultPlan {
+            rng: AtomicU64::new(self.rng.load(Ordering::Relaxed)),
+            ..*self
+        }
+    }
+}
+impl FromStr for FaultPlan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let mut plan = FaultPlan {
+            fail: 0.0,
+            slow: 0.0,
+            slow_by: Duration::from_secs(5),
+            corrupt: 0.0,
+            rng: AtomicU64::new(seed | 1),
+        };
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("`{}` is not key=value", part))?;
+            let rate = |v: &str| match v.parse::<f64>() {
+                Ok(r) if (0.0..=1.0).contains(&r) => Ok(r),
+                _ => Err(format!("`{}` is not a rate between 0 and 1", v)),
+            };
+            match key {
+                "fail" => plan.fail = rate(value)?,
+                "corrupt" => plan.corrupt = rate(value)?,
+                "slow" => {
+                    let (r, secs) = value.split_once(':').unwrap_or((value, "5"));
+                    plan.slow = rate(r)?;
+                    plan.slow_by = Duration::from_secs_f64(
+                        secs.parse::<f64>()
+                            .map_err(|_| format!("`{}` is not a delay in seconds", secs))?,
+                    );
+                }
+                _ => return Err(format!("unknown fault `{}`", key)),
+            }
+        }
+        Ok(plan)
+    }
+}
+impl FaultPlan {
+    /// xorshift64; good enough to decide which request to sabotage.
+    fn roll(&self) -> f64 {
+        let mut x = self.rng.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng.store(x, Ordering::Relaxed);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn should_fail(&self) -> bool {
+        self.roll() < self.fail
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        (self.roll() < self.slow).then_some(self.slow_by)
+    }
+
+    /// Turn a share of the parsed records into skipped rows, as if the page
+    /// had served them garbled.
+    pub fn corrupt(&self, result: &mut CrawlResult) {
+        let records = std::mem::take(&mut result.records);
+        for record in records {
+            if self.roll() < self.corrupt {
+                result.skipped.push(SkippedRow {
+                    reason: "fault injection: corrupted row".into(),
+                    cells: vec![record.id.to_string(), record.name],
+                });
+            } else {
+                result.records.push(record);
+            }
+        }
+    }
+}
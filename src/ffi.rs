@@ -0,0 +1,57 @@
+use std::ffi::{c_char, c_int, CStr, CString};
+
+use crate::error::EXIT_PARSE;
+use crate::parse_html;
+
+/// `html` or `json_out` was null.
+pub const WEATHER_CRAWL_INVALID_ARGUMENT: c_int = -1;
+
+/// Parse the AWS page `html` into the JSON `index.json` holds.
+///
+/// Returns 0 with the document in `*json_out`, or an exit code of the
+/// binary with the error message there instead. Either string is the
+/// caller's to release with `weather_crawl_free`.
+///
+/// # Safety
+///
+/// `html` must be a NUL-terminated string and `json_out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn weather_crawl_parse(
+    html: *const c_char,
+    json_out: *mut *mut c_char,
+) -> c_int {
+    if html.is_null() || json_out.is_null() {
+        return WEATHER_CRAWL_INVALID_ARGUMENT;
+    }
+    let parsed = match CStr::from_ptr(html).to_str() {
+        Ok(html) => parse_html(html)
+            .map_err(|e| (e.exit_code(), e.to_string()))
+            .and_then(|result| {
+                serde_json::to_string(&result).map_err(|e| (EXIT_PARSE, e.to_string()))
+            }),
+        Err(e) => Err((EXIT_PARSE, format!("page is not UTF-8: {}", e))),
+    };
+    let (code, text) = match parsed {
+        Ok(json) => (0, json),
+        Err(failure) => failure,
+    };
+    // Neither JSON nor our messages contain NUL, but a page may.
+    let text = CString::new(text).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        CString::new(&e.into_vec()[..end]).unwrap()
+    });
+    *json_out = text.into_raw();
+    code
+}
+
+/// Release a string `weather_crawl_parse` returned; null is ignored.
+///
+/// # Safety
+///
+/// `s` must come from `weather_crawl_parse` and not be released twice.
+#[no_mangle]
+pub unsafe extern "C" fn weather_crawl_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(feature = "cli")]
+use clap::{arg, value_parser, ArgMatches, Command};
+
+#[cfg(feature = "cli")]
+use serde_json::Value;
+
+use std::fs::{create_dir_all, File};
+#[cfg(feature = "cli")]
+use std::fs::{read, read_dir};
+#[cfg(feature = "cli")]
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+#[cfg(feature = "cli")]
+use std::path::PathBuf;
+
+use crate::CrawlResult;
+#[cfg(feature = "cli")]
+use crate::{charset, parse_page};
+
+const RAW_EXT: &str = "html";
+const PARSED_EXT: &str = "json";
+
+/// Save the fetched bytes and what `parse_page` made of them.
+///
+/// Produces `<dir>/<observed_at>.html` and `<dir>/<observed_at>.json`.
+pub fn record(dir: &Path, raw: &[u8], result: &CrawlResult) -> std::io::Result<()> {
+    create_dir_all(dir)?;
+    let stem = result.observed_at.to_rfc3339();
+    File::create(dir.join(format!("{}.{}", stem, RAW_EXT)))?.write_all(raw)?;
+    write_parsed(&dir.join(format!("{}.{}", stem, PARSED_EXT)), result)
+}
+
+fn write_parsed(path: &Path, result: &CrawlResult) -> std::io::Result<()> {
+    let mut parsed = File::create(path)?;
+    serde_json::to_writer_pretty(&mut parsed, result)?;
+    writeln!(parsed)
+}
+
+#[cfg(feature = "cli")]
+pub fn command() -> Command {
+    Command::new("replay")
+        .about("re-parse recorded fixtures and compare against the recorded results")
+        .hide(true)
+        .arg(arg!(<dir> "fixture directory").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--update "overwrite the recorded results with the current parser's output"))
+}
+
+#[cfg(feature = "cli")]
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = matches.get_one::<PathBuf>("dir").unwrap();
+    let update = matches.get_flag("update");
+    let mut raws: Vec<PathBuf> = read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some(RAW_EXT))
+        .collect();
+    raws.sort();
+    let mut changed = 0;
+    for raw in raws.iter() {
+        let expected_path = raw.with_extension(PARSED_EXT);
+        let expected: Option<Value> = File::open(&expected_path)
+            .ok()
+            .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok());
+        let source = expected
+            .as_ref()
+            .and_then(|v| v["source"].as_str())
+            .unwrap_or_default()
+            .to_string();
+        let html = charset::decode(&read(raw)?, None, false)?;
+        let result = parse_page(&source, &html)?;
+        let actual = serde_json::to_value(&result)?;
+        let name = raw.file_stem().unwrap_or_default().to_string_lossy();
+        if expected.as_ref() == Some(&actual) {
+            println!("ok       {}", name);
+            continue;
+        }
+        if update {
+            write_parsed(&expected_path, &result)?;
+            println!("updated  {}", name);
+        } else {
+            println!("changed  {}", name);
+            changed += 1;
+        }
+    }
+    if changed > 0 {
+        return Err(format!("{} of {} fixtures parse differently", changed, raws.len()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,350 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+use clap::{arg, ArgAction, ArgMatches, Command};
+
+use reqwest::Client;
+
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use tracing::{info, info_span, warn, Instrument};
+
+use std::collections::BTreeMap;
+
+use crate::quantity::{Celsius, MetersPerSecond};
+use crate::sink::{self, Document};
+use crate::source::{with_query, without_query};
+use crate::{archive, logging, openapi, timezone};
+
+/// 단기예보 of data.go.kr's VilageFcstInfoService.
+pub const FORECAST_URL: &str = "https://apis.data.go.kr/1360000/VilageFcstInfoService_2.0/getVilageFcst?pageNo=1&numOfRows=1000&dataType=JSON";
+
+/// Hours (KST) a 단기예보 is issued at; each is served from ten past.
+const ISSUES: [i64; 8] = [2, 5, 8, 11, 14, 17, 20, 23];
+
+/// Forecast grid point of each province's seat, under the short names of
+/// `region`.
+const REGIONS: [(&str, u32, u32); 17] = [
+    ("서울", 60, 127),
+    ("부산", 98, 76),
+    ("대구", 89, 90),
+    ("인천", 55, 124),
+    ("광주", 58, 74),
+    ("대전", 67, 100),
+    ("울산", 102, 84),
+    ("세종", 66, 103),
+    ("경기", 60, 121),
+    ("강원", 73, 134),
+    ("충북", 69, 107),
+    ("충남", 68, 100),
+    ("전북", 63, 89),
+    ("전남", 51, 67),
+    ("경북", 89, 91),
+    ("경남", 91, 77),
+    ("제주", 52, 38),
+];
+
+/// One 단기예보 issue for every requested grid point.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Forecast {
+    /// When KMA issued the forecast.
+    pub issued_at: DateTime<FixedOffset>,
+    pub source: String,
+    pub points: Vec<Point>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Point {
+    pub nx: u32,
+    pub ny: u32,
+    /// Region the point was asked for by, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub hours: Vec<Hour>,
+    pub days: Vec<Day>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Hour {
+    pub at: DateTime<FixedOffset>,
+    pub sky: Option<Sky>,
+    pub precipitation: Option<PrecipitationType>,
+    /// Probability of precipitation in %.
+    pub pop: Option<Decimal>,
+    pub temperature: Option<Celsius>,
+    /// Relative humidity in %.
+    pub humidity: Option<Decimal>,
+    pub wind_speed: Option<MetersPerSecond>,
+    /// Degrees clockwise from north the wind blows from.
+    pub wind_direction: Option<Decimal>,
+}
+
+/// Daily extremes, forecast for 06:00 and 15:00.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub min: Option<Celsius>,
+    pub max: Option<Celsius>,
+}
+
+/// `SKY` codes.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum Sky {
+    /// 맑음.
+    Clear,
+    /// 구름많음.
+    MostlyCloudy,
+    /// 흐림.
+    Overcast,
+}
+
+/// `PTY` codes.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum PrecipitationType {
+    None,
+    Rain,
+    RainAndSnow,
+    Snow,
+    Shower,
+}
+
+pub fn command() -> Command {
+    Command::new("forecast")
+        .about("fetch KMA's short-term forecast (단기예보) for grid points or regions")
+        .args(crate::cli::crawl_args())
+        .arg(
+            arg!(--grid <NX_NY> "forecast grid point, e.g. 60,127")
+                .value_parser(parse_grid)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            arg!(--region <NAME> "province whose seat to forecast, e.g. 서울")
+                .value_parser(REGIONS.map(|(name, _, _)| name))
+                .action(ArgAction::Append),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = crate::cli::settings_from(matches)?;
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let key = matches
+        .get_one::<String>("api-key")
+        .ok_or("forecast needs --api-key")?;
+    let mut points: Vec<(u32, u32, Option<String>)> = matches
+        .get_many::<(u32, u32)>("grid")
+        .unwrap_or_default()
+        .map(|&(nx, ny)| (nx, ny, None))
+        .collect();
+    for name in matches.get_many::<String>("region").unwrap_or_default() {
+        let (_, nx, ny) = REGIONS.iter().find(|(n, _, _)| n == name).unwrap();
+        points.push((*nx, *ny, Some(name.clone())));
+    }
+    if points.is_empty() {
+        return Err("give at least one --grid or --region".into());
+    }
+    let url = matches
+        .get_many::<String>("url")
+        .and_then(|mut urls| urls.next().cloned())
+        .unwrap_or_else(|| FORECAST_URL.to_string());
+    let (date, time) = latest_issue();
+    let url = with_query(
+        &url,
+        &[
+            ("serviceKey", key),
+            ("base_date", &date),
+            ("base_time", &time),
+        ],
+    );
+    let client = crate::cli::client_from(matches)?;
+    let issued_at = NaiveDateTime::parse_from_str(&format!("{}{}", date, time), "%Y%m%d%H%M")?
+        .and_local_timezone(timezone::kst())
+        .unwrap();
+    let mut forecast = Forecast {
+        issued_at,
+        source: without_query(&url, "serviceKey"),
+        points: Vec::new(),
+    };
+    for (nx, ny, region) in points {
+        let url = with_query(&url, &[("nx", &nx.to_string()), ("ny", &ny.to_string())]);
+        let body = fetch(&client, &url)
+            .instrument(info_span!("forecast", nx, ny))
+            .await?;
+        let mut point = parse(&body)?;
+        point.nx = nx;
+        point.ny = ny;
+        point.region = region;
+        forecast.points.push(point);
+    }
+    info!(issued_at = %forecast.issued_at, points = forecast.points.len(), "forecast fetched");
+    Ok(sink::write_document(&settings, &forecast).await?)
+}
+
+fn parse_grid(s: &str) -> Result<(u32, u32), String> {
+    let (nx, ny) = s
+        .split_once(',')
+        .ok_or_else(|| format!("`{}` is not NX,NY", s))?;
+    let coordinate = |c: &str| {
+        c.trim()
+            .parse::<u32>()
+            .map_err(|e| format!("`{}`: {}", c, e))
+    };
+    Ok((coordinate(nx)?, coordinate(ny)?))
+}
+
+/// `base_date` and `base_time` of the newest issue served.
+fn latest_issue() -> (String, String) {
+    let now = archive::minute_at(&Utc::now().fixed_offset()) - 10;
+    let (day, hour) = (now.div_euclid(24 * 60), now.rem_euclid(24 * 60) / 60);
+    let (day, hour) = match ISSUES.iter().rev().find(|issue| **issue <= hour) {
+        Some(issue) => (day, *issue),
+        None => (day - 1, ISSUES[ISSUES.len() - 1]),
+    };
+    let stamp = archive::format_minute(day * 24 * 60);
+    let date: String = stamp[..10].chars().filter(char::is_ascii_digit).collect();
+    (date, format!("{:02}00", hour))
+}
+
+async fn fetch(client: &Client, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let response = client.get(url).send().await?.error_for_status()?;
+    Ok(response.text().await?)
+}
+
+/// The hours and days of one grid point's response.
+fn parse(body: &str) -> Result<Point, Box<dyn std::error::Error>> {
+    let items = openapi::items(body)?;
+    if items.is_empty() {
+        return Err("Open API has no forecast for the point yet".into());
+    }
+    let mut hours: BTreeMap<String, Hour> = BTreeMap::new();
+    let mut days: BTreeMap<String, Day> = BTreeMap::new();
+    for item in &items {
+        let field = |name: &str| item[name].as_str().unwrap_or_default();
+        let (date, time, value) = (field("fcstDate"), field("fcstTime"), field("fcstValue"));
+        let number = Decimal::from_str(value).ok();
+        match field("category") {
+            category @ ("TMN" | "TMX") => {
+                let Ok(day) = NaiveDate::parse_from_str(date, "%Y%m%d") else {
+                    warn!(date, "skipping forecast of an unreadable date");
+                    continue;
+                };
+                let entry = days.entry(date.to_string()).or_insert(Day {
+                    date: day,
+                    min: None,
+                    max: None,
+                });
+                if category == "TMN" {
+                    entry.min = number.map(Celsius);
+                } else {
+                    entry.max = number.map(Celsius);
+                }
+            }
+            category => {
+                let Some(at) =
+                    NaiveDateTime::parse_from_str(&format!("{}{}", date, time), "%Y%m%d%H%M")
+                        .ok()
+                        .and_then(|naive| naive.and_local_timezone(timezone::kst()).single())
+                else {
+                    warn!(date, time, "skipping forecast of an unreadable time");
+                    continue;
+                };
+                let hour = hours.entry(format!("{}{}", date, time)).or_insert(Hour {
+                    at,
+                    sky: None,
+                    precipitation: None,
+                    pop: None,
+                    temperature: None,
+                    humidity: None,
+                    wind_speed: None,
+                    wind_direction: None,
+                });
+                match category {
+                    "SKY" => hour.sky = sky(value),
+                    "PTY" => hour.precipitation = precipitation(value),
+                    "POP" => hour.pop = number,
+                    "TMP" => hour.temperature = number.map(Celsius),
+                    "REH" => hour.humidity = number,
+                    "WSD" => hour.wind_speed = number.map(MetersPerSecond),
+                    "VEC" => hour.wind_direction = number,
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(Point {
+        nx: 0,
+        ny: 0,
+        region: None,
+        hours: hours.into_values().collect(),
+        days: days.into_values().collect(),
+    })
+}
+
+fn sky(code: &str) -> Option<Sky> {
+    match code {
+        "1" => Some(Sky::Clear),
+        "3" => Some(Sky::MostlyCloudy),
+        "4" => Some(Sky::Overcast),
+        _ => None,
+    }
+}
+
+fn precipitation(code: &str) -> Option<PrecipitationType> {
+    match code {
+        "0" => Some(PrecipitationType::None),
+        "1" => Some(PrecipitationType::Rain),
+        "2" => Some(PrecipitationType::RainAndSnow),
+        "3" => Some(PrecipitationType::Snow),
+        "4" => Some(PrecipitationType::Shower),
+        _ => None,
+    }
+}
+
+impl Document for Forecast {
+    const FILE: &'static str = "forecast.json";
+
+    /// Hours go to a `forecasts` table keyed by issue, point and hour, next
+    /// to the `observations` of the crawl.
+    #[cfg(feature = "sqlite")]
+    fn to_sqlite(&self, path: &std::path::Path) -> rusqlite::Result<()> {
+        use rusqlite::params;
+
+        let mut conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS forecasts (issued_at TEXT, nx INTEGER, ny INTEGER, \
+             at TEXT, sky TEXT, precipitation TEXT, pop REAL, temperature REAL, humidity REAL, \
+             wind_speed REAL, wind_direction REAL, PRIMARY KEY (issued_at, nx, ny, at))",
+        )?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO forecasts VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )?;
+            let name = |v: serde_json::Value| v.as_str().map(String::from);
+            for point in &self.points {
+                for hour in &point.hours {
+                    insert.execute(params![
+                        self.issued_at.to_rfc3339(),
+                        point.nx,
+                        point.ny,
+                        hour.at.to_rfc3339(),
+                        hour.sky.and_then(|s| name(serde_json::to_value(s).ok()?)),
+                        hour.precipitation
+                            .and_then(|p| name(serde_json::to_value(p).ok()?)),
+                        hour.pop.and_then(|d| d.to_f64()),
+                        hour.temperature.and_then(Celsius::to_f64),
+                        hour.humidity.and_then(|d| d.to_f64()),
+                        hour.wind_speed.and_then(|v| v.to_f64()),
+                        hour.wind_direction.and_then(|d| d.to_f64()),
+                    ])?;
+                }
+            }
+        }
+        tx.commit()
+    }
+}
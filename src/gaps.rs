@@ -0,0 +1,117 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use serde::Serialize;
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+
+#[derive(Serialize)]
+struct Report {
+    from: String,
+    to: String,
+    cadence_minutes: i64,
+    expected: i64,
+    observed: usize,
+    missing: i64,
+    gaps: Vec<Gap>,
+}
+
+/// A run of consecutive observations that never made it to the archive.
+#[derive(Serialize)]
+struct Gap {
+    /// First missing observation.
+    from: String,
+    /// Last missing observation.
+    to: String,
+    missing: i64,
+}
+
+pub fn command() -> Command {
+    Command::new("gaps")
+        .about("report observations missing from the snapshots and bundles under <base>")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--cadence <MINUTES> "minutes expected between observations")
+                .value_parser(value_parser!(i64).range(1..))
+                .default_value("1"),
+        )
+        .arg(arg!(--from <TIME> "start of the period to check [default: the first observation]"))
+        .arg(arg!(--to <TIME> "end of the period to check [default: the last observation]"))
+        .arg(arg!(--json "print the report as JSON"))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let cadence = *matches.get_one::<i64>("cadence").unwrap();
+    let bound = |name: &str| -> Result<Option<i64>, String> {
+        match matches.get_one::<String>(name) {
+            Some(time) => archive::minute_of(time)
+                .map(Some)
+                .ok_or_else(|| format!("`{}` is not a YYYY-MM-DDTHH:MM time", time)),
+            None => Ok(None),
+        }
+    };
+    let (from, to) = (bound("from")?, bound("to")?);
+
+    let observed = observed_minutes(base)?;
+    let from = match from.or_else(|| observed.first().copied()) {
+        Some(from) => from,
+        None => return Err(format!("no observations under {}", base.display()).into()),
+    };
+    let to = to.or_else(|| observed.last().copied()).unwrap_or(from);
+    if to < from {
+        return Err("--to is before --from".into());
+    }
+
+    // The period's bounds count as seen so that missing edges show up as gaps.
+    let seen: Vec<i64> = observed.range(from..=to).copied().collect();
+    let mut gaps = Vec::new();
+    let mut previous = from - cadence;
+    for minute in seen.iter().copied().chain([to + cadence]) {
+        let missing = (minute - previous) / cadence - 1;
+        if missing > 0 {
+            gaps.push(Gap {
+                from: archive::format_minute(previous + cadence),
+                to: archive::format_minute(previous + missing * cadence),
+                missing,
+            });
+        }
+        previous = minute;
+    }
+    let report = Report {
+        from: archive::format_minute(from),
+        to: archive::format_minute(to),
+        cadence_minutes: cadence,
+        expected: (to - from) / cadence + 1,
+        observed: seen.len(),
+        missing: gaps.iter().map(|g| g.missing).sum(),
+        gaps,
+    };
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string(&report)?);
+        return Ok(());
+    }
+    for gap in &report.gaps {
+        println!("{} .. {}  {} missing", gap.from, gap.to, gap.missing);
+    }
+    println!(
+        "{} gaps, {} of {} observations missing between {} and {}",
+        report.gaps.len(),
+        report.missing,
+        report.expected,
+        report.from,
+        report.to
+    );
+    Ok(())
+}
+
+/// Minutes with an observation under `base`, in snapshots or in bundles.
+pub fn observed_minutes(base: &Path) -> Result<BTreeSet<i64>, Box<dyn std::error::Error>> {
+    Ok(archive::retained(base)?
+        .iter()
+        .filter_map(|s| archive::minute_of(&s.observed_at))
+        .collect())
+}
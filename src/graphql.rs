@@ -0,0 +1,257 @@
+use async_graphql::dynamic::{
+    Field, FieldFuture, FieldValue, InputValue, Object, ResolverContext, Scalar, Schema,
+    SchemaError, TypeRef,
+};
+
+use axum::extract::State;
+use axum::routing::get;
+use axum::{Json, Router};
+
+use serde_json::{Map, Value};
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::publish::Profile;
+use crate::schema;
+use crate::serve::{in_region, records, Crawl};
+
+/// Scalar of what the JSON schema leaves open, such as `quality`, whose
+/// keys are field names.
+const JSON: &str = "JSON";
+
+/// Name of the whole document, the root of the JSON schema.
+const DOCUMENT: &str = "CrawlResult";
+
+/// `/graphql`: queries by POST, and the schema in SDL by GET.
+pub(crate) fn router<S>(crawl: Arc<Crawl>) -> Router<S> {
+    let schema = build(crawl).expect("the document schema makes a GraphQL schema");
+    Router::new()
+        .route("/graphql", get(sdl).post(execute))
+        .with_state(schema)
+}
+
+async fn execute(
+    State(schema): State<Schema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request).await)
+}
+
+async fn sdl(State(schema): State<Schema>) -> String {
+    schema.sdl()
+}
+
+/// The GraphQL schema of the documents the crawler writes, translated from
+/// their JSON schema so that the two never disagree.
+fn build(crawl: Arc<Crawl>) -> Result<Schema, SchemaError> {
+    let document = schema::current(Profile::Full);
+    let empty = Map::new();
+    let definitions = document["definitions"].as_object().unwrap_or(&empty);
+    let objects: Arc<BTreeSet<String>> = Arc::new(
+        definitions
+            .iter()
+            .filter(|(_, definition)| definition["properties"].is_object())
+            .map(|(name, _)| name.clone())
+            .chain([DOCUMENT.to_string()])
+            .collect(),
+    );
+    let mut builder = Schema::build("Query", None, None)
+        .register(Scalar::new(JSON).description("Any JSON value."))
+        .register(query())
+        .data(crawl);
+    for name in objects.iter() {
+        let definition = definitions.get(name).unwrap_or(&document);
+        builder = builder.register(object(name, definition, definitions, &objects));
+    }
+    builder.finish()
+}
+
+fn query() -> Object {
+    let at = || {
+        InputValue::new("at", TypeRef::named(TypeRef::STRING)).description(
+            "KST minute to answer from the closest retained snapshot for, e.g. `2024-05-01T12:34`.",
+        )
+    };
+    Object::new("Query")
+        .field(
+            Field::new("crawl", TypeRef::named_nn(DOCUMENT), |ctx| {
+                FieldFuture::new(async move {
+                    let doc = document(&ctx).await?;
+                    Ok(Some(FieldValue::owned_any(doc.as_ref().clone())))
+                })
+            })
+            .description("The whole document, as `/latest` returns it.")
+            .argument(at()),
+        )
+        .field(
+            Field::new("stations", TypeRef::named_nn_list_nn("Record"), |ctx| {
+                FieldFuture::new(async move {
+                    let doc = document(&ctx).await?;
+                    let region = ctx.args.get("region").map(|r| r.string()).transpose()?;
+                    let ids: Option<BTreeSet<u64>> = match ctx.args.get("ids") {
+                        Some(ids) => Some(
+                            ids.list()?
+                                .iter()
+                                .map(|id| id.u64())
+                                .collect::<Result<_, _>>()?,
+                        ),
+                        None => None,
+                    };
+                    let stations = records(&doc)
+                        .filter(|record| region.is_none_or(|wanted| in_region(record, wanted)))
+                        .filter(|record| {
+                            ids.as_ref().is_none_or(|ids| {
+                                record["id"].as_u64().is_some_and(|id| ids.contains(&id))
+                            })
+                        })
+                        .map(|record| FieldValue::owned_any(record.clone()));
+                    Ok(Some(FieldValue::list(stations)))
+                })
+            })
+            .description("Records of the stations, in a region or by id when given.")
+            .argument(
+                InputValue::new("region", TypeRef::named(TypeRef::STRING))
+                    .description("Province, city or district, as for `/stations`."),
+            )
+            .argument(InputValue::new("ids", TypeRef::named_nn_list(TypeRef::INT)))
+            .argument(at()),
+        )
+        .field(
+            Field::new("station", TypeRef::named("Record"), |ctx| {
+                FieldFuture::new(async move {
+                    let doc = document(&ctx).await?;
+                    let id = ctx.args.try_get("id")?.u64()?;
+                    let record = records(&doc)
+                        .find(|record| record["id"].as_u64() == Some(id))
+                        .map(|record| FieldValue::owned_any(record.clone()));
+                    Ok(record)
+                })
+            })
+            .argument(InputValue::new("id", TypeRef::named_nn(TypeRef::INT)))
+            .argument(at()),
+        )
+}
+
+/// The document `at` asks for, or the latest one.
+async fn document(ctx: &ResolverContext<'_>) -> async_graphql::Result<Arc<Value>> {
+    let crawl = ctx.data::<Arc<Crawl>>()?;
+    let at = ctx.args.get("at").map(|at| at.string()).transpose()?;
+    crawl
+        .document(at)
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))
+}
+
+/// The object type of a JSON schema definition, each field read from the
+/// JSON of its parent.
+fn object(
+    name: &str,
+    definition: &Value,
+    definitions: &Map<String, Value>,
+    objects: &Arc<BTreeSet<String>>,
+) -> Object {
+    let required = |field: &str| {
+        definition["required"]
+            .as_array()
+            .is_some_and(|required| required.iter().any(|r| r == field))
+    };
+    let mut object = Object::new(name);
+    if let Some(description) = definition["description"].as_str() {
+        object = object.description(description);
+    }
+    let properties = definition["properties"].as_object().into_iter().flatten();
+    for (field, property) in properties {
+        let (ty, nullable) = type_of(property, definitions);
+        let ty = if required(field) && !nullable {
+            TypeRef::NonNull(Box::new(ty))
+        } else {
+            ty
+        };
+        let (key, shape, objects) = (field.clone(), ty.clone(), objects.clone());
+        let mut resolver = Field::new(field, ty, move |ctx| {
+            let value = ctx
+                .parent_value
+                .downcast_ref::<Value>()
+                .map_or(&Value::Null, |parent| &parent[&key]);
+            FieldFuture::Value((!value.is_null()).then(|| resolve(value, &shape, &objects)))
+        });
+        if let Some(description) = property["description"].as_str() {
+            resolver = resolver.description(description);
+        }
+        object = object.field(resolver);
+    }
+    object
+}
+
+/// The GraphQL type of a property of the JSON schema, and whether it is
+/// nullable there.
+fn type_of(property: &Value, definitions: &Map<String, Value>) -> (TypeRef, bool) {
+    if let Some(reference) = property["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/definitions/");
+        let name = match definitions.get(name) {
+            Some(definition) if definition["properties"].is_object() => name,
+            // Unit enums, by the names serde gives their variants.
+            Some(definition) if definition["type"] == "string" => TypeRef::STRING,
+            _ => JSON,
+        };
+        return (TypeRef::named(name), false);
+    }
+    if let Some(variants) = property["anyOf"].as_array() {
+        let variants: Vec<&Value> = variants.iter().filter(|v| v["type"] != "null").collect();
+        return match variants[..] {
+            [variant] => (type_of(variant, definitions).0, true),
+            _ => (TypeRef::named(JSON), true),
+        };
+    }
+    let (kind, nullable) = match &property["type"] {
+        Value::String(kind) => (kind.as_str(), false),
+        Value::Array(kinds) => (
+            kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|kind| *kind != "null")
+                .unwrap_or_default(),
+            kinds.iter().any(|kind| kind == "null"),
+        ),
+        _ => return (TypeRef::named(JSON), true),
+    };
+    let ty = match kind {
+        "number" => TypeRef::named(TypeRef::FLOAT),
+        "integer" => TypeRef::named(TypeRef::INT),
+        "string" => TypeRef::named(TypeRef::STRING),
+        "boolean" => TypeRef::named(TypeRef::BOOLEAN),
+        "array" => {
+            let (item, nullable) = type_of(&property["items"], definitions);
+            let item = if nullable {
+                item
+            } else {
+                TypeRef::NonNull(Box::new(item))
+            };
+            TypeRef::List(Box::new(item))
+        }
+        _ => TypeRef::named(JSON),
+    };
+    (ty, nullable)
+}
+
+/// `value` as a field of type `ty`.
+fn resolve(value: &Value, ty: &TypeRef, objects: &BTreeSet<String>) -> FieldValue<'static> {
+    match ty {
+        _ if value.is_null() => FieldValue::NULL,
+        TypeRef::NonNull(ty) => resolve(value, ty, objects),
+        TypeRef::List(ty) => FieldValue::list(
+            value
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|item| resolve(item, ty, objects)),
+        ),
+        TypeRef::Named(name) if objects.contains(name.as_ref()) => {
+            FieldValue::owned_any(value.clone())
+        }
+        TypeRef::Named(_) => {
+            FieldValue::value(async_graphql::Value::from_json(value.clone()).unwrap_or_default())
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use futures_util::stream::{self, Stream};
+
+use rust_decimal::prelude::*;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use tokio::sync::broadcast;
+
+use tonic::{Request, Response, Status};
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::serve::{in_region, records, ApiError, Crawl};
+use crate::{Derived, Rain, Record, Region, Station, Units, Wind};
+
+mod pb {
+    tonic::include_proto!("weather_crawl.v1");
+}
+
+use pb::crawl_server::CrawlServer;
+
+/// Serve proto/weather_crawl.proto on `listen` until the process ends.
+pub(crate) async fn serve(
+    crawl: Arc<Crawl>,
+    listen: SocketAddr,
+) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(CrawlServer::new(Service { crawl }))
+        .serve(listen)
+        .await
+}
+
+struct Service {
+    crawl: Arc<Crawl>,
+}
+
+type Observations = Pin<Box<dyn Stream<Item = Result<pb::CrawlResult, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl pb::crawl_server::Crawl for Service {
+    async fn get_latest(
+        &self,
+        request: Request<pb::GetLatestRequest>,
+    ) -> Result<Response<pb::CrawlResult>, Status> {
+        let doc = self.crawl.document(request.get_ref().at.as_deref()).await?;
+        Ok(Response::new(result(&doc, |_| true).map_err(internal)?))
+    }
+
+    async fn get_station(
+        &self,
+        request: Request<pb::GetStationRequest>,
+    ) -> Result<Response<pb::Record>, Status> {
+        let request = request.into_inner();
+        let doc = self.crawl.document(request.at.as_deref()).await?;
+        let record = records(&doc)
+            .find(|record| record["id"].as_u64() == Some(u64::from(request.id)))
+            .ok_or_else(|| Status::not_found(format!("no station {}", request.id)))?;
+        Ok(Response::new(record_of(record).map_err(internal)?))
+    }
+
+    type StreamObservationsStream = Observations;
+
+    async fn stream_observations(
+        &self,
+        request: Request<pb::StreamObservationsRequest>,
+    ) -> Result<Response<Observations>, Status> {
+        let receiver = self.crawl.events.subscribe();
+        let request = request.into_inner();
+        let stream = stream::unfold((receiver, request), |(mut receiver, request)| async move {
+            loop {
+                let doc = match receiver.recv().await {
+                    Ok(doc) => doc,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                };
+                let wanted = |record: &Value| {
+                    (request.station_ids.is_empty()
+                        || request
+                            .station_ids
+                            .iter()
+                            .any(|id| record["id"].as_u64() == Some(u64::from(*id))))
+                        && request
+                            .region
+                            .as_deref()
+                            .is_none_or(|region| in_region(record, region))
+                };
+                match result(&doc, wanted) {
+                    Ok(result) if result.records.is_empty() => continue,
+                    outcome => return Some((outcome.map_err(internal), (receiver, request))),
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(e: ApiError) -> Self {
+        match e {
+            ApiError::BadRequest(m) => Status::invalid_argument(m),
+            ApiError::NotFound(m) => Status::not_found(m),
+            ApiError::Internal(m) => Status::internal(m),
+        }
+    }
+}
+
+/// A document that does not read as one the crawler wrote.
+fn internal(e: serde_json::Error) -> Status {
+    Status::internal(e.to_string())
+}
+
+/// `doc` with the records `wanted` keeps.
+fn result(
+    doc: &Value,
+    wanted: impl Fn(&Value) -> bool,
+) -> Result<pb::CrawlResult, serde_json::Error> {
+    let units: Option<Units> = serde_json::from_value(doc["units"].clone())?;
+    Ok(pb::CrawlResult {
+        schema_version: doc["schema_version"].as_u64().unwrap_or(1) as u32,
+        observed_at: doc["observed_at"].as_str().unwrap_or_default().into(),
+        source: doc["source"].as_str().unwrap_or_default().into(),
+        units: units.map(|units| pb::Units {
+            temperature: name(&units.temperature),
+            wind: name(&units.wind),
+            rain: name(&units.rain),
+        }),
+        records: records(doc)
+            .filter(|record| wanted(record))
+            .map(record_of)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn record_of(record: &Value) -> Result<pb::Record, serde_json::Error> {
+    let record: Record = serde_json::from_value(record.clone())?;
+    Ok(pb::Record {
+        id: record.id,
+        name: record.name,
+        name_en: record.name_en,
+        station: record.station.map(station),
+        height: record.height.map(|height| height.value),
+        rain: Some(rain(record.rain)),
+        temperature: float(record.temperature.map(|t| t.0)),
+        wind1: Some(wind(record.wind1)),
+        wind10: Some(wind(record.wind10)),
+        humidity: float(record.humidity),
+        atmospheric: float(record.atmospheric.map(|p| p.0)),
+        sunshine: float(record.sunshine),
+        solar_radiation: float(record.solar_radiation),
+        snow_depth: float(record.snow_depth),
+        visibility: float(record.visibility),
+        cloud_cover: float(record.cloud_cover),
+        pressure_sea_level: float(record.pressure_sea_level.map(|p| p.0)),
+        address: record.address,
+        region: record.region.map(region),
+        derived: record.derived.map(derived),
+    })
+}
+
+fn station(station: Station) -> pb::Station {
+    pb::Station {
+        id: station.id,
+        latitude: station.latitude,
+        longitude: station.longitude,
+        elevation: station.elevation,
+        admin_code: station.admin_code,
+        operator: station.operator,
+        start_date: station.start_date.map(|date| date.to_string()),
+    }
+}
+
+fn rain(rain: Rain) -> pb::Rain {
+    pb::Rain {
+        is_raining: name(&rain.is_raining),
+        rain15: float(rain.rain15.map(|r| r.0)),
+        rain60: float(rain.rain60.map(|r| r.0)),
+        rain3h: float(rain.rain3h.map(|r| r.0)),
+        rain6h: float(rain.rain6h.map(|r| r.0)),
+        rain12h: float(rain.rain12h.map(|r| r.0)),
+        rainday: float(rain.rainday.map(|r| r.0)),
+        intensity: rain.intensity.as_ref().map(name),
+    }
+}
+
+fn wind(wind: Wind) -> pb::Wind {
+    pb::Wind {
+        direction_code: float(wind.direction_code),
+        direction_text: name(&wind.direction_text),
+        velocity: float(wind.velocity.map(|v| v.0)),
+        beaufort: wind.beaufort.map(u32::from),
+        bearing: float(wind.bearing),
+    }
+}
+
+fn region(region: Region) -> pb::Region {
+    pb::Region {
+        province: region.province,
+        province_code: region.province_code,
+        city: region.city,
+        district: region.district,
+        code: region.code,
+    }
+}
+
+fn derived(derived: Derived) -> pb::Derived {
+    pb::Derived {
+        dew_point: float(derived.dew_point.map(|t| t.0)),
+        heat_index: float(derived.heat_index.map(|t| t.0)),
+        wind_chill: float(derived.wind_chill.map(|t| t.0)),
+        discomfort_index: float(derived.discomfort_index),
+        apparent_temperature: float(derived.apparent_temperature.map(|t| t.0)),
+        pressure_sea_level: float(derived.pressure_sea_level.map(|p| p.0)),
+    }
+}
+
+fn float(value: Option<Decimal>) -> Option<f64> {
+    value.and_then(|value| value.to_f64())
+}
+
+/// The name `index.json` gives an enumeration's value.
+fn name(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,83 @@
+#[cfg(feature = "cli")]
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::{create_dir_all, File};
+use std::io::BufReader;
+use std::path::Path;
+#[cfg(feature = "cli")]
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::atomic;
+
+const HEARTBEAT_FILE: &str = ".heartbeat";
+
+/// Proof of life written to `<base>/.heartbeat` after every successful crawl.
+#[derive(Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// Unix time of the last crawl that ended well.
+    pub last_success: u64,
+    /// Observation published as of that crawl.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_at: Option<String>,
+}
+impl Heartbeat {
+    pub fn now(observed_at: Option<String>) -> Self {
+        Heartbeat {
+            last_success: unix_now(),
+            observed_at,
+        }
+    }
+
+    pub fn load(base: &Path) -> std::io::Result<Self> {
+        let file = File::open(base.join(HEARTBEAT_FILE))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, base: &Path) -> std::io::Result<()> {
+        create_dir_all(base)?;
+        atomic::write(&base.join(HEARTBEAT_FILE), &serde_json::to_vec(self)?)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(feature = "cli")]
+pub fn command() -> Command {
+    Command::new("healthcheck")
+        .about("exit non-zero unless a crawl into <base> succeeded recently")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--"max-age" <SECONDS> "oldest heartbeat still considered healthy")
+                .value_parser(value_parser!(u64))
+                .default_value("1800"),
+        )
+}
+
+#[cfg(feature = "cli")]
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let max_age = *matches.get_one::<u64>("max-age").unwrap();
+    let heartbeat = Heartbeat::load(base)
+        .map_err(|e| format!("no heartbeat under {}: {}", base.display(), e))?;
+    let age = unix_now().saturating_sub(heartbeat.last_success);
+    if age > max_age {
+        return Err(format!(
+            "last successful crawl was {}s ago, over the {}s limit",
+            age, max_age
+        )
+        .into());
+    }
+    println!(
+        "healthy: last success {}s ago, observed_at {}",
+        age,
+        heartbeat.observed_at.as_deref().unwrap_or("unknown")
+    );
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::Json;
+
+use rust_decimal::Decimal;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::client::{History, Point};
+use crate::region::spread;
+use crate::serve::{ApiError, Crawl};
+use crate::{aggregate, archive, export};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Range `/history` covers when `from` is not given.
+const DEFAULT_RANGE_MINUTES: i64 = 24 * 60;
+
+/// Most points one response may have.
+const MAX_POINTS: i64 = 10_000;
+
+#[derive(Deserialize)]
+pub(crate) struct Params {
+    /// First KST minute, e.g. `2024-05-01T00:00`; a day before `to` when
+    /// not given.
+    from: Option<String>,
+    /// Last KST minute; the latest observation when not given.
+    to: Option<String>,
+    /// Width of a point: minutes, hours or days, e.g. `10m`, `1h` or `1d`.
+    step: Option<String>,
+}
+
+/// One observation of the station.
+struct Sample {
+    minute: i64,
+    temperature: Option<Decimal>,
+    humidity: Option<Decimal>,
+    wind: Option<Decimal>,
+    atmospheric: Option<Decimal>,
+    rainday: Option<Decimal>,
+}
+
+/// What was read for the station.
+#[derive(Default)]
+struct Samples {
+    name: Value,
+    name_en: Value,
+    units: Value,
+    samples: Vec<Sample>,
+}
+
+/// `/stations/:id/history`.
+pub(crate) async fn station(
+    State(crawl): State<Arc<Crawl>>,
+    UrlPath(id): UrlPath<u32>,
+    Query(params): Query<Params>,
+) -> Result<Json<History>, ApiError> {
+    let minute = |time: &str| {
+        archive::minute_of(time).ok_or_else(|| {
+            ApiError::BadRequest(format!("`{}` is not a YYYY-MM-DDTHH:MM time", time))
+        })
+    };
+    let step = params.step.as_deref().unwrap_or("10m");
+    let step_minutes = step_minutes(step).ok_or_else(|| {
+        ApiError::BadRequest(format!("`{}` is not a step such as 10m, 1h or 1d", step))
+    })?;
+    let end = match &params.to {
+        Some(to) => minute(to)?,
+        None => {
+            let (_, doc) = crawl.latest().await?;
+            minute(doc["observed_at"].as_str().unwrap_or_default())?
+        }
+    };
+    let start = match &params.from {
+        Some(from) => minute(from)?,
+        None => end - DEFAULT_RANGE_MINUTES,
+    };
+    if start > end {
+        return Err(ApiError::BadRequest("`from` is after `to`".into()));
+    }
+    if (end - start) / step_minutes >= MAX_POINTS {
+        return Err(ApiError::BadRequest(format!(
+            "more than {} points; narrow the range or widen the step",
+            MAX_POINTS
+        )));
+    }
+    let source = crawl.clone();
+    let read = tokio::task::spawn_blocking(move || source.history(id, start, end))
+        .await
+        .map_err(ApiError::internal)?
+        .map_err(ApiError::internal)?;
+    if read.samples.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "no observations of station {} from {} to {}",
+            id,
+            archive::format_minute(start),
+            archive::format_minute(end)
+        )));
+    }
+    Ok(Json(History {
+        id,
+        name: read.name.as_str().map(str::to_string),
+        name_en: read.name_en.as_str().map(str::to_string),
+        from: archive::format_minute(start),
+        to: archive::format_minute(end),
+        step: step.to_string(),
+        units: serde_json::from_value(read.units).unwrap_or_default(),
+        points: downsample(read.samples, step_minutes),
+    }))
+}
+
+impl Crawl {
+    /// The station's samples in `start..=end`, from the database when serving
+    /// one and the snapshots otherwise.
+    fn history(&self, id: u32, start: i64, end: i64) -> Result<Samples, Error> {
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = &self.history_db {
+            return from_db(db, id, start, end);
+        }
+        from_archive(&self.base, id, start, end)
+    }
+}
+
+/// Minutes of a step such as `10m`, `1h` or `1d`; none for a step too long
+/// to count in minutes.
+fn step_minutes(step: &str) -> Option<i64> {
+    let unit = step.chars().last()?;
+    let count: i64 = step[..step.len() - unit.len_utf8()]
+        .parse()
+        .ok()
+        .filter(|count| *count > 0)?;
+    match unit {
+        'm' => Some(count),
+        'h' => count.checked_mul(60),
+        'd' => count.checked_mul(24 * 60),
+        _ => None,
+    }
+}
+
+/// The samples of the station in the retained snapshots and bundles.
+fn from_archive(base: &Path, id: u32, start: i64, end: i64) -> Result<Samples, Error> {
+    let mut read = Samples::default();
+    for path in aggregate::inputs(base, start, end)? {
+        export::each_snapshot(&path, &mut |snapshot| {
+            let minute = match snapshot["observed_at"]
+                .as_str()
+                .and_then(archive::minute_of)
+            {
+                Some(minute) if (start..=end).contains(&minute) => minute,
+                _ => return Ok(()),
+            };
+            let mut records = snapshot["records"].as_array().into_iter().flatten();
+            if let Some(record) = records.find(|r| r["id"].as_u64() == Some(u64::from(id))) {
+                let value = |pointer: &str| {
+                    record
+                        .pointer(pointer)
+                        .and_then(|v| serde_json::from_value::<Decimal>(v.clone()).ok())
+                };
+                read.samples.push(Sample {
+                    minute,
+                    temperature: value("/temperature"),
+                    humidity: value("/humidity"),
+                    wind: value("/wind10/velocity"),
+                    atmospheric: value("/atmospheric"),
+                    rainday: value("/rain/rainday"),
+                });
+                read.name = record["name"].clone();
+                read.name_en = record["name_en"].clone();
+                read.units = snapshot["units"].clone();
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(read)
+}
+
+/// The samples of the station in the `observations` table `--sink sqlite`
+/// writes, which holds no units.
+#[cfg(feature = "sqlite")]
+fn from_db(db: &Path, id: u32, start: i64, end: i64) -> Result<Samples, Error> {
+    use rust_decimal::prelude::FromPrimitive;
+
+    let conn =
+        rusqlite::Connection::open_with_flags(db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut statement = conn.prepare(
+        "SELECT observed_at, name, name_en, temperature, humidity, wind10_velocity, atmospheric, rainday \
+         FROM observations WHERE id = ?1 \
+         AND unixepoch(observed_at) BETWEEN unixepoch(?2) AND unixepoch(?3) + 59",
+    )?;
+    // The bounds as KST times, whatever offset `--tz` wrote `observed_at` in.
+    let kst = |minute| format!("{}+09:00", archive::format_minute(minute));
+    let mut rows = statement.query(rusqlite::params![id, kst(start), kst(end)])?;
+    let mut read = Samples::default();
+    while let Some(row) = rows.next()? {
+        let observed_at: String = row.get(0)?;
+        let Some(minute) = archive::minute_of(&observed_at) else {
+            continue;
+        };
+        let value = |i| -> rusqlite::Result<Option<Decimal>> {
+            Ok(row.get::<_, Option<f64>>(i)?.and_then(Decimal::from_f64))
+        };
+        read.samples.push(Sample {
+            minute,
+            temperature: value(3)?,
+            humidity: value(4)?,
+            wind: value(5)?,
+            atmospheric: value(6)?,
+            rainday: value(7)?,
+        });
+        read.name = row.get::<_, Option<String>>(1)?.into();
+        read.name_en = row.get::<_, Option<String>>(2)?.into();
+    }
+    Ok(read)
+}
+
+/// A point per step that has samples, the steps counted from midnight KST
+/// so that `1d` is a calendar day and `1h` an hour of the clock.
+fn downsample(mut samples: Vec<Sample>, step: i64) -> Vec<Point> {
+    samples.sort_by_key(|sample| sample.minute);
+    let mut points = Vec::new();
+    // The last `rainday` of the step before, for the rain between the two.
+    let mut previous: Option<(i64, Decimal)> = None;
+    for chunk in samples.chunk_by(|a, b| a.minute.div_euclid(step) == b.minute.div_euclid(step)) {
+        let values = |field: fn(&Sample) -> Option<Decimal>| -> Vec<Decimal> {
+            chunk.iter().filter_map(field).collect()
+        };
+        let day = |minute: i64| minute.div_euclid(24 * 60);
+        // Of an earlier day, it only says that `rainday` was zero at midnight.
+        let mut rainday: Vec<(i64, Decimal)> = previous
+            .map(|(minute, mm)| match day(chunk[0].minute) {
+                today if today == day(minute) => (minute, mm),
+                today => (today * 24 * 60, Decimal::ZERO),
+            })
+            .into_iter()
+            .chain(
+                chunk
+                    .iter()
+                    .filter_map(|s| s.rainday.map(|mm| (s.minute, mm))),
+            )
+            .collect();
+        previous = rainday.last().copied().or(previous);
+        points.push(Point {
+            at: archive::format_minute(chunk[0].minute.div_euclid(step) * step),
+            samples: chunk.len(),
+            temperature: spread(&values(|s| s.temperature)),
+            humidity: spread(&values(|s| s.humidity)),
+            wind: spread(&values(|s| s.wind)),
+            atmospheric: spread(&values(|s| s.atmospheric)),
+            precipitation: aggregate::precipitation(&mut rainday),
+        });
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        assert_eq!(step_minutes("10m"), Some(10));
+        assert_eq!(step_minutes("1h"), Some(60));
+        assert_eq!(step_minutes("1d"), Some(24 * 60));
+        for step in ["0m", "-1h", "", "m", "1", "1w", "1.5h", "1분", "분"] {
+            assert_eq!(step_minutes(step), None, "{}", step);
+        }
+        assert_eq!(step_minutes(&format!("{}m", i64::MAX)), Some(i64::MAX));
+        assert_eq!(step_minutes(&format!("{}h", i64::MAX / 60 + 1)), None);
+        assert_eq!(step_minutes(&format!("{}d", i64::MAX)), None);
+        assert_eq!(step_minutes("99999999999999999999m"), None);
+    }
+
+    fn sample(at: &str, rainday: &str) -> Sample {
+        Sample {
+            minute: archive::minute_of(at).unwrap(),
+            temperature: None,
+            humidity: None,
+            wind: None,
+            atmospheric: None,
+            rainday: Some(rainday.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn rain_across_midnight() {
+        let samples = vec![
+            sample("2024-05-02T00:50", "1.5"),
+            sample("2024-05-01T23:00", "4.0"),
+            sample("2024-05-02T00:10", "0.5"),
+            sample("2024-05-01T23:50", "5.0"),
+        ];
+        let points = downsample(samples, 60);
+        let rain: Vec<(&str, usize, Option<Decimal>)> = points
+            .iter()
+            .map(|p| (p.at.as_str(), p.samples, p.precipitation))
+            .collect();
+        assert_eq!(
+            rain,
+            [
+                ("2024-05-01T23:00", 2, Some("1.0".parse().unwrap())),
+                ("2024-05-02T00:00", 2, Some("1.5".parse().unwrap())),
+            ]
+        );
+    }
+
+    #[test]
+    fn rain_after_days_without_samples() {
+        let samples = vec![
+            sample("2024-05-01T10:00", "3.0"),
+            sample("2024-05-03T10:00", "7.0"),
+        ];
+        let points = downsample(samples, 24 * 60);
+        let rain: Vec<(&str, Option<Decimal>)> = points
+            .iter()
+            .map(|p| (p.at.as_str(), p.precipitation))
+            .collect();
+        assert_eq!(
+            rain,
+            [
+                ("2024-05-01T00:00", Some(Decimal::ZERO)),
+                ("2024-05-03T00:00", Some("7.0".parse().unwrap())),
+            ]
+        );
+    }
+}
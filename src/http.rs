@@ -0,0 +1,83 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, Proxy, Response};
+
+use std::fs::read;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Knobs for the HTTP client used to reach KMA.
+///
+/// One client is built per process and shared by every request so that
+/// retries reuse the pooled keep-alive (or HTTP/2) connection.
+#[derive(Default)]
+pub struct HttpOptions {
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub proxy: Option<String>,
+    pub no_proxy: bool,
+    pub insecure: bool,
+    pub ca_cert: Option<PathBuf>,
+    pub user_agent: Option<String>,
+    pub headers: Vec<String>,
+}
+
+pub fn default_user_agent() -> String {
+    format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// Split a `Name: value` pair given on the command line.
+fn parse_header(raw: &str) -> Result<(HeaderName, HeaderValue), Box<dyn std::error::Error>> {
+    let (name, value) = raw
+        .split_once(':')
+        .ok_or_else(|| format!("header `{}` is not in `Name: value` form", raw))?;
+    Ok((
+        HeaderName::from_bytes(name.trim().as_bytes())?,
+        HeaderValue::from_str(value.trim())?,
+    ))
+}
+
+pub fn build_client(options: &HttpOptions) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut headers = HeaderMap::new();
+    for raw in options.headers.iter() {
+        let (name, value) = parse_header(raw)?;
+        headers.append(name, value);
+    }
+    let mut builder = Client::builder()
+        .user_agent(
+            options
+                .user_agent
+                .clone()
+                .unwrap_or_else(default_user_agent),
+        )
+        .default_headers(headers)
+        .tcp_keepalive(Duration::from_secs(60))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .http2_adaptive_window(true);
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if options.no_proxy {
+        builder = builder.no_proxy();
+    } else if let Some(proxy) = &options.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    if let Some(path) = &options.ca_cert {
+        let pem = read(path)?;
+        builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+    }
+    if options.insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder.build()?)
+}
+
+pub fn header_string(response: &Response, name: HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+
+use clap::{arg, ArgAction, ArgMatches, Command};
+
+use tracing::{info, info_span, warn, Instrument};
+
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+
+use crate::crawler::RetryPolicy;
+use crate::pipeline::{fetch, Fetched, Settings};
+use crate::state::State;
+use crate::{atomic, logging, timezone};
+
+/// How far back to look for an image that has been published.
+const LOOKBACK_MINUTES: i64 = 60;
+
+/// An image product KMA publishes at fixed minutes.
+struct Product {
+    name: &'static str,
+    /// URL of an image, with the `strftime` fields of its time.
+    template: &'static str,
+    /// Minutes between images.
+    every: i64,
+    /// Whether the URL is timed in UTC rather than KST.
+    utc: bool,
+}
+
+const PRODUCTS: [Product; 2] = [
+    Product {
+        name: "radar",
+        template: "https://www.kma.go.kr/repositary/image/rdr/img/RDR_CMP_WRC_%Y%m%d%H%M.png",
+        every: 5,
+        utc: false,
+    },
+    // Infrared (10.5 µm) of the Korean peninsula from GK2A, every 2 minutes
+    // but kept to the 10-minute ones the other areas share.
+    Product {
+        name: "satellite",
+        template: "https://nmsc.kma.go.kr/IMG/GK2A/AMI/PRIMARY/L1B/COMPLETED/%Y%m/%d/%H/gk2a_ami_le1b_ir105_ko020lc_%Y%m%d%H%M.srv.png",
+        every: 10,
+        utc: true,
+    },
+];
+
+/// Name of the link to the newest image of a product.
+const LATEST: &str = "latest";
+
+pub fn command() -> Command {
+    Command::new("images")
+        .about(
+            "download the newest radar composite and satellite image into <base>/<product>/; \
+             --url replaces the URL template (strftime fields) of the products in order",
+        )
+        .args(crate::cli::crawl_args())
+        .arg(
+            arg!(--product <PRODUCT> "image to download; repeat for several [default: all]")
+                .value_parser(PRODUCTS.map(|p| p.name))
+                .action(ArgAction::Append),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = crate::cli::settings_from(matches)?;
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let products: Vec<&Product> = match matches.get_many::<String>("product") {
+        Some(names) => names
+            .map(|name| PRODUCTS.iter().find(|p| p.name == name).unwrap())
+            .collect(),
+        None => PRODUCTS.iter().collect(),
+    };
+    let templates: Vec<&String> = matches
+        .get_many::<String>("url")
+        .unwrap_or_default()
+        .collect();
+    let client = crate::cli::client_from(matches)?;
+    let now = Utc::now();
+    let mut failed = Vec::new();
+    for (i, product) in products.iter().enumerate() {
+        let template = templates.get(i).map_or(product.template, |t| t.as_str());
+        let dir = settings.base.join(product.name);
+        let saved = download(&client, product, template, &dir, now, &settings)
+            .instrument(info_span!("images", product = product.name))
+            .await;
+        match saved {
+            Ok(path) => info!(product = product.name, path = %path.display(), "image saved"),
+            Err(e) => {
+                warn!(product = product.name, error = %e, "no image downloaded");
+                failed.push(product.name);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("no image of {} found", failed.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Fetch the newest published image of `product` into `dir` unless it is
+/// there already, going back a slot at a time and retrying the lot as the
+/// crawl does, then point `latest` at it.
+async fn download(
+    client: &reqwest::Client,
+    product: &Product,
+    template: &str,
+    dir: &Path,
+    now: DateTime<Utc>,
+    settings: &Settings,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    create_dir_all(dir)?;
+    let extension = Path::new(template)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("png");
+    let newest = now.timestamp().div_euclid(60 * product.every) * product.every;
+    let slots: Vec<DateTime<Utc>> = (0..=LOOKBACK_MINUTES / product.every)
+        .filter_map(|back| DateTime::from_timestamp((newest - back * product.every) * 60, 0))
+        .collect();
+    let retry = RetryPolicy::default();
+    let state = State::default();
+    let mut failure = String::from("no slot to try");
+    for attempt in 0..retry.attempts {
+        if attempt > 0 {
+            tokio::time::sleep(retry.delay).await;
+            info!(attempt = attempt + 1, of = retry.attempts, "retrying");
+        }
+        for at in &slots {
+            let kst = at.with_timezone(&timezone::kst());
+            let file = format!("{}.{}", kst.format("%Y-%m-%dT%H:%M"), extension);
+            let path = dir.join(&file);
+            if !path.exists() {
+                let url = if product.utc {
+                    at.format(template).to_string()
+                } else {
+                    kst.format(template).to_string()
+                };
+                let fetched = fetch(client, &url, &state, settings.fetch_options())
+                    .instrument(info_span!("fetch", %url))
+                    .await?;
+                let page = match fetched {
+                    Fetched::Page(page) => page,
+                    Fetched::NotModified => continue,
+                    Fetched::Unavailable(reason) => {
+                        failure = format!("{}: {}", url, reason);
+                        continue;
+                    }
+                };
+                // Missing images are sometimes answered with an HTML page.
+                if !page
+                    .content_type
+                    .as_deref()
+                    .unwrap_or("image/")
+                    .starts_with("image/")
+                {
+                    failure = format!("{}: not an image", url);
+                    continue;
+                }
+                atomic::write(&path, &page.body)?;
+            }
+            let latest = dir.join(format!("{}.{}", LATEST, extension));
+            atomic::symlink(Path::new(&file), &latest)?;
+            return Ok(path);
+        }
+    }
+    Err(failure.into())
+}
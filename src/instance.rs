@@ -0,0 +1,47 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+#[cfg(feature = "fetch")]
+use std::fs::read_to_string;
+
+/// Which crawler deployment produced a document.
+///
+/// Lets several crawlers feeding one archive tell their contributions apart.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Instance {
+    pub site: String,
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+#[cfg(feature = "fetch")]
+impl Instance {
+    pub fn new(site: &str, host: Option<&str>, region: Option<&str>) -> Self {
+        Instance {
+            site: site.into(),
+            host: host.map_or_else(hostname, String::from),
+            region: region.map(String::from),
+        }
+    }
+}
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.site, self.host)?;
+        if let Some(region) = &self.region {
+            write!(f, " ({})", region)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "fetch")]
+/// Name of the machine, or `unknown` when it cannot be told.
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| read_to_string("/proc/sys/kernel/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".into())
+}
@@ -0,0 +1,778 @@
+//! Crawler of KMA's per-minute AWS observations.
+//!
+//! [`fetch_aws_page`] and [`parse_html`] turn KMA's page into a
+//! [`CrawlResult`] for embedding; [`cli`] is the `weather_crawl` binary.
+
+// Without `cli`, much of what the modules offer is only used by subcommands.
+#![cfg_attr(not(feature = "cli"), allow(dead_code))]
+
+#[cfg(feature = "cli")]
+mod aggregate;
+#[cfg(feature = "fetch")]
+mod air;
+#[cfg(feature = "fetch")]
+mod alert;
+#[cfg(feature = "fetch")]
+mod archive;
+#[cfg(feature = "fetch")]
+mod asos;
+mod astro;
+#[cfg(feature = "fetch")]
+mod atomic;
+mod attribution;
+#[cfg(feature = "cli")]
+mod backfill;
+#[cfg(feature = "cli")]
+mod bench;
+mod budget;
+#[cfg(feature = "fetch")]
+mod cache;
+#[cfg(feature = "fetch")]
+mod charset;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "client")]
+pub mod client;
+mod columns;
+#[cfg(feature = "cli")]
+mod compact;
+#[cfg(feature = "fetch")]
+mod crawler;
+#[cfg(feature = "fetch")]
+mod delta;
+mod derived;
+#[cfg(feature = "cli")]
+mod diff;
+#[cfg(feature = "email")]
+mod email;
+mod error;
+#[cfg(feature = "cli")]
+mod export;
+#[cfg(feature = "cli")]
+mod extremes;
+#[cfg(feature = "fetch")]
+mod failover;
+#[cfg(feature = "fetch")]
+mod fault;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "fetch")]
+mod fixture;
+#[cfg(feature = "cli")]
+mod forecast;
+#[cfg(feature = "cli")]
+mod gaps;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "fetch")]
+mod heartbeat;
+#[cfg(feature = "serve")]
+mod history;
+#[cfg(feature = "fetch")]
+mod http;
+#[cfg(feature = "cli")]
+mod images;
+mod instance;
+#[cfg(feature = "cli")]
+mod lightning;
+#[cfg(feature = "fetch")]
+mod lock;
+#[cfg(feature = "cli")]
+mod logging;
+#[cfg(feature = "fetch")]
+mod manifest;
+#[cfg(feature = "fetch")]
+mod marine;
+#[cfg(feature = "cli")]
+mod merge;
+#[cfg(feature = "cli")]
+mod migrate;
+#[cfg(feature = "fetch")]
+mod names;
+#[cfg(feature = "cli")]
+mod nearest;
+#[cfg(feature = "fetch")]
+mod notify;
+#[cfg(feature = "cli")]
+mod offline;
+#[cfg(feature = "fetch")]
+mod openapi;
+#[cfg(feature = "cli")]
+mod parallel;
+#[cfg(feature = "fetch")]
+mod patch;
+#[cfg(feature = "fetch")]
+mod pipeline;
+#[cfg(feature = "fetch")]
+mod polite;
+#[cfg(feature = "fetch")]
+mod publish;
+#[cfg(feature = "python")]
+mod python;
+mod qc;
+mod quantity;
+#[cfg(feature = "cli")]
+mod query;
+mod region;
+#[cfg(feature = "cli")]
+mod render;
+#[cfg(feature = "fetch")]
+mod romanize;
+#[cfg(feature = "cli")]
+mod schema;
+#[cfg(feature = "serve")]
+mod serve;
+#[cfg(feature = "fetch")]
+mod sink;
+#[cfg(feature = "fetch")]
+mod snow;
+#[cfg(feature = "fetch")]
+mod source;
+#[cfg(feature = "fetch")]
+mod state;
+mod stations;
+#[cfg(feature = "fetch")]
+mod stats;
+#[cfg(feature = "telegram")]
+mod telegram;
+#[cfg(feature = "otlp")]
+mod telemetry;
+mod timezone;
+mod trend;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "cli")]
+mod typhoon;
+mod units;
+#[cfg(feature = "cli")]
+mod uv;
+#[cfg(feature = "cli")]
+mod warnings;
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+
+#[cfg(feature = "fetch")]
+use reqwest::Client;
+
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+
+use scraper::{ElementRef, Html, Selector};
+
+use serde::{Deserialize, Serialize};
+
+pub use astro::{Astro, Daylight, MoonPhase};
+pub use attribution::Attribution;
+pub use budget::BudgetExceeded;
+use columns::{ColumnMap, Field, HeaderCell};
+#[cfg(feature = "fetch")]
+pub use crawler::{Crawler, CrawlerBuilder, RetryPolicy};
+pub use derived::Derived;
+pub use error::CrawlError;
+pub use instance::Instance;
+#[cfg(feature = "fetch")]
+use pipeline::{fetch, FetchOptions, Fetched};
+pub use qc::{FieldQuality, Quality, SpatialFlag};
+pub use quantity::{Celsius, HectoPascals, MetersPerSecond, Millimeters};
+pub use region::{Region, Spread, Summary};
+#[cfg(feature = "fetch")]
+pub use sink::{Sink, Written};
+#[cfg(feature = "fetch")]
+use state::State;
+pub use stations::Station;
+pub use trend::Trend;
+pub use units::{Precipitation, Speed, Temperature, Units};
+
+use std::collections::BTreeMap;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::string::{ParseError, String};
+
+pub const AWS_URL: &str = "https://www.kma.go.kr/cgi-bin/aws/nph-aws_txt_min";
+
+/// Version of the written documents, raised whenever `migrate` has to
+/// upgrade older ones.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Version of documents written before versioning.
+fn unversioned() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CrawlResult {
+    /// Documents from before versioning are version 1.
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Attribution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<Instance>,
+    /// Present when any value is written in non-metric units.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub units: Option<Units>,
+    /// RFC 3339, in the timezone chosen with `--tz`.
+    pub observed_at: DateTime<FixedOffset>,
+    pub source: String,
+    pub records: Vec<Record>,
+    /// Buoys and lighthouses, from the `marine` source.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marine: Vec<MarineRecord>,
+    /// AirKorea stations, from the `air` source.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub air: Vec<AirRecord>,
+    /// Per-province summaries, with `--aggregate region`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Summary>,
+    /// Data rows that could not be turned into a `Record`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedRow>,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SkippedRow {
+    pub reason: String,
+    pub cells: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Record {
+    pub id: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_en: Option<String>,
+    /// Registry entry of the station, when the catalog knows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub station: Option<Station>,
+    pub height: Option<Height>,
+    pub rain: Rain,
+    pub temperature: Option<Celsius>,
+    pub wind1: Wind,
+    pub wind10: Wind,
+    /// Relative humidity in %.
+    pub humidity: Option<Decimal>,
+    pub atmospheric: Option<HectoPascals>,
+    /// Hours of bright sunshine, from ASOS stations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunshine: Option<Decimal>,
+    /// Global solar radiation (일사) of the hour in MJ/m², from ASOS
+    /// stations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solar_radiation: Option<Decimal>,
+    /// Snow depth in cm, or inches with `--rain-unit in`, where the page
+    /// reports it in winter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snow_depth: Option<Decimal>,
+    /// Visibility in km, from ASOS stations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Decimal>,
+    /// Total cloud cover in tenths of the sky, from ASOS stations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud_cover: Option<Decimal>,
+    /// Pressure reduced to mean sea level as KMA reports it, from ASOS
+    /// stations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure_sea_level: Option<HectoPascals>,
+    pub address: String,
+    /// `address` split into administrative units.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spatial_flags: Vec<SpatialFlag>,
+    /// Quality of the fields that are not simply `Ok`, by field.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quality: BTreeMap<String, FieldQuality>,
+    /// Only with `--derive`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived: Option<Derived>,
+    /// Only with `--trends`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trend: Option<Trend>,
+    /// Only with `--astro`, for stations with known coordinates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub astro: Option<Astro>,
+    /// Only with `--join-air`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub air: Option<NearestAir>,
+}
+
+/// Sea state and weather at a buoy or lighthouse.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MarineRecord {
+    pub id: u32,
+    pub name: String,
+    pub kind: MarineStation,
+    pub wind: Wind,
+    /// Strongest gust.
+    pub gust: Option<MetersPerSecond>,
+    pub temperature: Option<Celsius>,
+    pub water_temperature: Option<Celsius>,
+    /// Relative humidity in %.
+    pub humidity: Option<Decimal>,
+    pub atmospheric: Option<HectoPascals>,
+    /// Significant wave height in m.
+    pub wave_height: Option<Decimal>,
+    /// Highest wave in m.
+    pub max_wave_height: Option<Decimal>,
+    /// Wave period in seconds.
+    pub wave_period: Option<Decimal>,
+    /// Degrees clockwise from north the waves come from.
+    pub wave_direction: Option<Decimal>,
+    /// Quality of the fields that are not simply `Ok`, by field.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quality: BTreeMap<String, FieldQuality>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum MarineStation {
+    /// 해양기상부이.
+    Buoy,
+    /// 등표 AWS on a lighthouse or beacon.
+    Lighthouse,
+}
+
+/// Particulate matter at an AirKorea (에어코리아) station.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AirRecord {
+    /// AirKorea names its stations rather than numbering them.
+    pub station: String,
+    /// Known from `--air-stations`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+    /// PM10 in µg/m³; Asian dust (황사) shows here.
+    pub pm10: Option<Decimal>,
+    /// PM2.5 in µg/m³.
+    pub pm25: Option<Decimal>,
+    /// AirKorea's grade of `pm10`, from 1 (좋음) to 4 (매우나쁨).
+    pub pm10_grade: Option<u8>,
+    pub pm25_grade: Option<u8>,
+    /// Quality of the fields that are not simply `Ok`, by field.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quality: BTreeMap<String, FieldQuality>,
+}
+
+/// Readings of the AirKorea station nearest to a weather station.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NearestAir {
+    pub station: String,
+    pub distance_km: f64,
+    /// Hour of the readings, which AirKorea publishes hourly.
+    pub observed_at: DateTime<FixedOffset>,
+    pub pm10: Option<Decimal>,
+    pub pm25: Option<Decimal>,
+    pub pm10_grade: Option<u8>,
+    pub pm25_grade: Option<u8>,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Rain {
+    pub is_raining: RainStatus,
+    pub rain15: Option<Millimeters>,
+    pub rain60: Option<Millimeters>,
+    pub rain3h: Option<Millimeters>,
+    pub rain6h: Option<Millimeters>,
+    pub rain12h: Option<Millimeters>,
+    pub rainday: Option<Millimeters>,
+    /// KMA category of `rain60`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intensity: Option<RainIntensity>,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Wind {
+    pub direction_code: Option<Decimal>,
+    pub direction_text: WindDirectionText,
+    pub velocity: Option<MetersPerSecond>,
+    /// Beaufort force of `velocity`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beaufort: Option<u8>,
+    /// Degrees clockwise from north of `direction_text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearing: Option<Decimal>,
+}
+
+impl Wind {
+    fn new(
+        direction_code: Option<Decimal>,
+        direction_text: WindDirectionText,
+        velocity: Option<MetersPerSecond>,
+    ) -> Self {
+        Wind {
+            direction_code,
+            beaufort: velocity.and_then(|v| v.to_f64()).map(beaufort),
+            bearing: direction_text.bearing(),
+            direction_text,
+            velocity,
+        }
+    }
+}
+
+/// Lowest speed in m/s of each Beaufort force from 1 up.
+const BEAUFORT_FLOORS: [f64; 12] = [
+    0.3, 1.6, 3.4, 5.5, 8.0, 10.8, 13.9, 17.2, 20.8, 24.5, 28.5, 32.7,
+];
+
+fn beaufort(velocity: f64) -> u8 {
+    BEAUFORT_FLOORS
+        .iter()
+        .filter(|floor| velocity >= **floor)
+        .count() as u8
+}
+
+/// Station elevation as printed on the page, e.g. `85m`.
+///
+/// Serialized as `{"value": 85, "unit": "m", "raw": "85m"}` so the number is
+/// never separated from its unit. Other unit-suffixed columns should follow
+/// the same shape.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Height {
+    pub value: u32,
+    pub unit: String,
+    pub raw: String,
+}
+
+impl FromStr for Height {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+        Ok(Height {
+            value: value.parse::<u32>()?,
+            unit: unit.trim().into(),
+            raw: s.into(),
+        })
+    }
+}
+
+/// How hard it rains by KMA's hourly thresholds.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub enum RainIntensity {
+    None,
+    /// Under 3mm/h.
+    Weak,
+    /// 3mm/h up to 15mm/h.
+    Moderate,
+    /// 15mm/h up to 30mm/h.
+    Strong,
+    /// 30mm/h and more.
+    VeryStrong,
+}
+
+impl RainIntensity {
+    fn of(rain60: Millimeters) -> Self {
+        match rain60.0 {
+            mm if mm <= Decimal::ZERO => RainIntensity::None,
+            mm if mm < Decimal::from(3) => RainIntensity::Weak,
+            mm if mm < Decimal::from(15) => RainIntensity::Moderate,
+            mm if mm < Decimal::from(30) => RainIntensity::Strong,
+            _ => RainIntensity::VeryStrong,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub enum RainStatus {
+    Clear,
+    Rain,
+    Unavailable,
+    Unknown,
+}
+
+impl FromStr for RainStatus {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "●" => RainStatus::Rain,
+            "○" => RainStatus::Clear,
+            "." => RainStatus::Unavailable,
+            _ => RainStatus::Unknown,
+        })
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum WindDirectionText {
+    N,
+    NNW,
+    NW,
+    WNW,
+    W,
+    WSW,
+    SW,
+    SSW,
+    S,
+    SSE,
+    SE,
+    ESE,
+    E,
+    ENE,
+    NE,
+    NNE,
+    No,
+    Unavailable,
+}
+
+/// Compass points clockwise from north.
+const POINTS: [WindDirectionText; 16] = {
+    use WindDirectionText::*;
+    [
+        N, NNE, NE, ENE, E, ESE, SE, SSE, S, SSW, SW, WSW, W, WNW, NW, NNW,
+    ]
+};
+
+impl WindDirectionText {
+    fn bearing(&self) -> Option<Decimal> {
+        let point = POINTS.iter().position(|p| p == self)?;
+        Some(Decimal::new(225, 1) * Decimal::from(point))
+    }
+
+    /// The `point`th compass point clockwise from north, modulo 16.
+    fn from_point(point: usize) -> Self {
+        POINTS[point % POINTS.len()].clone()
+    }
+}
+
+impl FromStr for WindDirectionText {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "N" => WindDirectionText::N,
+            "NNW" => WindDirectionText::NNW,
+            "NW" => WindDirectionText::NW,
+            "WNW" => WindDirectionText::WNW,
+            "W" => WindDirectionText::W,
+            "WSW" => WindDirectionText::WSW,
+            "SW" => WindDirectionText::SW,
+            "SSW" => WindDirectionText::SSW,
+            "S" => WindDirectionText::S,
+            "SSE" => WindDirectionText::SSE,
+            "SE" => WindDirectionText::SE,
+            "ESE" => WindDirectionText::ESE,
+            "E" => WindDirectionText::E,
+            "ENE" => WindDirectionText::ENE,
+            "NE" => WindDirectionText::NE,
+            "NNE" => WindDirectionText::NNE,
+            "-" => WindDirectionText::No,
+            _ => WindDirectionText::Unavailable,
+        })
+    }
+}
+
+/// Fetch KMA's AWS page once and decode it to text.
+///
+/// Neither retried nor cached; pass the result to [`parse_html`].
+#[cfg(feature = "fetch")]
+pub async fn fetch_aws_page(client: &Client) -> Result<String, CrawlError> {
+    let page = match fetch(client, AWS_URL, &State::default(), FetchOptions::default()).await? {
+        Fetched::Page(page) => page,
+        Fetched::Unavailable(reason) => {
+            return Err(CrawlError::Unreachable {
+                attempts: 1,
+                reason: format!("{}: {}", AWS_URL, reason),
+            })
+        }
+        Fetched::NotModified => unreachable!("no validators were sent"),
+    };
+    charset::decode(&page.body, page.content_type.as_deref(), false).map_err(CrawlError::Decode)
+}
+
+/// Parse an AWS page into records, as the crawler does before enriching and
+/// writing them. `source` is set to [`AWS_URL`].
+pub fn parse_html(html: &str) -> Result<CrawlResult, CrawlError> {
+    parse_page(AWS_URL, html)
+}
+
+fn selector(css: &'static str) -> Result<Selector, CrawlError> {
+    Selector::parse(css).map_err(|_| CrawlError::Selector(css))
+}
+
+fn parse_page(source: &str, html: &str) -> Result<CrawlResult, CrawlError> {
+    let document = Html::parse_document(html);
+    let time_selector = selector("span.ehead")?;
+    let row_selector = selector("table table tr")?;
+    let dt = document
+        .select(&time_selector)
+        .next()
+        .ok_or(CrawlError::MissingTimestamp)?
+        .text()
+        .next()
+        .unwrap_or_default();
+    // The time is the last word, after a title such as `AWS 매분자료`.
+    let observed_at = dt
+        .split_whitespace()
+        .next_back()
+        .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, "%Y.%m.%d.%H:%M").ok())
+        .and_then(|naive| naive.and_local_timezone(timezone::kst()).single())
+        .ok_or_else(|| CrawlError::Timestamp(dt.to_string()))?;
+    let mut records: Vec<Record> = Vec::new();
+    let mut skipped: Vec<SkippedRow> = Vec::new();
+    // Pages without a header row keep the historical fixed layout.
+    let mut columns: Result<ColumnMap, String> = Ok(ColumnMap::legacy());
+    let mut header: Vec<Vec<HeaderCell>> = Vec::new();
+    for el in document.select(&row_selector) {
+        let cells = row_cells(el);
+        if !is_data_row(&cells) {
+            let row: Vec<HeaderCell> = el
+                .children()
+                .filter_map(ElementRef::wrap)
+                .map(HeaderCell::from_element)
+                .collect();
+            if !row.iter().all(HeaderCell::is_blank) {
+                header.push(row);
+            }
+            continue;
+        }
+        if !header.is_empty() {
+            columns = ColumnMap::from_header(&header);
+            header.clear();
+        }
+        let record = match &columns {
+            Ok(map) => make_record(&cells, map),
+            Err(reason) => Err(reason.clone()),
+        };
+        match record {
+            Ok(record) => records.push(record),
+            Err(reason) => skipped.push(SkippedRow {
+                reason,
+                cells: cells.iter().map(|c| c.to_string()).collect(),
+            }),
+        };
+    }
+    Ok(CrawlResult {
+        schema_version: SCHEMA_VERSION,
+        attribution: None,
+        instance: None,
+        units: None,
+        observed_at,
+        source: source.to_owned(),
+        records,
+        marine: Vec::new(),
+        air: Vec::new(),
+        regions: Vec::new(),
+        skipped,
+    })
+}
+
+/// The number in a cell, noting in `quality` why there is none.
+fn reading(
+    quality: &mut BTreeMap<String, FieldQuality>,
+    field: &str,
+    input: &str,
+) -> Option<Decimal> {
+    let (status, reason) = match input {
+        "" | "-" | "." => (Quality::Missing, None),
+        _ => match Decimal::from_str(input) {
+            Ok(value) => return Some(value),
+            Err(_) => (Quality::Invalid, Some(format!("not a number: `{}`", input))),
+        },
+    };
+    quality.insert(
+        field.to_string(),
+        FieldQuality {
+            status,
+            value: None,
+            reason,
+        },
+    );
+    None
+}
+
+/// Trimmed text of every cell of a table row.
+fn row_cells<'a>(el: ElementRef<'a>) -> Vec<&'a str> {
+    el.children()
+        .filter_map(ElementRef::wrap)
+        .map(|cell| cell.text().next().unwrap_or_default().trim())
+        .collect()
+}
+
+/// Station rows start with a numeric id; headers and layout rows do not.
+fn is_data_row(cells: &[&str]) -> bool {
+    cells
+        .first()
+        .is_some_and(|c| !c.is_empty() && c.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn make_record(cells: &[&str], map: &ColumnMap) -> Result<Record, String> {
+    if cells.len() < map.width() {
+        return Err(format!(
+            "expected {} cells, found {}",
+            map.width(),
+            cells.len()
+        ));
+    }
+    let cell = |field| map.get(cells, field);
+
+    let id = u32::from_str(cell(Field::Id)).map_err(|e| format!("invalid station id: {}", e))?;
+    let name = cell(Field::Name).into();
+    let height = Height::from_str(cell(Field::Height)).ok();
+    let mut quality = BTreeMap::new();
+    let mut read = |name: &str, field| reading(&mut quality, name, cell(field));
+    let rain60 = read("rain60", Field::Rain60).map(Millimeters);
+    let rain = Rain {
+        is_raining: RainStatus::from_str(cell(Field::IsRaining)).unwrap(),
+        rain15: read("rain15", Field::Rain15).map(Millimeters),
+        rain60,
+        rain3h: read("rain3h", Field::Rain3h).map(Millimeters),
+        rain6h: read("rain6h", Field::Rain6h).map(Millimeters),
+        rain12h: read("rain12h", Field::Rain12h).map(Millimeters),
+        rainday: read("rainday", Field::RainDay).map(Millimeters),
+        intensity: rain60.map(RainIntensity::of),
+    };
+    let temperature = read("temperature", Field::Temperature).map(Celsius);
+    let wind1 = Wind::new(
+        read("wind1_direction", Field::Wind1Code),
+        WindDirectionText::from_str(cell(Field::Wind1Text)).unwrap(),
+        read("wind1", Field::Wind1Velocity).map(MetersPerSecond),
+    );
+    let wind10 = Wind::new(
+        read("wind10_direction", Field::Wind10Code),
+        WindDirectionText::from_str(cell(Field::Wind10Text)).unwrap(),
+        read("wind10", Field::Wind10Velocity).map(MetersPerSecond),
+    );
+    let humidity = read("humidity", Field::Humidity);
+    let atmospheric = read("atmospheric", Field::Atmospheric).map(HectoPascals);
+    // Blank without snow on the ground.
+    let snow_depth = map
+        .snow_depth(cells)
+        .filter(|text| !text.is_empty())
+        .and_then(|text| reading(&mut quality, "snow_depth", text));
+    let address = cell(Field::Address).into();
+    Ok(Record {
+        id,
+        name,
+        name_en: None,
+        station: None,
+        height,
+        rain,
+        temperature,
+        wind1,
+        wind10,
+        humidity,
+        atmospheric,
+        sunshine: None,
+        solar_radiation: None,
+        snow_depth,
+        visibility: None,
+        cloud_cover: None,
+        pressure_sea_level: None,
+        address,
+        region: None,
+        spatial_flags: Vec::new(),
+        quality,
+        derived: None,
+        trend: None,
+        astro: None,
+        air: None,
+    })
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SubsecRound, Utc};
+
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use tracing::{info, warn};
+
+use std::collections::BTreeMap;
+
+use crate::region::{self, Region};
+use crate::sink::{self, Document};
+use crate::source::{with_query, without_query};
+use crate::{logging, timezone};
+
+/// Strikes detected by KMA's lightning network, from the KMA API hub.
+pub const LIGHTNING_URL: &str = "https://apihub.kma.go.kr/api/typ01/url/lgt_kma_np3.php";
+
+/// How far from a province seat a strike still counts as in the province.
+const PROVINCE_KM: f64 = 80.0;
+
+/// The strikes of the requested minutes, listed and counted by province.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Lightning {
+    pub fetched_at: DateTime<FixedOffset>,
+    pub source: String,
+    /// Start of the window, inclusive.
+    pub from: DateTime<FixedOffset>,
+    /// End of the window, inclusive.
+    pub to: DateTime<FixedOffset>,
+    pub strikes: Vec<Strike>,
+    pub provinces: Vec<ProvinceCount>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Strike {
+    pub at: DateTime<FixedOffset>,
+    pub latitude: Decimal,
+    pub longitude: Decimal,
+    /// Peak current in kA; negative for negative strokes.
+    pub current: Option<Decimal>,
+    pub kind: StrikeKind,
+    /// Province with the nearest seat; none far out at sea.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum StrikeKind {
+    /// 대지방전, the ones that endanger people outdoors.
+    CloudToGround,
+    /// 구름방전.
+    InCloud,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ProvinceCount {
+    pub province: String,
+    pub province_code: String,
+    pub strikes: usize,
+    pub cloud_to_ground: usize,
+    /// Time of the latest strike.
+    pub last_at: DateTime<FixedOffset>,
+}
+
+pub fn command() -> Command {
+    Command::new("lightning")
+        .about("fetch the lightning strikes KMA detected over the last minutes")
+        .args(crate::cli::crawl_args())
+        .arg(
+            arg!(--minutes <N> "minutes of strikes to fetch, ending now")
+                .value_parser(value_parser!(i64).range(1..=180))
+                .default_value("60"),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = crate::cli::settings_from(matches)?;
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let key = matches
+        .get_one::<String>("api-key")
+        .ok_or("lightning needs --api-key")?;
+    let url = matches
+        .get_many::<String>("url")
+        .and_then(|mut urls| urls.next().cloned())
+        .unwrap_or_else(|| LIGHTNING_URL.to_string());
+    let fetched_at = Utc::now().with_timezone(&timezone::kst()).trunc_subsecs(0);
+    let minutes = *matches.get_one::<i64>("minutes").unwrap();
+    let to = fetched_at.fixed_offset();
+    let from = to - chrono::Duration::minutes(minutes);
+    let url = with_query(
+        &url,
+        &[
+            ("authKey", key),
+            ("tm1", &from.format("%Y%m%d%H%M").to_string()),
+            ("tm2", &to.format("%Y%m%d%H%M").to_string()),
+        ],
+    );
+    let client = crate::cli::client_from(matches)?;
+    let body = client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let mut strikes = Vec::new();
+    for line in body.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse(line) {
+            Some(strike) => strikes.push(strike),
+            None => warn!(line, "skipping an unreadable strike"),
+        }
+    }
+    strikes.sort_by_key(|strike| strike.at);
+    let provinces = count(&strikes);
+    info!(
+        strikes = strikes.len(),
+        provinces = provinces.len(),
+        "lightning fetched"
+    );
+    let convert = |at| settings.tz.convert(at);
+    for strike in &mut strikes {
+        strike.at = convert(strike.at);
+    }
+    let lightning = Lightning {
+        fetched_at: convert(fetched_at.fixed_offset()),
+        source: without_query(&url, "authKey"),
+        from: convert(from),
+        to: convert(to),
+        strikes,
+        provinces: provinces
+            .into_iter()
+            .map(|p| ProvinceCount {
+                last_at: convert(p.last_at),
+                ..p
+            })
+            .collect(),
+    };
+    Ok(sink::write_document(&settings, &lightning).await?)
+}
+
+/// One line of the response: time (`YYYYMMDDHHMI`, optionally with
+/// seconds), longitude, latitude, peak current and type, 1 being
+/// cloud-to-ground, separated by blanks or commas.
+fn parse(line: &str) -> Option<Strike> {
+    let fields: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|f| !f.is_empty())
+        .collect();
+    let format = if fields.first()?.len() == 14 {
+        "%Y%m%d%H%M%S"
+    } else {
+        "%Y%m%d%H%M"
+    };
+    let at = NaiveDateTime::parse_from_str(fields[0], format)
+        .ok()?
+        .and_local_timezone(timezone::kst())
+        .single()?;
+    let longitude = Decimal::from_str(fields.get(1)?).ok()?;
+    let latitude = Decimal::from_str(fields.get(2)?).ok()?;
+    Some(Strike {
+        at,
+        latitude,
+        longitude,
+        current: fields.get(3).and_then(|f| Decimal::from_str(f).ok()),
+        kind: match fields.get(4) {
+            Some(&"1") => StrikeKind::CloudToGround,
+            _ => StrikeKind::InCloud,
+        },
+        region: region::nearest_province(latitude.to_f64()?, longitude.to_f64()?, PROVINCE_KM),
+    })
+}
+
+/// Strikes per province, by province code.
+fn count(strikes: &[Strike]) -> Vec<ProvinceCount> {
+    let mut provinces: BTreeMap<&str, ProvinceCount> = BTreeMap::new();
+    for strike in strikes {
+        let Some(region) = &strike.region else {
+            continue;
+        };
+        let count = provinces
+            .entry(&region.province_code)
+            .or_insert_with(|| ProvinceCount {
+                province: region.province.clone(),
+                province_code: region.province_code.clone(),
+                strikes: 0,
+                cloud_to_ground: 0,
+                last_at: strike.at,
+            });
+        count.strikes += 1;
+        if strike.kind == StrikeKind::CloudToGround {
+            count.cloud_to_ground += 1;
+        }
+        count.last_at = count.last_at.max(strike.at);
+    }
+    provinces.into_values().collect()
+}
+
+impl Document for Lightning {
+    const FILE: &'static str = "lightning.json";
+
+    /// Strikes go to a `lightning` table keyed by time and place, next to
+    /// the `observations` of the crawl.
+    #[cfg(feature = "sqlite")]
+    fn to_sqlite(&self, path: &std::path::Path) -> rusqlite::Result<()> {
+        use rusqlite::params;
+
+        let mut conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS lightning (at TEXT, latitude REAL, longitude REAL, \
+             current REAL, cloud_to_ground INTEGER, province_code TEXT, \
+             PRIMARY KEY (at, latitude, longitude))",
+        )?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert =
+                tx.prepare("INSERT OR REPLACE INTO lightning VALUES (?, ?, ?, ?, ?, ?)")?;
+            for strike in &self.strikes {
+                insert.execute(params![
+                    strike.at.to_rfc3339(),
+                    strike.latitude.to_f64(),
+                    strike.longitude.to_f64(),
+                    strike.current.and_then(|c| c.to_f64()),
+                    strike.kind == StrikeKind::CloudToGround,
+                    strike.region.as_ref().map(|r| &r.province_code),
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+}
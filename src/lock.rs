@@ -0,0 +1,38 @@
+use std::fs::{create_dir_all, File, OpenOptions, TryLockError};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::error::CrawlError;
+
+const LOCK_FILE: &str = ".lock";
+
+/// Exclusive claim on a base path, held until dropped.
+///
+/// The lock is advisory (`flock`/`LockFileEx`) and released by the OS if the
+/// process dies, so a stale `.lock` file never blocks later runs.
+pub struct CrawlLock {
+    _file: File,
+}
+
+/// Claim `<base>/.lock`, waiting up to `wait` for another crawl to finish.
+pub async fn acquire(base: &Path, wait: Duration) -> Result<CrawlLock, CrawlError> {
+    create_dir_all(base)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(base.join(LOCK_FILE))?;
+    let deadline = Instant::now() + wait;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(CrawlLock { _file: file }),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(CrawlError::Locked(base.to_path_buf()));
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+}
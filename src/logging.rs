@@ -0,0 +1,36 @@
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry};
+
+use std::io::{stdout, IsTerminal};
+
+/// Level names accepted by `--log-level`.
+pub const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Install the process-wide subscriber that prints crawl events to stdout.
+///
+/// `level` applies to the crawler's own events; the HTTP and HTML libraries
+/// underneath only get to report warnings. `export` receives the same events,
+/// e.g. to forward spans to a collector.
+pub fn init(level: &str, format: &str, export: Option<Box<dyn Layer<Registry> + Send + Sync>>) {
+    let filter = Targets::new()
+        .with_target(
+            env!("CARGO_CRATE_NAME"),
+            level.parse::<Level>().unwrap_or(Level::INFO),
+        )
+        .with_default(Level::WARN);
+    let output = tracing_subscriber::fmt::layer()
+        .with_ansi(stdout().is_terminal())
+        .with_target(false);
+    let output = match format {
+        "json" => output.json().boxed(),
+        _ => output.boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(export)
+        .with(output)
+        .with(filter)
+        .init();
+}
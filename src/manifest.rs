@@ -0,0 +1,108 @@
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+
+use sha2::{Digest, Sha256};
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::atomic;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Every file retained under a base path, so mirrors can verify and sync
+/// them without downloading everything.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub snapshots: Vec<Entry>,
+    /// Daily bundles made by `compact`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bundles: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    /// File name relative to the base path.
+    pub file: String,
+    /// Observation time of a snapshot, or the day of a bundle.
+    pub observed_at: String,
+    pub sha256: String,
+    pub bytes: usize,
+    pub records: usize,
+}
+impl Entry {
+    pub fn new(file: &str, observed_at: &str, body: &[u8], records: usize) -> Self {
+        Entry {
+            file: file.into(),
+            observed_at: observed_at.into(),
+            sha256: sha256_hex(body),
+            bytes: body.len(),
+            records,
+        }
+    }
+}
+
+/// Just enough of a crawl result to count its records.
+#[derive(Deserialize)]
+pub struct Counted {
+    pub records: Vec<IgnoredAny>,
+}
+
+pub fn count_records(body: &[u8]) -> serde_json::Result<usize> {
+    Ok(serde_json::from_slice::<Counted>(body)?.records.len())
+}
+
+pub fn sha256_hex(body: &[u8]) -> String {
+    Sha256::digest(body)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+impl Manifest {
+    /// The manifest of `base`, empty if there is none yet.
+    pub fn load(base: &Path) -> Self {
+        File::open(base.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, base: &Path) -> std::io::Result<()> {
+        atomic::write(&base.join(MANIFEST_FILE), &serde_json::to_vec(self)?)
+    }
+
+    /// Add or replace the entry of a snapshot, keeping them ordered by file.
+    pub fn put_snapshot(&mut self, entry: Entry) {
+        put(&mut self.snapshots, entry);
+    }
+
+    pub fn put_bundle(&mut self, entry: Entry) {
+        put(&mut self.bundles, entry);
+    }
+
+    pub fn remove_snapshot(&mut self, file: &str) {
+        self.snapshots.retain(|e| e.file != file);
+    }
+}
+
+fn put(entries: &mut Vec<Entry>, entry: Entry) {
+    match entries.binary_search_by(|e| e.file.as_str().cmp(&entry.file)) {
+        Ok(i) => entries[i] = entry,
+        Err(i) => entries.insert(i, entry),
+    }
+}
+
+/// Record a snapshot that was just written as `<base>/<file>`.
+pub fn add_snapshot(
+    base: &Path,
+    file: &str,
+    observed_at: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    let mut manifest = Manifest::load(base);
+    let records = count_records(body)?;
+    manifest.put_snapshot(Entry::new(file, observed_at, body, records));
+    manifest.save(base)
+}
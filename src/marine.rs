@@ -0,0 +1,186 @@
+use rust_decimal::prelude::*;
+
+use scraper::Html;
+
+use std::collections::BTreeMap;
+
+use crate::asos::{self, compass};
+use crate::columns::{labelled_rows, LabelledRow};
+use crate::error::CrawlError;
+use crate::pipeline::Outcome;
+use crate::quantity::{Celsius, HectoPascals, MetersPerSecond};
+use crate::source::Source;
+use crate::{
+    reading, CrawlResult, MarineRecord, MarineStation, SkippedRow, Wind, WindDirectionText,
+    SCHEMA_VERSION,
+};
+
+pub const MARINE_URL: &str = "https://www.kma.go.kr/weather/observation/marine_buoy.jsp";
+
+/// Columns read from the table, found by their header labels.
+#[derive(Clone, Copy, PartialEq)]
+enum Column {
+    Name,
+    WindDirection,
+    WindVelocity,
+    Gust,
+    Atmospheric,
+    Humidity,
+    Temperature,
+    WaterTemperature,
+    WaveHeight,
+    MaxWaveHeight,
+    WavePeriod,
+    WaveDirection,
+}
+
+/// The column a header label belongs to, e.g. `파고(m)유의` or `기온(℃)`.
+fn column_of(label: &str) -> Option<Column> {
+    let column = if label.contains("지점") {
+        Column::Name
+    } else if label.contains("GUST") || label.contains("돌풍") {
+        Column::Gust
+    } else if label.contains("풍향") {
+        Column::WindDirection
+    } else if label.contains("풍속") {
+        Column::WindVelocity
+    } else if label.contains("기압") {
+        Column::Atmospheric
+    } else if label.contains("습도") {
+        Column::Humidity
+    } else if label.contains("수온") {
+        Column::WaterTemperature
+    } else if label.contains("기온") {
+        Column::Temperature
+    } else if label.contains("최대") && label.contains("파고") {
+        Column::MaxWaveHeight
+    } else if label.contains("평균") && label.contains("파고") {
+        return None;
+    } else if label.contains("파고") {
+        Column::WaveHeight
+    } else if label.contains("파주기") {
+        Column::WavePeriod
+    } else if label.contains("파향") {
+        Column::WaveDirection
+    } else {
+        return None;
+    };
+    Some(column)
+}
+
+/// Hourly observations of the sea, `marine_buoy.jsp`: 해양기상부이 and the
+/// 등표 AWS on lighthouses.
+///
+/// The stations go to `marine` of the result rather than `records`, as they
+/// report waves and water temperature instead of rain.
+pub struct Marine;
+impl Source for Marine {
+    fn name(&self) -> &'static str {
+        "marine"
+    }
+
+    fn default_url(&self) -> &'static str {
+        MARINE_URL
+    }
+
+    /// The page has no past hours.
+    fn timed_url(&self, _url: &str, _minute: i64) -> Option<String> {
+        None
+    }
+
+    fn problem(&self, html: &str) -> Option<Outcome> {
+        asos::problem(html)
+    }
+
+    fn parse(&self, url: &str, html: &str) -> Result<CrawlResult, CrawlError> {
+        let document = Html::parse_document(html);
+        let observed_at = asos::observed_at(&document)
+            .ok_or_else(|| CrawlError::Decode("no observation time on the page".into()))?;
+        let mut marine = Vec::new();
+        let mut skipped = Vec::new();
+        for row in labelled_rows(&document, Column::Name, column_of) {
+            match make_record(&row) {
+                Ok(record) => marine.push(record),
+                Err(reason) => skipped.push(SkippedRow {
+                    reason,
+                    cells: row.cells,
+                }),
+            }
+        }
+        Ok(CrawlResult {
+            schema_version: SCHEMA_VERSION,
+            attribution: None,
+            instance: None,
+            units: None,
+            observed_at,
+            source: url.to_owned(),
+            records: Vec::new(),
+            marine,
+            air: Vec::new(),
+            regions: Vec::new(),
+            skipped,
+        })
+    }
+}
+
+/// A direction given either in degrees or as a Korean compass point.
+fn bearing(text: &str) -> (Option<Decimal>, WindDirectionText) {
+    match Decimal::from_str(text) {
+        Ok(degrees) => {
+            let point = (degrees / Decimal::new(225, 1)).round().to_usize();
+            (
+                Some(degrees),
+                point.map_or(
+                    WindDirectionText::Unavailable,
+                    WindDirectionText::from_point,
+                ),
+            )
+        }
+        Err(_) => {
+            let point = compass(text);
+            (point.bearing(), point)
+        }
+    }
+}
+
+fn make_record(row: &LabelledRow<Column>) -> Result<MarineRecord, String> {
+    let id = row.station.ok_or("no station number in the row")?;
+    let name = row.get(Column::Name).unwrap_or_default().to_string();
+    let mut quality = BTreeMap::new();
+    // Not every page has every column; only those it has can be missing.
+    let mut read = |name: &str, wanted: Column| {
+        row.get(wanted)
+            .and_then(|text| reading(&mut quality, name, text))
+    };
+    let velocity = read("wind", Column::WindVelocity).map(MetersPerSecond);
+    let gust = read("gust", Column::Gust).map(MetersPerSecond);
+    let temperature = read("temperature", Column::Temperature).map(Celsius);
+    let water_temperature = read("water_temperature", Column::WaterTemperature).map(Celsius);
+    let humidity = read("humidity", Column::Humidity);
+    let atmospheric = read("atmospheric", Column::Atmospheric).map(HectoPascals);
+    let wave_height = read("wave_height", Column::WaveHeight);
+    let max_wave_height = read("max_wave_height", Column::MaxWaveHeight);
+    let wave_period = read("wave_period", Column::WavePeriod);
+    let (degrees, direction) = bearing(row.get(Column::WindDirection).unwrap_or_default());
+    let (wave_direction, _) = bearing(row.get(Column::WaveDirection).unwrap_or_default());
+    Ok(MarineRecord {
+        id,
+        kind: if name.ends_with("등표") {
+            MarineStation::Lighthouse
+        } else {
+            MarineStation::Buoy
+        },
+        name,
+        wind: Wind::new(degrees, direction, velocity),
+        gust,
+        temperature,
+        water_temperature,
+        humidity,
+        atmospheric,
+        wave_height,
+        max_wave_height,
+        wave_period,
+        wave_direction,
+        quality,
+    })
+}
@@ -0,0 +1,240 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read};
+use std::path::PathBuf;
+
+use crate::archive::{self, Snapshot};
+use crate::atomic;
+use crate::manifest::{count_records, Entry, Manifest};
+
+/// The best snapshot seen so far for one minute.
+struct Candidate {
+    snapshot: Snapshot,
+    records: usize,
+    /// Index of the archive it came from, `0` being `<out>` itself.
+    from: usize,
+}
+
+pub fn command() -> Command {
+    Command::new("merge")
+        .about("combine two snapshot archives, keeping the fuller snapshot of each minute")
+        .arg(arg!(<a> "first archive, preferred on ties").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(<b> "second archive").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(<out> "archive to write into; snapshots already there are kept unless beaten")
+                .value_parser(value_parser!(PathBuf)),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let out = matches.get_one::<PathBuf>("out").unwrap();
+    let archives = [
+        out,
+        matches.get_one::<PathBuf>("a").unwrap(),
+        matches.get_one::<PathBuf>("b").unwrap(),
+    ];
+    create_dir_all(out)?;
+    let mut best: BTreeMap<i64, Candidate> = BTreeMap::new();
+    let mut duplicates = 0;
+    for (from, dir) in archives.iter().enumerate() {
+        archive::each_retained(dir, &mut |snapshot, body| {
+            let records =
+                count_records(&body).map_err(|e| format!("{}: {}", snapshot.path.display(), e))?;
+            let minute = archive::minute_of(&snapshot.observed_at).unwrap();
+            let candidate = Candidate {
+                snapshot,
+                records,
+                from,
+            };
+            match best.get(&minute) {
+                None => {
+                    best.insert(minute, candidate);
+                }
+                Some(kept) => {
+                    duplicates += 1;
+                    if candidate.records > kept.records {
+                        best.insert(minute, candidate);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    let mut manifest = Manifest::load(out);
+    let mut taken = [0; 3];
+    let taking = best.values().filter(|c| c.from > 0);
+    archive::read_each(taking.map(|c| &c.snapshot), &mut |snapshot, body| {
+        let candidate = &best[&archive::minute_of(&snapshot.observed_at).unwrap()];
+        let to = archive::snapshot_path(out, &snapshot.observed_at);
+        atomic::write(&to, &body)?;
+        let file = to.file_name().unwrap().to_string_lossy();
+        manifest.put_snapshot(Entry::new(
+            &file,
+            &snapshot.observed_at,
+            &body,
+            candidate.records,
+        ));
+        taken[candidate.from] += 1;
+        Ok(())
+    })?;
+    manifest.save(out)?;
+
+    // Publish the latest merged snapshot unless <out> already shows a newer one.
+    if let Some(latest) = best.values().next_back() {
+        let newer = archive::published_observed_at(out)
+            .is_some_and(|published| !archive::is_older(&published, &latest.snapshot.observed_at));
+        if latest.from > 0 && !newer {
+            let body = read(archive::snapshot_path(out, &latest.snapshot.observed_at))?;
+            atomic::write(&out.join(archive::INDEX_FILE), &body)?;
+        }
+    }
+    println!(
+        "merged {} minutes: {} from {}, {} from {}, {} already in {}, {} duplicates resolved",
+        best.len(),
+        taken[1],
+        archives[1].display(),
+        taken[2],
+        archives[2].display(),
+        best.len() - taken[1] - taken[2],
+        out.display(),
+        duplicates
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::Value;
+
+    use std::fs::{remove_dir_all, write};
+    use std::path::Path;
+
+    use crate::compact;
+
+    /// An empty archive for one test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "weather_crawl-merge-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write a snapshot of `records` records into `base`, tagged with the
+    /// archive it was written to.
+    fn put(base: &Path, observed_at: &str, records: usize) {
+        let records: Vec<Value> = (0..records as u32)
+            .map(|id| serde_json::json!({ "id": 100 + id }))
+            .collect();
+        let doc = serde_json::json!({
+            "observed_at": observed_at,
+            "source": base.file_name().unwrap().to_str().unwrap(),
+            "records": records,
+        });
+        let body = serde_json::to_vec(&doc).unwrap();
+        write(archive::snapshot_path(base, observed_at), &body).unwrap();
+    }
+
+    fn publish(base: &Path, observed_at: &str) {
+        let doc = serde_json::json!({ "observed_at": observed_at, "records": [] });
+        write(base.join(archive::INDEX_FILE), doc.to_string()).unwrap();
+    }
+
+    fn merge(a: &Path, b: &Path, out: &Path) {
+        let [a, b, out] = [a, b, out].map(|p| p.to_str().unwrap().to_string());
+        run(&command().get_matches_from(["merge", &a, &b, &out])).unwrap();
+    }
+
+    /// The archive each snapshot in `out` came from, by minute.
+    fn sources(out: &Path) -> Vec<(String, String)> {
+        archive::list(out)
+            .unwrap()
+            .iter()
+            .map(|s| {
+                let doc: Value = serde_json::from_slice(&s.read().unwrap()).unwrap();
+                let source = doc["source"].as_str().unwrap().to_string();
+                (s.observed_at[..16].to_string(), source)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fuller_snapshots_win_and_ties_go_to_the_first() {
+        let root = scratch("overlap");
+        let [a, b, out] = ["a", "b", "out"].map(|name| root.join(name));
+        for dir in [&a, &b, &out] {
+            create_dir_all(dir).unwrap();
+        }
+        // The day before is bundled in `a`.
+        put(&a, "2024-04-30T23:40:00+09:00", 2);
+        put(&a, "2024-04-30T23:50:00+09:00", 1);
+        put(&a, "2024-05-01T12:00:00+09:00", 2);
+        put(&a, "2024-05-01T12:10:00+09:00", 1);
+        let a_path = a.to_str().unwrap();
+        compact::run(&compact::command().get_matches_from(["compact", a_path])).unwrap();
+        assert_eq!(archive::list(&a).unwrap().len(), 2);
+
+        put(&b, "2024-04-30T23:50:00+09:00", 2);
+        put(&b, "2024-05-01T12:00:00+09:00", 2);
+        put(&b, "2024-05-01T12:10:00+09:00", 3);
+        put(&b, "2024-05-01T12:20:00+09:00", 3);
+        put(&out, "2024-05-01T12:20:00+09:00", 5);
+        publish(&out, "2024-05-01T13:00:00+09:00");
+
+        merge(&a, &b, &out);
+        assert_eq!(
+            sources(&out),
+            [
+                ("2024-04-30T23:40".to_string(), "a".to_string()),
+                ("2024-04-30T23:50".to_string(), "b".to_string()),
+                ("2024-05-01T12:00".to_string(), "a".to_string()),
+                ("2024-05-01T12:10".to_string(), "b".to_string()),
+                ("2024-05-01T12:20".to_string(), "out".to_string()),
+            ]
+        );
+        // `out` already showed a newer observation.
+        assert_eq!(
+            archive::published_observed_at(&out).as_deref(),
+            Some("2024-05-01T13:00:00+09:00")
+        );
+        // What was taken is in the manifest as written.
+        let manifest = Manifest::load(&out);
+        for (minute, records) in [("2024-04-30T23:40", 2), ("2024-05-01T12:10", 3)] {
+            let file = format!("{}:00+09:00.json", minute);
+            let entry = manifest.snapshots.iter().find(|e| e.file == file).unwrap();
+            assert_eq!(entry.records, records, "{}", file);
+            let body = read(out.join(&file)).unwrap();
+            assert_eq!(entry.sha256, crate::manifest::sha256_hex(&body), "{}", file);
+        }
+
+        // Merging again takes nothing new.
+        merge(&a, &b, &out);
+        assert_eq!(sources(&out).len(), 5);
+        remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn the_latest_is_published_over_an_older_index() {
+        let root = scratch("publish");
+        let [a, b, out] = ["a", "b", "out"].map(|name| root.join(name));
+        for dir in [&a, &b, &out] {
+            create_dir_all(dir).unwrap();
+        }
+        put(&a, "2024-05-01T12:00:00+09:00", 1);
+        put(&b, "2024-05-01T12:30:00+09:00", 1);
+        publish(&out, "2024-05-01T11:00:00+09:00");
+
+        merge(&a, &b, &out);
+        let index = archive::read_index(&out).unwrap();
+        assert_eq!(index["observed_at"], "2024-05-01T12:30:00+09:00");
+        assert_eq!(index["source"], "b");
+        remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use chrono::DateTime;
+
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use serde_json::Value;
+
+use std::fs::{read, read_link, remove_file, symlink_metadata};
+use std::path::{Path, PathBuf};
+
+use crate::manifest::{count_records, Entry, Manifest};
+use crate::{archive, atomic, compact, region};
+use crate::{unversioned, Rain, RainIntensity, Wind, SCHEMA_VERSION};
+
+/// Upgrades from each version to the next, starting at version 1.
+const STEPS: [fn(&mut Value); 1] = [v1_to_v2];
+
+pub fn command() -> Command {
+    Command::new("migrate")
+        .about("upgrade the snapshots, bundles and index.json under <base> to the current schema")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--"dry-run" "only report what would be upgraded"))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let dry_run = matches.get_flag("dry-run");
+    let mut manifest = Manifest::load(base);
+    let (mut upgraded, mut current) = (0, 0);
+    let index = base.join(archive::INDEX_FILE);
+    let linked = read_link(&index).ok().map(|target| base.join(target));
+    let verb = if dry_run { "would upgrade" } else { "upgraded" };
+
+    for snapshot in archive::list(base)? {
+        let mut doc: Value = serde_json::from_slice(&read(&snapshot.path)?)
+            .map_err(|e| format!("{}: {}", snapshot.path.display(), e))?;
+        if !upgrade(&mut doc) {
+            current += 1;
+            continue;
+        }
+        upgraded += 1;
+        // Older versions named snapshots by an observed_at written differently.
+        let observed_at = doc["observed_at"].as_str().unwrap_or(&snapshot.observed_at);
+        let path = archive::snapshot_path(base, observed_at);
+        println!("{} {}", verb, path.display());
+        if dry_run {
+            continue;
+        }
+        let body = serde_json::to_vec(&doc)?;
+        atomic::write(&path, &body)?;
+        if path != snapshot.path {
+            remove_file(&snapshot.path)?;
+            manifest.remove_snapshot(&file_name(&snapshot.path));
+            if linked.as_ref() == Some(&snapshot.path) {
+                atomic::symlink(Path::new(&file_name(&path)), &index)?;
+            }
+        }
+        manifest.put_snapshot(Entry::new(
+            &file_name(&path),
+            observed_at,
+            &body,
+            count_records(&body)?,
+        ));
+    }
+
+    for bundle in compact::bundles(base)? {
+        let mut stale = false;
+        compact::each_bundled(&bundle.path, &mut |doc| {
+            stale |= version(&doc) < SCHEMA_VERSION;
+            Ok(())
+        })?;
+        if !stale {
+            current += 1;
+            continue;
+        }
+        upgraded += 1;
+        println!("{} {}", verb, bundle.path.display());
+        if dry_run {
+            continue;
+        }
+        let records = compact::rewrite_bundle(&bundle.path, &mut |doc| {
+            upgrade(doc);
+        })?;
+        manifest.put_bundle(Entry::new(
+            &file_name(&bundle.path),
+            &bundle.day,
+            &read(&bundle.path)?,
+            records,
+        ));
+    }
+
+    // A symlinked index.json follows its snapshot.
+    if symlink_metadata(&index).is_ok_and(|m| m.is_file()) {
+        let mut doc: Value = serde_json::from_slice(&read(&index)?)?;
+        if upgrade(&mut doc) {
+            upgraded += 1;
+            println!("{} {}", verb, index.display());
+            if !dry_run {
+                atomic::write(&index, &serde_json::to_vec(&doc)?)?;
+            }
+        } else {
+            current += 1;
+        }
+    }
+
+    if !dry_run {
+        manifest.save(base)?;
+    }
+    println!(
+        "{} files {} to schema version {}, {} already current",
+        upgraded, verb, SCHEMA_VERSION, current
+    );
+    Ok(())
+}
+
+fn version(doc: &Value) -> u32 {
+    doc["schema_version"]
+        .as_u64()
+        .map_or(unversioned(), |v| v as u32)
+}
+
+/// Bring `doc` to the current schema, returning whether it changed.
+fn upgrade(doc: &mut Value) -> bool {
+    let from = version(doc);
+    if from >= SCHEMA_VERSION {
+        return false;
+    }
+    for step in &STEPS[from as usize - 1..] {
+        step(doc);
+    }
+    if let Some(doc) = doc.as_object_mut() {
+        // Keep the version in front, where the crawler writes it.
+        doc.shift_remove("schema_version");
+        doc.shift_insert(0, "schema_version".into(), Value::from(SCHEMA_VERSION));
+    }
+    true
+}
+
+/// Version 2 writes `observed_at` in RFC 3339, and adds the Beaufort force
+/// and bearing of winds, rain intensity, and the region of the address.
+fn v1_to_v2(doc: &mut Value) {
+    if let Some(observed_at) = doc["observed_at"]
+        .as_str()
+        .and_then(|s| DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%z").ok())
+    {
+        doc["observed_at"] = Value::from(observed_at.to_rfc3339());
+    }
+    // Converted values would give wrong Beaufort forces and intensities.
+    let metric = doc["units"].is_null();
+    let records = doc.get_mut("records").and_then(Value::as_array_mut);
+    for record in records.into_iter().flatten() {
+        if metric {
+            for field in ["wind1", "wind10"] {
+                if let Ok(wind) = serde_json::from_value::<Wind>(record[field].clone()) {
+                    let wind = Wind::new(wind.direction_code, wind.direction_text, wind.velocity);
+                    record[field] = serde_json::to_value(wind).unwrap();
+                }
+            }
+            if let Ok(mut rain) = serde_json::from_value::<Rain>(record["rain"].clone()) {
+                rain.intensity = rain.rain60.map(RainIntensity::of);
+                record["rain"] = serde_json::to_value(rain).unwrap();
+            }
+        }
+        let region = record["address"]
+            .as_str()
+            .and_then(|address| region::parse(address, None));
+        if let (Some(region), Some(record)) = (region, record.as_object_mut()) {
+            record
+                .entry("region")
+                .or_insert_with(|| serde_json::to_value(region).unwrap());
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().into_owned()
+}
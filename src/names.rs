@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Official English names of the ASOS stations, as published by KMA.
+const BUNDLED: &str = include_str!("../data/station_names_en.json");
+
+/// Official English station names keyed by station id.
+///
+/// These are the names KMA itself uses in English publications, which do
+/// not always match a mechanical romanization of the Korean name.
+pub struct NameTable {
+    names: HashMap<u32, String>,
+}
+impl NameTable {
+    pub fn bundled() -> Self {
+        NameTable {
+            names: serde_json::from_str(BUNDLED).expect("bundled station names are valid"),
+        }
+    }
+
+    /// Overlay the bundled table with a `{"<id>": "<name>"}` JSON file.
+    pub fn extend_from(&mut self, path: &Path) -> std::io::Result<()> {
+        let names: HashMap<u32, String> =
+            serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        self.names.extend(names);
+        Ok(())
+    }
+
+    pub fn get(&self, id: u32) -> Option<&str> {
+        self.names.get(&id).map(String::as_str)
+    }
+}
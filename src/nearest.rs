@@ -0,0 +1,132 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use std::path::PathBuf;
+
+use crate::archive;
+use crate::stations::{Catalog, Station};
+
+#[derive(Serialize)]
+struct Nearby<'a> {
+    distance_km: f64,
+    record: &'a Value,
+}
+
+pub fn command() -> Command {
+    Command::new("nearest")
+        .about("print the stations closest to a point with their latest observations")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--lat <DEGREES> "latitude of the point")
+                .value_parser(value_parser!(f64))
+                .allow_negative_numbers(true)
+                .required(true),
+        )
+        .arg(
+            arg!(--lon <DEGREES> "longitude of the point")
+                .value_parser(value_parser!(f64))
+                .allow_negative_numbers(true)
+                .required(true),
+        )
+        .arg(
+            arg!(--n <N> "number of stations to print")
+                .value_parser(value_parser!(usize))
+                .default_value("3"),
+        )
+        .arg(
+            arg!(--"stations-file" <PATH> "JSON station catalog overlaying the bundled one")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(arg!(--json "print the stations as JSON"))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let latitude = *matches.get_one::<f64>("lat").unwrap();
+    let longitude = *matches.get_one::<f64>("lon").unwrap();
+    let mut catalog = Catalog::bundled();
+    if let Some(path) = matches.get_one::<PathBuf>("stations-file") {
+        catalog.extend_from(path)?;
+    }
+
+    let doc = archive::read_index(base)?;
+    let records = doc["records"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    // Prefer the station the crawl joined in, so the document stands alone.
+    let mut nearby: Vec<Nearby> = records
+        .iter()
+        .filter_map(|record| {
+            let joined = serde_json::from_value::<Station>(record["station"].clone()).ok();
+            let station = match joined {
+                Some(station) => station,
+                None => catalog.get(record["id"].as_u64()? as u32)?.clone(),
+            };
+            Some(Nearby {
+                distance_km: station.distance_to(latitude, longitude),
+                record,
+            })
+        })
+        .collect();
+    if nearby.is_empty() {
+        return Err(format!("no station under {} has known coordinates", base.display()).into());
+    }
+    nearby.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+    nearby.truncate(*matches.get_one::<usize>("n").unwrap());
+
+    if matches.get_flag("json") {
+        println!("{}", serde_json::to_string(&nearby)?);
+        return Ok(());
+    }
+    let (temperature, wind, rain) = (
+        unit(&doc, "temperature"),
+        unit(&doc, "wind"),
+        unit(&doc, "rain"),
+    );
+    println!("as of {}", doc["observed_at"].as_str().unwrap_or("?"));
+    for Nearby {
+        distance_km,
+        record,
+    } in &nearby
+    {
+        println!(
+            "{:>7.1}km  {:>4} {}: {}, humidity {}, wind {} {}, rain {}",
+            distance_km,
+            record["id"],
+            label(record),
+            show(&record["temperature"], &temperature),
+            show(&record["humidity"], "%"),
+            show(&record["wind10"]["velocity"], &wind),
+            record["wind10"]["direction_text"].as_str().unwrap_or(""),
+            show(&record["rain"]["rain60"], &format!("{}/h", rain)),
+        );
+    }
+    Ok(())
+}
+
+/// Station name of `record`, with the English one when known.
+pub fn label(record: &Value) -> String {
+    let name = record["name"].as_str().unwrap_or("");
+    match record["name_en"].as_str() {
+        Some(en) => format!("{} ({})", name, en),
+        None => name.to_string(),
+    }
+}
+
+/// Unit `doc` writes `field` in.
+pub fn unit(doc: &Value, field: &str) -> String {
+    let metric = match field {
+        "temperature" => "°C",
+        "wind" => "m/s",
+        _ => "mm",
+    };
+    doc["units"][field].as_str().unwrap_or(metric).to_string()
+}
+
+/// `value` with its unit, or `-` when it was not observed.
+pub fn show(value: &Value, unit: &str) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        value => format!("{}{}", value, unit),
+    }
+}
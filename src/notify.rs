@@ -0,0 +1,153 @@
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+
+use rust_decimal::Decimal;
+
+use serde_json::{json, Value};
+
+/// A chat service taking messages at an incoming webhook, or at the
+/// `sendMessage` of a Telegram bot.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Chat {
+    Slack,
+    Discord,
+    /// The id of the chat, or the `@username` of a channel, to send to.
+    #[cfg(feature = "telegram")]
+    Telegram(String),
+}
+impl Chat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Chat::Slack => "slack",
+            Chat::Discord => "discord",
+            #[cfg(feature = "telegram")]
+            Chat::Telegram(_) => "telegram",
+        }
+    }
+
+    /// Longest message the service takes; Discord refuses longer ones and
+    /// Slack cuts them off.
+    fn limit(&self) -> usize {
+        match self {
+            Chat::Slack => 40_000,
+            Chat::Discord => 2_000,
+            #[cfg(feature = "telegram")]
+            Chat::Telegram(_) => 4_096,
+        }
+    }
+
+    /// `text` in bold, in the markup of the service; messages to Telegram
+    /// are sent as plain text.
+    pub fn bold(&self, text: &str) -> String {
+        match self {
+            Chat::Slack => format!("*{}*", text),
+            Chat::Discord => format!("**{}**", text),
+            #[cfg(feature = "telegram")]
+            Chat::Telegram(_) => text.to_string(),
+        }
+    }
+
+    /// The body of a webhook request posting `text`, with the lines that do
+    /// not fit left out.
+    fn payload(&self, text: &str) -> Value {
+        let mut message = String::new();
+        let lines: Vec<&str> = text.lines().collect();
+        for (i, line) in lines.iter().enumerate() {
+            let more = format!("… and {} more", lines.len() - i);
+            if message.chars().count() + line.chars().count() + more.chars().count() + 2
+                > self.limit()
+            {
+                message.push_str(&more);
+                break;
+            }
+            message.push_str(line);
+            message.push('\n');
+        }
+        let message = message.trim_end();
+        match self {
+            Chat::Slack => json!({ "text": message }),
+            Chat::Discord => json!({ "content": message }),
+            #[cfg(feature = "telegram")]
+            Chat::Telegram(chat_id) => json!({ "chat_id": chat_id, "text": message }),
+        }
+    }
+
+    /// Post `text` to the webhook at `url`.
+    pub async fn post(&self, client: &Client, url: &str, text: &str) -> Result<(), String> {
+        let response = client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(self.payload(text).to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("webhook answered HTTP {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// One line on `doc`, a crawl in the units it is written in: how many
+/// stations, the range of temperature, the heaviest rain of the hour and the
+/// strongest wind.
+pub fn summary(chat: &Chat, source: &str, doc: &Value) -> String {
+    let records: Vec<&Value> = doc["records"].as_array().into_iter().flatten().collect();
+    // The station with the highest reading at `pointer`, or the lowest.
+    let extreme = |pointer: &str, highest: bool| {
+        records
+            .iter()
+            .filter_map(|record| {
+                let value = serde_json::from_value::<Decimal>(record.pointer(pointer)?.clone());
+                Some((value.ok()?, record["name"].as_str().unwrap_or_default()))
+            })
+            .reduce(|a, b| if (b.0 > a.0) == highest { b } else { a })
+    };
+    let mut parts = vec![format!("{} stations", records.len())];
+    if let (Some(low), Some(high)) = (
+        extreme("/temperature", false),
+        extreme("/temperature", true),
+    ) {
+        let temperature = unit(doc, "temperature");
+        parts.push(format!(
+            "{} {} at {} to {} {} at {}",
+            low.0, temperature, low.1, high.0, temperature, high.1
+        ));
+    }
+    match extreme("/rain/rain60", true) {
+        Some((mm, name)) if !mm.is_zero() => parts.push(format!(
+            "rain of {} {} in the hour at {}",
+            mm,
+            unit(doc, "rain"),
+            name
+        )),
+        _ => parts.push("no rain in the hour".to_string()),
+    }
+    if let Some((speed, name)) = extreme("/wind10/velocity", true) {
+        parts.push(format!(
+            "wind up to {} {} at {}",
+            speed,
+            unit(doc, "wind"),
+            name
+        ));
+    }
+    format!(
+        "{}: {}",
+        chat.bold(&format!(
+            "{} {}",
+            source,
+            doc["observed_at"].as_str().unwrap_or_default()
+        )),
+        parts.join(", ")
+    )
+}
+
+/// The unit `doc` gives `name` in, one of `temperature`, `wind` and `rain`; a
+/// metric document carries no `units`.
+pub fn unit<'a>(doc: &'a Value, name: &str) -> &'a str {
+    doc["units"][name].as_str().unwrap_or(match name {
+        "temperature" => "°C",
+        "wind" => "m/s",
+        _ => "mm",
+    })
+}
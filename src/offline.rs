@@ -0,0 +1,53 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use std::fs::read;
+use std::io::{stdin, stdout, Read, Write};
+use std::path::PathBuf;
+
+use crate::error::CrawlError;
+use crate::timezone::OutputTz;
+use crate::{charset, parse_page};
+
+pub fn command() -> Command {
+    Command::new("parse")
+        .about("parse a saved AWS page without touching the network and print the result")
+        .arg(
+            arg!(-i --input <PATH> "saved page to parse, or `-` for stdin")
+                .value_parser(value_parser!(PathBuf))
+                .required(true),
+        )
+        .arg(arg!(--pretty "pretty-print the resulting JSON"))
+        .arg(arg!(--"strict-encoding" "fail instead of replacing bytes that do not decode"))
+        .arg(
+            arg!(--tz <TZ> "timezone of observed_at: utc, local, or an offset like +09:00")
+                .value_parser(value_parser!(OutputTz))
+                .allow_hyphen_values(true)
+                .default_value("+09:00"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let input = matches.get_one::<PathBuf>("input").unwrap();
+    let (source, blob) = if input.as_os_str() == "-" {
+        let mut blob = Vec::new();
+        stdin().read_to_end(&mut blob)?;
+        ("stdin".to_string(), blob)
+    } else {
+        (format!("file:{}", input.display()), read(input)?)
+    };
+    let html = charset::decode(&blob, None, matches.get_flag("strict-encoding"))
+        .map_err(CrawlError::Decode)?;
+    let mut result = parse_page(&source, &html)?;
+    result.observed_at = matches
+        .get_one::<OutputTz>("tz")
+        .unwrap()
+        .convert(result.observed_at);
+    let mut out = stdout().lock();
+    if matches.get_flag("pretty") {
+        serde_json::to_writer_pretty(&mut out, &result)?;
+    } else {
+        serde_json::to_writer(&mut out, &result)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
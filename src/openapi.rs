@@ -0,0 +1,261 @@
+use chrono::{NaiveDateTime, Utc};
+
+use rust_decimal::prelude::*;
+
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+
+use crate::archive;
+use crate::error::CrawlError;
+use crate::pipeline::Outcome;
+use crate::quantity::{Celsius, HectoPascals, MetersPerSecond, Millimeters};
+use crate::source::{self, Source};
+use crate::timezone;
+use crate::{
+    reading, CrawlResult, Rain, RainIntensity, RainStatus, Record, SkippedRow, Wind,
+    WindDirectionText, SCHEMA_VERSION,
+};
+
+/// ASOS hourly observations of Seoul; other stations with `stnIds=`.
+pub const OPENAPI_URL: &str = "https://apis.data.go.kr/1360000/AsosHourlyInfoService/getWthrDataList?pageNo=1&numOfRows=999&dataType=JSON&dataCd=ASOS&dateCd=HR&stnIds=108";
+
+/// ASOS hourly observations from KMA's Open API on data.go.kr.
+///
+/// The service publishes a day's hours on the next day, so the newest page
+/// is 23:00 KST of yesterday.
+pub struct OpenApi;
+impl Source for OpenApi {
+    fn name(&self) -> &'static str {
+        "openapi"
+    }
+
+    fn default_url(&self) -> &'static str {
+        OPENAPI_URL
+    }
+
+    fn needs_api_key(&self) -> bool {
+        true
+    }
+
+    fn latest_url(&self, url: &str) -> String {
+        let now = archive::minute_at(&Utc::now().fixed_offset());
+        let yesterday = now.div_euclid(24 * 60) - 1;
+        self.timed_url(url, yesterday * 24 * 60 + 23 * 60)
+            .unwrap_or_else(|| url.to_string())
+    }
+
+    /// The hour `minute` falls in, e.g. `startDt=20240501&startHh=12`.
+    fn timed_url(&self, url: &str, minute: i64) -> Option<String> {
+        let stamp = archive::format_minute(minute);
+        let day: String = stamp[..10].chars().filter(char::is_ascii_digit).collect();
+        let hour = &stamp[11..13];
+        Some(source::with_query(
+            url,
+            &[
+                ("startDt", &day),
+                ("startHh", hour),
+                ("endDt", &day),
+                ("endHh", hour),
+            ],
+        ))
+    }
+
+    fn problem(&self, body: &str) -> Option<Outcome> {
+        problem(body)
+    }
+
+    fn parse(&self, url: &str, body: &str) -> Result<CrawlResult, CrawlError> {
+        let items = items(body).map_err(CrawlError::Decode)?;
+        let mut observed_at = None;
+        let mut records = Vec::new();
+        let mut skipped = Vec::new();
+        for item in &items {
+            let field = |name: &str| item[name].as_str().unwrap_or_default().trim();
+            let stamp = field("tm");
+            let at = NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M")
+                .ok()
+                .and_then(|naive| naive.and_local_timezone(timezone::kst()).single())
+                .ok_or_else(|| {
+                    CrawlError::Decode(format!("time `{}` is not YYYY-MM-DD HH:MM", stamp))
+                })?;
+            observed_at = observed_at.max(Some(at));
+            match make_record(&field) {
+                Ok(record) => records.push(record),
+                Err(reason) => skipped.push(SkippedRow {
+                    reason,
+                    cells: ["tm", "stnId", "stnNm", "ta", "rn", "ws", "wd", "hm", "pa"]
+                        .iter()
+                        .map(|name| field(name).to_string())
+                        .collect(),
+                }),
+            }
+        }
+        Ok(CrawlResult {
+            schema_version: SCHEMA_VERSION,
+            attribution: None,
+            instance: None,
+            units: None,
+            observed_at: observed_at
+                .ok_or_else(|| CrawlError::Decode("no observations in the response".into()))?,
+            // The key is the user's own and must not end up in the documents.
+            source: source::without_query(url, "serviceKey"),
+            records,
+            marine: Vec::new(),
+            air: Vec::new(),
+            regions: Vec::new(),
+            skipped,
+        })
+    }
+}
+
+/// Why a response of an Open API service has no rows, if it has none.
+pub fn problem(body: &str) -> Option<Outcome> {
+    let Ok(doc) = serde_json::from_str::<Value>(body) else {
+        // Gateway errors, such as an unregistered key, come as XML.
+        return Some(match xml_text(body, "returnAuthMsg") {
+            Some(message) => Outcome::Down(format!("Open API: {}", message)),
+            None => Outcome::Retry("not an Open API response".into()),
+        });
+    };
+    let header = &doc["response"]["header"];
+    match header["resultCode"].as_str() {
+        Some("00") if doc["response"]["body"]["totalCount"] == 0 => {
+            Some(Outcome::Retry("Open API has no data for the hour".into()))
+        }
+        Some("00") => None,
+        // NODATA_ERROR: the hour is not published yet.
+        Some("03") => Some(Outcome::Retry("Open API has no data for the hour".into())),
+        code => Some(Outcome::Down(format!(
+            "Open API answered {}: {}",
+            code.unwrap_or("without a result code"),
+            header["resultMsg"].as_str().unwrap_or_default()
+        ))),
+    }
+}
+
+/// Rows of a JSON response of any of the Open API services on data.go.kr,
+/// KMA's or AirKorea's; none when the service has no data (`NODATA_ERROR`).
+pub fn items(body: &str) -> Result<Vec<Value>, String> {
+    let Ok(mut doc) = serde_json::from_str::<Value>(body) else {
+        return Err(match xml_text(body, "returnAuthMsg") {
+            Some(message) => format!("Open API: {}", message),
+            None => "not an Open API response".into(),
+        });
+    };
+    let header = &doc["response"]["header"];
+    match header["resultCode"].as_str() {
+        Some("00") => {}
+        Some("03") => return Ok(Vec::new()),
+        code => {
+            return Err(format!(
+                "Open API answered {}: {}",
+                code.unwrap_or("without a result code"),
+                header["resultMsg"].as_str().unwrap_or_default()
+            ))
+        }
+    }
+    Ok(match doc["response"]["body"]["items"].take() {
+        // AirKorea's services leave out the `item` level.
+        Value::Array(items) => items,
+        mut items => match items.get_mut("item").map(Value::take) {
+            Some(Value::Array(items)) => items,
+            // A single row is sometimes not wrapped in an array.
+            Some(item @ Value::Object(_)) => vec![item],
+            _ => Vec::new(),
+        },
+    })
+}
+
+fn make_record<'a>(field: &impl Fn(&str) -> &'a str) -> Result<Record, String> {
+    let id = u32::from_str(field("stnId")).map_err(|e| format!("invalid station id: {}", e))?;
+    let mut quality = BTreeMap::new();
+    let mut read = |name: &str, key: &str| reading(&mut quality, name, field(key));
+    let temperature = read("temperature", "ta").map(Celsius);
+    let velocity = read("wind10", "ws").map(MetersPerSecond);
+    let direction = read("wind10_direction", "wd");
+    let humidity = read("humidity", "hm");
+    let atmospheric = read("atmospheric", "pa").map(HectoPascals);
+    let sunshine = read("sunshine", "ss");
+    // Blank at night rather than missing.
+    let solar_radiation = match field("icsr") {
+        "" => None,
+        _ => read("solar_radiation", "icsr"),
+    };
+    // Blank without snow on the ground.
+    let snow_depth = match field("dsnw") {
+        "" => None,
+        _ => read("snow_depth", "dsnw"),
+    };
+    // In units of 10m.
+    let visibility = read("visibility", "vs").map(|tens| tens / Decimal::from(100));
+    let cloud_cover = read("cloud_cover", "dc10Tca");
+    let pressure_sea_level = read("pressure_sea_level", "ps").map(HectoPascals);
+    // Hours without rain leave `rn` blank; QC flag 9 marks a missing value.
+    let rain60 = match (field("rn"), field("rnQcflg")) {
+        (_, "9") => None,
+        ("", _) => Some(Millimeters(Decimal::ZERO)),
+        _ => read("rain60", "rn").map(Millimeters),
+    };
+    Ok(Record {
+        id,
+        name: field("stnNm").into(),
+        name_en: None,
+        station: None,
+        height: None,
+        rain: Rain {
+            is_raining: match rain60 {
+                Some(Millimeters(mm)) if mm > Decimal::ZERO => RainStatus::Rain,
+                Some(_) => RainStatus::Clear,
+                None => RainStatus::Unavailable,
+            },
+            rain15: None,
+            rain60,
+            rain3h: None,
+            rain6h: None,
+            rain12h: None,
+            rainday: None,
+            intensity: rain60.map(RainIntensity::of),
+        },
+        temperature,
+        wind1: Wind::new(None, WindDirectionText::Unavailable, None),
+        // ASOS reports the 10-minute mean wind.
+        wind10: Wind::new(direction, direction_text(direction, velocity), velocity),
+        humidity,
+        atmospheric,
+        sunshine,
+        solar_radiation,
+        snow_depth,
+        visibility,
+        cloud_cover,
+        pressure_sea_level,
+        address: String::new(),
+        region: None,
+        spatial_flags: Vec::new(),
+        quality,
+        derived: None,
+        trend: None,
+        astro: None,
+        air: None,
+    })
+}
+
+/// Compass point of a bearing in degrees; calm wind has none.
+fn direction_text(
+    degrees: Option<Decimal>,
+    velocity: Option<MetersPerSecond>,
+) -> WindDirectionText {
+    match (degrees.and_then(|d| d.to_f64()), velocity) {
+        (_, Some(MetersPerSecond(v))) if v.is_zero() => WindDirectionText::No,
+        (Some(degrees), _) => WindDirectionText::from_point((degrees / 22.5).round() as usize),
+        (None, _) => WindDirectionText::Unavailable,
+    }
+}
+
+/// Text of the first `<tag>` of an XML document.
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find('<')?;
+    Some(xml[start..end].trim())
+}
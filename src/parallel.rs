@@ -0,0 +1,115 @@
+use reqwest::Client;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use tracing::{error, info, info_span, warn, Instrument};
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::crawler::{self, RetryPolicy};
+use crate::error::CrawlError;
+use crate::lock;
+use crate::pipeline::Settings;
+use crate::stats::CrawlStats;
+
+/// One product of a crawl of several.
+pub struct Job {
+    pub settings: Settings,
+    pub urls: Vec<String>,
+}
+
+/// Crawl every job at once, at most `limit` at a time, each holding the
+/// lock on its own base path, and log how each went.
+///
+/// The outcome is the first failure in the order of `jobs`, so that the
+/// exit status is the one a crawl of that product alone would have had.
+pub async fn crawl(
+    client: &Client,
+    jobs: Vec<Job>,
+    limit: usize,
+    lock_wait: Duration,
+    stats_file: Option<&PathBuf>,
+) -> Result<(), CrawlError> {
+    let started = Instant::now();
+    let limit = Arc::new(Semaphore::new(limit));
+    let mut tasks = JoinSet::new();
+    for (i, job) in jobs.into_iter().enumerate() {
+        let (client, limit) = (client.clone(), limit.clone());
+        let name = job.settings.source.name();
+        tasks.spawn(
+            async move {
+                let _permit = limit.acquire_owned().await.unwrap();
+                let started = Instant::now();
+                let mut stats = CrawlStats {
+                    source: Some(name.to_string()),
+                    ..CrawlStats::default()
+                };
+                let result = match lock::acquire(&job.settings.base, lock_wait).await {
+                    Ok(_lock) => {
+                        crawler::cycle(
+                            &client,
+                            &job.urls,
+                            &job.settings,
+                            RetryPolicy::default(),
+                            &mut stats,
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                };
+                stats.finish(&result);
+                (i, started.elapsed(), result, stats)
+            }
+            .instrument(info_span!("source", source = name)),
+        );
+    }
+    let mut outcomes = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    outcomes.sort_by_key(|(i, ..)| *i);
+    let products = outcomes.len();
+    let mut failed = 0;
+    let mut first_failure = None;
+    for (_, took, result, stats) in outcomes {
+        let source = stats.source.as_deref().unwrap_or_default();
+        match &result {
+            Ok(()) => info!(
+                source,
+                outcome = %stats.outcome,
+                observed_at = stats.observed_at.as_deref(),
+                took_ms = took.as_millis(),
+                "product crawled"
+            ),
+            Err(e) => {
+                failed += 1;
+                warn!(source, error = %e, took_ms = took.as_millis(), "product failed");
+            }
+        }
+        if let Some(path) = stats_file {
+            emit(&stats, path);
+        }
+        if let (Err(e), None) = (result, &first_failure) {
+            first_failure = Some(e);
+        }
+    }
+    info!(
+        products,
+        failed,
+        took_ms = started.elapsed().as_millis(),
+        "crawl of all products done"
+    );
+    first_failure.map_or(Ok(()), Err)
+}
+
+fn emit(stats: &CrawlStats, path: &Path) {
+    if let Err(e) = stats.emit(path) {
+        error!(error = %e, path = %path.display(), "writing crawl stats failed");
+    }
+}
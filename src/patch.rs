@@ -0,0 +1,94 @@
+use serde_json::{json, Map, Value};
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::archive::INDEX_FILE;
+use crate::atomic;
+
+/// How the change from the previous `index.json` is published.
+#[derive(Clone, Copy)]
+pub enum PatchFormat {
+    /// RFC 6902, written to `index.patch.json`.
+    JsonPatch,
+    /// RFC 7386, written to `index.merge-patch.json`. Arrays cannot be
+    /// patched in this format, so any change to `records` resends all of them.
+    MergePatch,
+}
+impl PatchFormat {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "json-patch" => Some(PatchFormat::JsonPatch),
+            "merge-patch" => Some(PatchFormat::MergePatch),
+            _ => None,
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            PatchFormat::JsonPatch => "index.patch.json",
+            PatchFormat::MergePatch => "index.merge-patch.json",
+        }
+    }
+}
+
+/// The currently published document, if there is one to diff against.
+pub fn read_previous(base: &Path) -> Option<Value> {
+    let file = File::open(base.join(INDEX_FILE)).ok()?;
+    serde_json::from_reader(BufReader::new(file)).ok()
+}
+
+/// Write the patch turning `previous` into `current` next to `index.json`.
+///
+/// JSON Patch output starts with a `test` of the previous `observed_at`, so
+/// applying it to any other document fails instead of corrupting it.
+pub fn write_patch(
+    base: &Path,
+    format: PatchFormat,
+    previous: &Value,
+    current: &Value,
+) -> std::io::Result<()> {
+    let patch = match format {
+        PatchFormat::JsonPatch => {
+            let mut ops = vec![json!({
+                "op": "test",
+                "path": "/observed_at",
+                "value": previous["observed_at"],
+            })];
+            if let Value::Array(diff) = serde_json::to_value(json_patch::diff(previous, current))? {
+                ops.extend(diff);
+            }
+            Value::Array(ops)
+        }
+        PatchFormat::MergePatch => merge_diff(previous, current),
+    };
+    atomic::write(&base.join(format.file_name()), &serde_json::to_vec(&patch)?)
+}
+
+/// RFC 7386 merge patch from `previous` to `current`.
+fn merge_diff(previous: &Value, current: &Value) -> Value {
+    match (previous, current) {
+        (Value::Object(before), Value::Object(after)) => {
+            let mut patch = Map::new();
+            for key in before.keys() {
+                if !after.contains_key(key) {
+                    patch.insert(key.clone(), Value::Null);
+                }
+            }
+            for (key, value) in after {
+                match before.get(key) {
+                    Some(old) if old == value => {}
+                    Some(old) => {
+                        patch.insert(key.clone(), merge_diff(old, value));
+                    }
+                    None => {
+                        patch.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            Value::Object(patch)
+        }
+        _ => current.clone(),
+    }
+}
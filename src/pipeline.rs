@@ -0,0 +1,519 @@
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
+
+use serde::Serialize;
+
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+use std::collections::BTreeSet;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::air::{self, AirStations};
+use crate::alert::{Alerts, Rules};
+use crate::archive::{self, IndexMode};
+use crate::attribution::Attribution;
+use crate::budget::{Budget, BudgetExceeded};
+use crate::cache::HttpCache;
+use crate::error::CrawlError;
+use crate::fault::FaultPlan;
+use crate::instance::Instance;
+use crate::names::NameTable;
+use crate::patch::{self, PatchFormat};
+use crate::polite::HostLimit;
+use crate::publish::{self, Profile};
+use crate::qc::{self, SpatialQcOptions, ValidationOptions};
+use crate::sink::{self, Target};
+use crate::source::Source;
+use crate::state::State;
+use crate::stations::Catalog;
+use crate::stats::CrawlStats;
+use crate::timezone::OutputTz;
+use crate::trend::History;
+use crate::units::{self, Units};
+use crate::CrawlResult;
+use crate::{astro, atomic, delta, derived, failover, fixture, http, manifest, region, romanize};
+
+/// Everything that decides what happens to a page once it is fetched.
+pub struct Settings {
+    pub base: PathBuf,
+    pub secondary: Option<PathBuf>,
+    pub profile: Profile,
+    pub keep_snapshots: bool,
+    pub index_mode: IndexMode,
+    pub patch: Option<PatchFormat>,
+    /// With `--emit deltas`, how often a delta carries every record.
+    pub deltas: Option<u64>,
+    /// Product crawled.
+    pub source: &'static dyn Source,
+    /// Least time between two requests of a cycle, from `--rate-limit`.
+    pub request_gap: Option<Duration>,
+    /// Least time between requests to KMA's site across processes.
+    pub host_limit: Option<HostLimit>,
+    /// Responses reused across runs while fresh.
+    pub cache: Option<HttpCache>,
+    /// Where results go, in the order given.
+    pub sinks: Vec<Target>,
+    /// Keep only the records of these stations.
+    pub only_stations: Option<BTreeSet<u32>>,
+    pub strict: bool,
+    pub strict_encoding: bool,
+    pub force: bool,
+    pub min_records: usize,
+    pub record_fixture: Option<PathBuf>,
+    pub attribution_source: String,
+    pub license: String,
+    pub instance: Option<Instance>,
+    pub names: NameTable,
+    /// Romanize names the table lacks.
+    pub romanize: bool,
+    pub stations: Catalog,
+    pub spatial_qc: Option<SpatialQcOptions>,
+    pub validation: Option<ValidationOptions>,
+    pub derive: bool,
+    /// Add sunrise, sunset and the moon phase at each station.
+    pub astro: bool,
+    /// Compare stations against their readings from previous crawls.
+    pub trends: bool,
+    /// Summarize records per province.
+    pub aggregate_regions: bool,
+    /// Conditions to alert on after each crawl, from `--alert-rules`.
+    pub alerts: Option<Rules>,
+    /// Locations of AirKorea's stations, for the `air` source.
+    pub air_stations: Option<AirStations>,
+    /// Base path of an `air` crawl whose nearest station to join onto every
+    /// record.
+    pub join_air: Option<PathBuf>,
+    pub budget: Budget,
+    pub fault: Option<FaultPlan>,
+    pub tz: OutputTz,
+    pub units: Units,
+    /// Minute being backfilled: the page has to show it, and only its
+    /// snapshot is written.
+    pub backfill: Option<i64>,
+}
+
+pub enum Outcome {
+    /// KMA answered but is not serving observations, e.g. during maintenance.
+    Down(String),
+    /// The page is not an observation table worth keeping; try again.
+    Retry(String),
+    /// The page shows the observation written last time; nothing was written.
+    Unchanged,
+    /// The page was parsed and written, raising these alerts.
+    Done(Option<Alerts>),
+}
+
+pub async fn process_page(
+    settings: &Settings,
+    page: &Page,
+    last_written: Option<&str>,
+    stats: &mut CrawlStats,
+) -> Result<Outcome, CrawlError> {
+    let source = settings.source;
+    let decoded = info_span!("decode").in_scope(|| source.decode(page, settings.strict_encoding));
+    let html = match decoded {
+        Ok(html) => html,
+        Err(e) => return Ok(Outcome::Retry(format!("undecodable page: {}", e))),
+    };
+    if let Some(outcome) = source.problem(&html) {
+        return Ok(outcome);
+    }
+    let mut result = match info_span!("parse").in_scope(|| source.parse(&page.url, &html)) {
+        Ok(result) => result,
+        Err(e @ CrawlError::Timestamp(_)) => return Ok(Outcome::Retry(e.to_string())),
+        Err(e) => return Err(e),
+    };
+    if let Some(minute) = settings.backfill {
+        if archive::minute_at(&result.observed_at) != minute {
+            return Ok(Outcome::Retry(format!(
+                "page shows {}, not {}",
+                result.observed_at.to_rfc3339(),
+                archive::format_minute(minute)
+            )));
+        }
+    }
+    if let Some(dir) = &settings.record_fixture {
+        fixture::record(dir, &page.body, &result)?;
+    }
+    if let Some(fault) = &settings.fault {
+        fault.corrupt(&mut result);
+    }
+    if let Some(ids) = &settings.only_stations {
+        result.records.retain(|r| ids.contains(&r.id));
+        result.marine.retain(|r| ids.contains(&r.id));
+    }
+    report_skipped(&result);
+    stats.parsed(&page.url, &result);
+    let minute = archive::minute_at(&result.observed_at);
+    if !settings.force && last_written.and_then(archive::minute_of) == Some(minute) {
+        return Ok(Outcome::Unchanged);
+    }
+    if settings.strict && !result.skipped.is_empty() {
+        return Err(CrawlError::Strict(result.skipped.len()));
+    }
+    let count = result.records.len() + result.marine.len() + result.air.len();
+    if count < settings.min_records {
+        return Ok(Outcome::Retry(format!(
+            "only {} records, expected at least {}",
+            count, settings.min_records
+        )));
+    }
+    let mut history = (settings.backfill.is_none()
+        && (settings.trends || settings.validation.is_some()))
+    .then(|| History::load(&settings.base));
+    if let Some(options) = &settings.validation {
+        qc::validate(&mut result.records, minute, options, history.as_mut());
+    }
+    enrich(settings, &mut result);
+    if let (true, Some(history)) = (settings.trends, history.as_mut()) {
+        history.observe(minute, &mut result.records);
+    }
+    sink::write_all(settings, &result, stats)
+        .instrument(info_span!("write"))
+        .await?;
+    let alerts = match (&settings.alerts, settings.backfill) {
+        (Some(rules), None) => Some(rules.evaluate(
+            &settings.base,
+            minute,
+            result.observed_at.to_rfc3339(),
+            &result.records,
+        )),
+        _ => None,
+    };
+    if let Some(history) = history {
+        if let Err(e) = history.save(&settings.base) {
+            error!(error = %e, "saving station history failed");
+        }
+    }
+    Ok(Outcome::Done(alerts))
+}
+
+pub struct Page {
+    pub url: String,
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub enum Fetched {
+    Page(Page),
+    NotModified,
+    /// The request failed or was answered with an error; worth retrying.
+    Unavailable(String),
+}
+
+/// What every request of a crawl goes by, besides the client.
+#[derive(Clone, Copy, Default)]
+pub struct FetchOptions<'a> {
+    pub max_body: Option<usize>,
+    pub fault: Option<&'a FaultPlan>,
+    pub host_limit: Option<&'a HostLimit>,
+    pub cache: Option<&'a HttpCache>,
+}
+impl Settings {
+    pub fn fetch_options(&self) -> FetchOptions<'_> {
+        FetchOptions {
+            max_body: self.budget.body_bytes,
+            fault: self.fault.as_ref(),
+            host_limit: self.host_limit.as_ref(),
+            cache: self.cache.as_ref(),
+        }
+    }
+}
+
+/// Request `url` once, unless the cache has a fresh response to it.
+///
+/// Conditional headers are only sent to the URL the stored validators came
+/// from.
+pub async fn fetch(
+    client: &Client,
+    url: &str,
+    state: &State,
+    options: FetchOptions<'_>,
+) -> Result<Fetched, CrawlError> {
+    if let Some(page) = options.cache.and_then(|cache| cache.get(url)) {
+        info!("served from the cache");
+        return Ok(Fetched::Page(page));
+    }
+    if let Some(limit) = options.host_limit {
+        limit.wait(url).await;
+    }
+    if let Some(fault) = options.fault {
+        if let Some(delay) = fault.delay() {
+            tokio::time::sleep(delay).await;
+        }
+        if fault.should_fail() {
+            warn!(%url, "fault injection: failing request");
+            return Ok(Fetched::Unavailable("fault injection".into()));
+        }
+    }
+    let mut request = client.get(url);
+    if state.source.as_deref() == Some(url) {
+        if let Some(etag) = &state.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &state.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let r = match request.send().await {
+        Ok(r) => r,
+        Err(e) => return Ok(Fetched::Unavailable(e.to_string())),
+    };
+    info!(status = %r.status(), "response");
+    if r.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !r.status().is_success() {
+        return Ok(Fetched::Unavailable(format!("HTTP {}", r.status())));
+    }
+    let etag = http::header_string(&r, ETAG);
+    let last_modified = http::header_string(&r, LAST_MODIFIED);
+    let content_type = http::header_string(&r, CONTENT_TYPE);
+    let lifetime = options.cache.and_then(|cache| cache.lifetime(r.headers()));
+    // A connection dropped mid-body is retried like one that failed to open;
+    // a body over the budget is not.
+    let body = match read_body(r, options.max_body).await {
+        Ok(body) => body,
+        Err(CrawlError::Fetch { source, .. }) => {
+            return Ok(Fetched::Unavailable(source.to_string()))
+        }
+        Err(e) => return Err(e),
+    };
+    let page = Page {
+        url: url.to_string(),
+        body,
+        content_type,
+        etag,
+        last_modified,
+    };
+    if let (Some(cache), Some(lifetime)) = (options.cache, lifetime) {
+        if let Err(e) = cache.put(&page, lifetime) {
+            warn!(error = %e, "caching the response failed");
+        }
+    }
+    Ok(Fetched::Page(page))
+}
+
+/// Read the response body, giving up as soon as it grows past `max_body`.
+async fn read_body(
+    mut response: reqwest::Response,
+    max_body: Option<usize>,
+) -> Result<Vec<u8>, CrawlError> {
+    let url = response.url().to_string();
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|source| CrawlError::Fetch {
+        url: url.clone(),
+        source,
+    })? {
+        body.extend_from_slice(&chunk);
+        if let Some(limit) = max_body {
+            if body.len() > limit {
+                return Err(BudgetExceeded::BodyBytes(limit).into());
+            }
+        }
+    }
+    Ok(body)
+}
+
+fn report_skipped(result: &CrawlResult) {
+    info!(
+        observed_at = %result.observed_at.to_rfc3339(),
+        records = result.records.len(),
+        skipped = result.skipped.len(),
+        "parsed"
+    );
+    for row in result.skipped.iter() {
+        warn!(reason = %row.reason, cells = %row.cells.join(" | "), "skipped row");
+    }
+}
+
+/// Join data the page does not carry and run the optional QC passes.
+fn enrich(settings: &Settings, result: &mut CrawlResult) {
+    result.attribution = Some(Attribution::new(
+        &settings.attribution_source,
+        &settings.license,
+        &result.source,
+    ));
+    result.instance = settings.instance.clone();
+    result.observed_at = settings.tz.convert(result.observed_at);
+    result.units = Some(settings.units).filter(|u| *u != units::METRIC);
+    for record in result.records.iter_mut() {
+        record.name_en = match settings.names.get(record.id) {
+            Some(name) => Some(name.to_string()),
+            None if settings.romanize => Some(romanize::romanize(&record.name)),
+            None => None,
+        };
+        record.station = settings.stations.get(record.id).cloned();
+        record.region = region::parse(&record.address, record.station.as_ref());
+        if settings.derive {
+            record.derived = derived::derive(record, &result.observed_at);
+        }
+        if settings.astro {
+            record.astro = record
+                .station
+                .as_ref()
+                .and_then(|station| astro::astro(station, &result.observed_at));
+        }
+    }
+    let found = result
+        .records
+        .iter()
+        .filter(|r| r.station.is_some())
+        .count();
+    if settings.stations.first_sparse(found, result.records.len()) {
+        // Locations, regions, astro and spatial QC leave those records out.
+        warn!(
+            found,
+            records = result.records.len(),
+            "most records have no station in the catalog; pass the full AWS catalog with --stations-file"
+        );
+    }
+    if let Some(stations) = &settings.air_stations {
+        stations.locate(&mut result.air);
+    }
+    if let Some(base) = &settings.join_air {
+        if let Err(e) = air::join(&mut result.records, base) {
+            warn!(error = %e, "joining air quality failed");
+        }
+    }
+    if let Some(options) = &settings.spatial_qc {
+        qc::spatial_check(&mut result.records, &settings.stations, options);
+    }
+    if settings.aggregate_regions {
+        result.regions = region::summarize(&result.records);
+    }
+}
+
+pub fn write_output(settings: &Settings, result: &CrawlResult) -> Result<(), CrawlError> {
+    let observed_at = result.observed_at.to_rfc3339();
+    if settings.backfill.is_some() {
+        return write_to(&settings.base, settings, result).map_err(|source| CrawlError::Write {
+            path: settings.base.clone(),
+            source,
+        });
+    }
+    if !settings.force {
+        if let Some(current) = archive::published_observed_at(&settings.base) {
+            if archive::is_older(&observed_at, &current) {
+                return Err(CrawlError::Stale {
+                    observed_at,
+                    published: current,
+                });
+            }
+        }
+    }
+    let started = Instant::now();
+    let primary = write_to(&settings.base, settings, result).map_err(|source| CrawlError::Write {
+        path: settings.base.clone(),
+        source,
+    });
+    let written = match (primary, &settings.secondary) {
+        (Err(e), Some(secondary)) => {
+            error!(error = %e, secondary = %secondary.display(), "failing over");
+            write_to(secondary, settings, result)
+                .and_then(|_| failover::mark_pending(secondary, &observed_at))
+                .map(|_| secondary)
+                .map_err(|source| CrawlError::Write {
+                    path: secondary.clone(),
+                    source,
+                })
+        }
+        (Ok(_), Some(secondary)) => {
+            match failover::reconcile(secondary, &settings.base) {
+                Ok(0) => {}
+                Ok(n) => info!(snapshots = n, from = %secondary.display(), "reconciled"),
+                Err(e) => error!(error = %e, from = %secondary.display(), "reconciling failed"),
+            }
+            Ok(&settings.base)
+        }
+        (written, None) => written.map(|_| &settings.base),
+    };
+    let base = written?;
+    info!(
+        path = %base.join(archive::INDEX_FILE).display(),
+        %observed_at,
+        "done"
+    );
+    if let Some(limit) = settings.budget.write {
+        let took = started.elapsed();
+        if took > limit {
+            return Err(BudgetExceeded::Write(took, limit).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn write_to(base: &PathBuf, settings: &Settings, result: &CrawlResult) -> std::io::Result<()> {
+    let options = WriteOptions {
+        keep_snapshot: settings.keep_snapshots,
+        index_mode: settings.index_mode,
+        patch: settings.patch,
+        deltas: settings.deltas,
+        publish: settings.backfill.is_none(),
+        units: settings.units,
+    };
+    let observed_at = result.observed_at.to_rfc3339();
+    match settings.profile {
+        Profile::Full => write_result_files(base, &observed_at, &options, result),
+        Profile::Publish => {
+            write_result_files(base, &observed_at, &options, &publish::sanitize(result))
+        }
+    }
+}
+
+struct WriteOptions {
+    keep_snapshot: bool,
+    index_mode: IndexMode,
+    patch: Option<PatchFormat>,
+    deltas: Option<u64>,
+    /// Whether to update index.json, the patch and the delta, or only keep
+    /// the snapshot.
+    publish: bool,
+    units: Units,
+}
+
+fn write_result_files<T: Serialize>(
+    path: &PathBuf,
+    observed_at: &str,
+    options: &WriteOptions,
+    result: &T,
+) -> std::io::Result<()> {
+    create_dir_all(path)?;
+    let previous = (options.publish && (options.patch.is_some() || options.deltas.is_some()))
+        .then(|| patch::read_previous(path))
+        .flatten();
+    let mut doc = serde_json::to_value(result)?;
+    options.units.apply(&mut doc);
+    let body = serde_json::to_vec(&doc)?;
+    let snapshot_path = archive::snapshot_path(path, observed_at);
+    if options.keep_snapshot || !options.publish || options.index_mode == IndexMode::Symlink {
+        atomic::write(&snapshot_path, &body)?;
+        let file = snapshot_path.file_name().unwrap().to_string_lossy();
+        manifest::add_snapshot(path, &file, observed_at, &body)?;
+        debug!(path = %snapshot_path.display(), "wrote snapshot");
+    }
+    if !options.publish {
+        return Ok(());
+    }
+    let index = path.join(archive::INDEX_FILE);
+    match options.index_mode {
+        IndexMode::Copy => atomic::write(&index, &body)?,
+        IndexMode::Symlink => {
+            atomic::symlink(Path::new(snapshot_path.file_name().unwrap()), &index)?
+        }
+    }
+    if let (Some(format), Some(previous)) = (options.patch, &previous) {
+        patch::write_patch(path, format, previous, &doc)?;
+        debug!(path = %path.join(format.file_name()).display(), "wrote patch");
+    }
+    if let Some(full_every) = options.deltas {
+        delta::write_delta(path, full_every, previous.as_ref(), &doc)?;
+        debug!(path = %path.join(delta::DELTA_FILE).display(), "wrote delta");
+    }
+    Ok(())
+}
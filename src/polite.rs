@@ -0,0 +1,110 @@
+use reqwest::Url;
+
+use tracing::{info, warn};
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// File the times of the last requests are kept in, under the temp dir.
+const STATE_FILE: &str = "weather_crawl-hosts.json";
+
+/// How far in the future a claimed request can plausibly be.
+const CLOCK_SLACK_MS: u64 = 3600 * 1000;
+
+/// Least time between two requests to KMA's site, kept in a file every
+/// process shares so that it holds however often cron starts the crawler.
+///
+/// The file maps each host to the Unix time in milliseconds of its last
+/// request, or of the next one already promised to a waiting process.
+#[derive(Clone)]
+pub struct HostLimit {
+    pub interval: Duration,
+    pub path: PathBuf,
+}
+impl HostLimit {
+    /// The limit with its state in the temp dir, shared by every base path.
+    pub fn new(interval: Duration) -> Self {
+        HostLimit {
+            interval,
+            path: default_path(),
+        }
+    }
+
+    /// Wait until a request to the host of `url` is allowed, claiming it.
+    ///
+    /// Only kma.go.kr is limited; data.go.kr meters its keys itself. When the
+    /// state cannot be read or written the request goes ahead.
+    pub async fn wait(&self, url: &str) {
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .filter(|host| host == "kma.go.kr" || host.ends_with(".kma.go.kr"))
+        else {
+            return;
+        };
+        if self.interval.is_zero() {
+            return;
+        }
+        let delay = match self.claim(&host).await {
+            Ok(delay) => delay,
+            Err(e) => {
+                warn!(error = %e, path = %self.path.display(), "host rate limit state unusable");
+                return;
+            }
+        };
+        if !delay.is_zero() {
+            info!(%host, wait_ms = delay.as_millis(), "waiting before the next request to the host");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Record the next request to `host` under the file lock, returning how
+    /// long until it is due.
+    async fn claim(&self, host: &str) -> std::io::Result<Duration> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&self.path)?;
+        lock(&file).await?;
+        let mut body = String::new();
+        file.read_to_string(&mut body)?;
+        // A torn write from a crash only forgets the hosts.
+        let mut last: BTreeMap<String, u64> = serde_json::from_str(&body).unwrap_or_default();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let interval = self.interval.as_millis() as u64;
+        // Claims an hour ahead are from a clock that was set back since.
+        let due = last
+            .get(host)
+            .filter(|at| **at < now + CLOCK_SLACK_MS)
+            .map_or(now, |at| at.saturating_add(interval).max(now));
+        last.insert(host.to_string(), due);
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&serde_json::to_vec(&last)?)?;
+        Ok(Duration::from_millis(due - now))
+    }
+}
+
+pub fn default_path() -> PathBuf {
+    std::env::temp_dir().join(STATE_FILE)
+}
+
+/// Take the lock on `file`, which others only hold for a moment.
+async fn lock(file: &File) -> std::io::Result<()> {
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(()),
+            Err(TryLockError::WouldBlock) => {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+    }
+}
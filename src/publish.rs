@@ -0,0 +1,213 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use rust_decimal::Decimal;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+
+use crate::astro::Astro;
+use crate::attribution::Attribution;
+use crate::derived::Derived;
+use crate::qc::FieldQuality;
+use crate::quantity::{Celsius, HectoPascals};
+use crate::region::{Region, Summary};
+use crate::stations::Station;
+use crate::trend::Trend;
+use crate::units::Units;
+use crate::{AirRecord, CrawlResult, MarineRecord, NearestAir, Rain, Record, Wind};
+
+/// Shape of the written document.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Everything the crawler parsed, as-is.
+    Full,
+    /// Sanitized dataset that is safe to mirror publicly.
+    Publish,
+}
+impl Profile {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "full" => Some(Profile::Full),
+            "publish" => Some(Profile::Publish),
+            _ => None,
+        }
+    }
+}
+
+/// `CrawlResult` cleared for redistribution; the instance block is dropped,
+/// since host names are not for the public.
+#[derive(Serialize, JsonSchema)]
+pub struct PublicResult<'a> {
+    schema_version: u32,
+    attribution: Attribution,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    units: Option<Units>,
+    observed_at: DateTime<FixedOffset>,
+    source: &'a str,
+    records: Vec<PublicRecord<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    marine: &'a [MarineRecord],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    air: Vec<PublicAir<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    regions: &'a [Summary],
+}
+
+/// Subset of `Record` cleared for redistribution.
+///
+/// Station addresses are dropped on purpose: they are free text maintained by
+/// KMA and not part of the observation itself.
+#[derive(Serialize, JsonSchema)]
+struct PublicRecord<'a> {
+    id: u32,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_en: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    station: Option<PublicStation<'a>>,
+    rain: &'a Rain,
+    temperature: Option<Celsius>,
+    wind1: &'a Wind,
+    wind10: &'a Wind,
+    humidity: Option<Decimal>,
+    atmospheric: Option<HectoPascals>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sunshine: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    solar_radiation: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snow_depth: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    visibility: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cloud_cover: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pressure_sea_level: Option<HectoPascals>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<&'a Region>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    quality: &'a BTreeMap<String, FieldQuality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    derived: Option<&'a Derived>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trend: Option<&'a Trend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    astro: Option<&'a Astro>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    air: Option<&'a NearestAir>,
+}
+impl<'a> From<&'a Record> for PublicRecord<'a> {
+    fn from(r: &'a Record) -> Self {
+        PublicRecord {
+            id: r.id,
+            name: &r.name,
+            name_en: r.name_en.as_deref(),
+            station: r.station.as_ref().map(PublicStation::from),
+            rain: &r.rain,
+            temperature: r.temperature,
+            wind1: &r.wind1,
+            wind10: &r.wind10,
+            humidity: r.humidity,
+            atmospheric: r.atmospheric,
+            sunshine: r.sunshine,
+            solar_radiation: r.solar_radiation,
+            snow_depth: r.snow_depth,
+            visibility: r.visibility,
+            cloud_cover: r.cloud_cover,
+            pressure_sea_level: r.pressure_sea_level,
+            region: r.region.as_ref(),
+            quality: &r.quality,
+            derived: r.derived.as_ref(),
+            trend: r.trend.as_ref(),
+            astro: r.astro.as_ref(),
+            air: r.air.as_ref(),
+        }
+    }
+}
+
+/// Decimal places kept of published coordinates, about a kilometer.
+const COORDINATE_DECIMALS: i32 = 2;
+
+/// Subset of `Station` cleared for redistribution: coordinates are rounded
+/// and the elevation is dropped, like the height of the record.
+#[derive(Serialize, JsonSchema)]
+struct PublicStation<'a> {
+    id: u32,
+    latitude: f64,
+    longitude: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    admin_code: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operator: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date: Option<NaiveDate>,
+}
+impl<'a> From<&'a Station> for PublicStation<'a> {
+    fn from(s: &'a Station) -> Self {
+        PublicStation {
+            id: s.id,
+            latitude: round(s.latitude),
+            longitude: round(s.longitude),
+            admin_code: s.admin_code.as_deref(),
+            operator: s.operator.as_deref(),
+            start_date: s.start_date,
+        }
+    }
+}
+
+/// `AirRecord` with its coordinates rounded like those of `PublicStation`.
+#[derive(Serialize, JsonSchema)]
+struct PublicAir<'a> {
+    station: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latitude: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    longitude: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<&'a Region>,
+    pm10: Option<Decimal>,
+    pm25: Option<Decimal>,
+    pm10_grade: Option<u8>,
+    pm25_grade: Option<u8>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    quality: &'a BTreeMap<String, FieldQuality>,
+}
+impl<'a> From<&'a AirRecord> for PublicAir<'a> {
+    fn from(a: &'a AirRecord) -> Self {
+        PublicAir {
+            station: &a.station,
+            latitude: a.latitude.map(round),
+            longitude: a.longitude.map(round),
+            region: a.region.as_ref(),
+            pm10: a.pm10,
+            pm25: a.pm25,
+            pm10_grade: a.pm10_grade,
+            pm25_grade: a.pm25_grade,
+            quality: &a.quality,
+        }
+    }
+}
+
+fn round(degrees: f64) -> f64 {
+    let scale = 10f64.powi(COORDINATE_DECIMALS);
+    (degrees * scale).round() / scale
+}
+
+pub fn sanitize(result: &CrawlResult) -> PublicResult<'_> {
+    PublicResult {
+        schema_version: result.schema_version,
+        attribution: result
+            .attribution
+            .clone()
+            .unwrap_or_else(|| Attribution::default_for(&result.source)),
+        units: result.units,
+        observed_at: result.observed_at,
+        source: &result.source,
+        records: result.records.iter().map(PublicRecord::from).collect(),
+        marine: &result.marine,
+        air: result.air.iter().map(PublicAir::from).collect(),
+        regions: &result.regions,
+    }
+}
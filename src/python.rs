@@ -0,0 +1,62 @@
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+use std::time::Duration;
+
+use crate::http::{self, HttpOptions};
+use crate::{fetch_aws_page, parse_html, CrawlResult};
+
+create_exception!(weather_crawl_py, CrawlError, PyException);
+
+/// The crawler's parser for Python, built with maturin:
+/// `from weather_crawl_py import parse_html, fetch_latest`.
+#[pymodule]
+fn weather_crawl_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("CrawlError", m.py().get_type::<CrawlError>())?;
+    m.add_function(wrap_pyfunction!(py_parse_html, m)?)?;
+    m.add_function(wrap_pyfunction!(fetch_latest, m)?)?;
+    Ok(())
+}
+
+/// The records of an AWS page as a dict shaped like `index.json`.
+#[pyfunction]
+#[pyo3(name = "parse_html")]
+fn py_parse_html(py: Python<'_>, html: &str) -> PyResult<PyObject> {
+    let result = py.allow_threads(|| parse_html(html)).map_err(raise)?;
+    to_dict(py, &result)
+}
+
+/// Fetch KMA's current AWS page and parse it like `parse_html`.
+#[pyfunction]
+fn fetch_latest(py: Python<'_>) -> PyResult<PyObject> {
+    let result = py.allow_threads(|| -> Result<CrawlResult, String> {
+        let client = http::build_client(&HttpOptions {
+            timeout: Some(Duration::from_secs(30)),
+            connect_timeout: Some(Duration::from_secs(10)),
+            ..HttpOptions::default()
+        })
+        .map_err(|e| e.to_string())?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let html = runtime
+            .block_on(fetch_aws_page(&client))
+            .map_err(|e| e.to_string())?;
+        parse_html(&html).map_err(|e| e.to_string())
+    });
+    to_dict(py, &result.map_err(CrawlError::new_err)?)
+}
+
+fn raise(e: crate::CrawlError) -> PyErr {
+    CrawlError::new_err(e.to_string())
+}
+
+/// Go through JSON so the dict has exactly the keys and values the crawler
+/// writes, decimals as floats.
+fn to_dict(py: Python<'_>, result: &CrawlResult) -> PyResult<PyObject> {
+    let json = serde_json::to_string(result).map_err(|e| CrawlError::new_err(e.to_string()))?;
+    let loads = py.import("json")?.getattr("loads")?;
+    Ok(loads.call1((json,))?.unbind())
+}
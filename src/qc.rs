@@ -0,0 +1,393 @@
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::derived::{sea_level_pressure, station_pressure, LAPSE_RATE};
+use crate::quantity::{Celsius, HectoPascals};
+use crate::stations::{distance_km, Catalog, Station};
+use crate::trend::History;
+use crate::Record;
+
+/// A value that disagrees with the stations around it.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SpatialFlag {
+    pub field: String,
+    pub value: Decimal,
+    /// Median of the neighbors reduced to sea level, brought back to the
+    /// height of the station.
+    pub neighbor_median: Decimal,
+    pub neighbors: usize,
+}
+
+type Field = fn(&Record) -> Option<Decimal>;
+
+/// Moves a value of a record between the height of its station, in meters,
+/// and sea level.
+type Reduce = fn(&Record, f64, f64) -> f64;
+
+/// Temperature to reduce pressure with when the station reports none.
+const STANDARD_TEMPERATURE: f64 = 15.0;
+
+pub struct SpatialQcOptions {
+    pub neighbors: usize,
+    pub temperature_delta: Decimal,
+    pub pressure_delta: Decimal,
+}
+
+/// Compare each station against the median of its nearest neighbors, with
+/// temperature and pressure reduced to sea level so that stations at
+/// different heights compare.
+///
+/// Stations missing from the catalog, or whose height is unknown, are neither
+/// checked nor used as neighbors.
+pub fn spatial_check(records: &mut [Record], catalog: &Catalog, options: &SpatialQcOptions) {
+    let checks: [(&str, Field, Reduce, Reduce, Decimal); 2] = [
+        (
+            "temperature",
+            |r| r.temperature.map(|t| t.0),
+            |_, t, h| t + LAPSE_RATE * h,
+            |_, t, h| t - LAPSE_RATE * h,
+            options.temperature_delta,
+        ),
+        (
+            "atmospheric",
+            |r| r.atmospheric.map(|p| p.0),
+            |r, p, h| {
+                r.pressure_sea_level
+                    .and_then(HectoPascals::to_f64)
+                    .unwrap_or_else(|| sea_level_pressure(p, h, temperature(r)))
+            },
+            |r, p, h| station_pressure(p, h, temperature(r)),
+            options.pressure_delta,
+        ),
+    ];
+    let located: Vec<Option<(&Station, f64)>> = records
+        .iter()
+        .map(|record| {
+            let station = catalog.get(record.id)?;
+            Some((station, height(station, record)?))
+        })
+        .collect();
+    let mut flags: Vec<(usize, SpatialFlag)> = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        let (here, h) = match located[i] {
+            Some(located) => located,
+            None => continue,
+        };
+        for (field, value_of, to_sea_level, from_sea_level, delta) in checks.iter() {
+            let reduced_of = |record: &Record, h: f64| {
+                let value = value_of(record)?.to_f64()?;
+                Decimal::from_f64(to_sea_level(record, value, h)).map(|d| d.round_dp(2))
+            };
+            let (value, reduced) = match (value_of(record), reduced_of(record, h)) {
+                (Some(value), Some(reduced)) => (value, reduced),
+                _ => continue,
+            };
+            let mut nearby: Vec<(f64, Decimal)> = records
+                .iter()
+                .zip(&located)
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .filter_map(|(_, (other, located))| {
+                    let (there, h) = (*located)?;
+                    Some((distance_km(here, there), reduced_of(other, h)?))
+                })
+                .collect();
+            nearby.sort_by(|a, b| a.0.total_cmp(&b.0));
+            nearby.truncate(options.neighbors);
+            if nearby.is_empty() {
+                continue;
+            }
+            let median = median(nearby.iter().map(|(_, v)| *v).collect());
+            if (reduced - median).abs() > *delta {
+                let neighbor_median = median
+                    .to_f64()
+                    .and_then(|median| Decimal::from_f64(from_sea_level(record, median, h)))
+                    .map_or(median, |d| d.round_dp(1));
+                flags.push((
+                    i,
+                    SpatialFlag {
+                        field: field.to_string(),
+                        value,
+                        neighbor_median,
+                        neighbors: nearby.len(),
+                    },
+                ));
+            }
+        }
+    }
+    for (i, flag) in flags {
+        records[i].spatial_flags.push(flag);
+    }
+}
+
+/// Meters above sea level of the station of `record`, from the catalog or
+/// else from the page.
+fn height(station: &Station, record: &Record) -> Option<f64> {
+    station.elevation.or_else(|| {
+        record
+            .height
+            .as_ref()
+            .filter(|h| h.unit == "m")
+            .map(|h| f64::from(h.value))
+    })
+}
+
+fn temperature(record: &Record) -> f64 {
+    record
+        .temperature
+        .and_then(Celsius::to_f64)
+        .unwrap_or(STANDARD_TEMPERATURE)
+}
+
+/// How much a value can be trusted.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Quality {
+    Ok,
+    /// The station did not report the value, which KMA marks with `-` or `.`.
+    Missing,
+    /// The cell held something that is not a number.
+    Invalid,
+    /// Out of physical bounds or jumped from the previous reading; the value
+    /// is withheld from the record.
+    Suspect,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FieldQuality {
+    pub status: Quality,
+    /// The withheld value of a suspect field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+type Withhold = fn(&mut Record);
+
+/// Fields the validation pass checks, by the name `--bound` and
+/// `--spike-limit` use, with how to read and to withhold them.
+pub const VALIDATED: [(&str, Field, Withhold); 6] = [
+    (
+        "temperature",
+        |r| r.temperature.map(|t| t.0),
+        |r| r.temperature = None,
+    ),
+    ("humidity", |r| r.humidity, |r| r.humidity = None),
+    (
+        "atmospheric",
+        |r| r.atmospheric.map(|p| p.0),
+        |r| r.atmospheric = None,
+    ),
+    (
+        "wind1",
+        |r| r.wind1.velocity.map(|v| v.0),
+        |r| r.wind1.velocity = None,
+    ),
+    (
+        "wind10",
+        |r| r.wind10.velocity.map(|v| v.0),
+        |r| r.wind10.velocity = None,
+    ),
+    (
+        "rain60",
+        |r| r.rain.rain60.map(|mm| mm.0),
+        |r| r.rain.rain60 = None,
+    ),
+];
+
+/// Readings further apart than this are not compared for spikes.
+const SPIKE_WINDOW_MINUTES: i64 = 10;
+
+pub struct ValidationOptions {
+    /// Inclusive physical bounds by field.
+    pub bounds: HashMap<&'static str, (Decimal, Decimal)>,
+    /// Largest believable change from the previous reading by field.
+    pub spike_limits: HashMap<&'static str, Decimal>,
+}
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        let d = |n: i64| Decimal::from(n);
+        ValidationOptions {
+            bounds: HashMap::from([
+                ("temperature", (d(-50), d(50))),
+                ("humidity", (d(0), d(100))),
+                ("atmospheric", (d(850), d(1100))),
+                ("wind1", (d(0), d(75))),
+                ("wind10", (d(0), d(75))),
+                ("rain60", (d(0), d(300))),
+            ]),
+            spike_limits: HashMap::from([
+                ("temperature", d(5)),
+                ("humidity", d(30)),
+                ("atmospheric", d(3)),
+                ("wind1", d(20)),
+                ("wind10", d(15)),
+            ]),
+        }
+    }
+}
+impl ValidationOptions {
+    /// Override the bounds of a field with `FIELD=MIN..MAX`.
+    pub fn set_bound(&mut self, spec: &str) -> Result<(), String> {
+        let (name, range) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("`{}` is not FIELD=MIN..MAX", spec))?;
+        let (min, max) = range
+            .split_once("..")
+            .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)))
+            .ok_or_else(|| format!("`{}` is not FIELD=MIN..MAX", spec))?;
+        self.bounds.insert(validated(name)?, (min, max));
+        Ok(())
+    }
+
+    /// Override the spike limit of a field with `FIELD=DELTA`.
+    pub fn set_spike_limit(&mut self, spec: &str) -> Result<(), String> {
+        let (name, delta) = spec
+            .split_once('=')
+            .and_then(|(name, delta)| Some((name, delta.parse().ok()?)))
+            .ok_or_else(|| format!("`{}` is not FIELD=DELTA", spec))?;
+        self.spike_limits.insert(validated(name)?, delta);
+        Ok(())
+    }
+}
+
+fn validated(name: &str) -> Result<&'static str, String> {
+    VALIDATED
+        .iter()
+        .map(|(field, _, _)| *field)
+        .find(|field| *field == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = VALIDATED.iter().map(|(field, _, _)| *field).collect();
+            format!("`{}` is not one of {}", name, names.join(", "))
+        })
+}
+
+/// Withhold values outside their bounds or too far from the station's
+/// previous reading in `history`, recording why in `quality`.
+pub fn validate(
+    records: &mut [Record],
+    minute: i64,
+    options: &ValidationOptions,
+    mut history: Option<&mut History>,
+) {
+    for record in records.iter_mut() {
+        for (field, value_of, withhold) in VALIDATED {
+            let value = match value_of(record) {
+                Some(value) => value,
+                None => continue,
+            };
+            let previous = match history.as_deref_mut() {
+                Some(history) => history.remember(record.id, field, minute, value),
+                None => None,
+            };
+            let reason = match (options.bounds.get(field), options.spike_limits.get(field)) {
+                (Some((min, max)), _) if value < *min || value > *max => {
+                    Some(format!("outside {}..{}", min, max))
+                }
+                (_, Some(limit)) => previous
+                    .filter(|(at, _)| minute - at <= SPIKE_WINDOW_MINUTES)
+                    .filter(|(_, before)| (value - before).abs() > *limit)
+                    .map(|(_, before)| format!("jumped from {}", before)),
+                _ => None,
+            };
+            if let Some(reason) = reason {
+                withhold(record);
+                record.quality.insert(
+                    field.to_string(),
+                    FieldQuality {
+                        status: Quality::Suspect,
+                        value: Some(value),
+                        reason: Some(reason),
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn median(mut values: Vec<Decimal>) -> Decimal {
+    values.sort();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / Decimal::TWO
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A record of the bundled station `id` with `temperature` and station
+    /// pressure `atmospheric`.
+    fn record(id: u32, temperature: f64, atmospheric: f64) -> Record {
+        let wind = serde_json::json!({
+            "direction_code": null,
+            "direction_text": "N",
+            "velocity": null,
+        });
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id.to_string(),
+            "height": null,
+            "rain": { "is_raining": "Clear" },
+            "temperature": temperature,
+            "wind1": wind,
+            "wind10": wind,
+            "humidity": null,
+            "atmospheric": atmospheric,
+            "address": "",
+        }))
+        .unwrap()
+    }
+
+    fn options() -> SpatialQcOptions {
+        SpatialQcOptions {
+            neighbors: 3,
+            temperature_delta: Decimal::from(3),
+            pressure_delta: Decimal::from(5),
+        }
+    }
+
+    #[test]
+    fn stations_at_different_heights_agree() {
+        // 대관령 (100) stands at 772 m, 강릉 (105) at 27 m, 속초 (90) at 18 m
+        // and 춘천 (101) at 76 m: at sea level these are about 15 °C and
+        // 1012 hPa everywhere.
+        let mut records = vec![
+            record(100, 10.0, 925.5),
+            record(105, 15.2, 1010.0),
+            record(90, 14.9, 1011.0),
+            record(101, 14.5, 1003.0),
+        ];
+        spatial_check(&mut records, &Catalog::bundled(), &options());
+        for record in &records {
+            assert!(record.spatial_flags.is_empty(), "{} flagged", record.id);
+        }
+    }
+
+    #[test]
+    fn outlier_is_flagged_at_its_own_height() {
+        let mut records = vec![
+            record(100, 2.0, 925.5),
+            record(105, 15.2, 1010.0),
+            record(90, 14.9, 1011.0),
+            record(101, 14.5, 1003.0),
+        ];
+        spatial_check(&mut records, &Catalog::bundled(), &options());
+        let flags = &records[0].spatial_flags;
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].field, "temperature");
+        assert_eq!(flags[0].value, Decimal::from(2));
+        // The neighbors' 15.02 °C at sea level is 10 °C at 772 m.
+        assert_eq!(flags[0].neighbor_median, Decimal::from(10));
+        for record in &records[1..] {
+            assert!(record.spatial_flags.is_empty(), "{} flagged", record.id);
+        }
+    }
+}
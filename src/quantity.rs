@@ -0,0 +1,73 @@
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Sub};
+
+/// A measured value tied to its unit, so values of different units cannot
+/// be added, compared or passed for one another. Serialized as the bare number.
+macro_rules! quantity {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[derive(Serialize, Deserialize, JsonSchema)]
+        #[serde(transparent)]
+        pub struct $name(pub Decimal);
+        // Not every quantity needs every conversion.
+        #[allow(dead_code)]
+        impl $name {
+            pub fn to_f64(self) -> Option<f64> {
+                self.0.to_f64()
+            }
+
+            /// `value` rounded to tenths, as KMA reports measurements.
+            pub fn from_f64(value: f64) -> Option<Self> {
+                Decimal::from_f64(value).map(|d| $name(d.round_dp(1)))
+            }
+        }
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: $name) -> $name {
+                $name(self.0 + other.0)
+            }
+        }
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                $name(self.0 - other.0)
+            }
+        }
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = $name>>(iter: I) -> $name {
+                $name(iter.map(|q| q.0).sum())
+            }
+        }
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+quantity!(
+    /// Temperature in °C.
+    Celsius
+);
+quantity!(
+    /// Precipitation in mm.
+    Millimeters
+);
+quantity!(
+    /// Pressure in hPa.
+    HectoPascals
+);
+quantity!(
+    /// Wind speed in m/s.
+    MetersPerSecond
+);
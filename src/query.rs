@@ -0,0 +1,35 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+
+use crate::archive;
+
+pub fn command() -> Command {
+    Command::new("query")
+        .about("look up retained snapshots")
+        .arg(
+            arg!(<base> "base path the crawler writes to")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(--"as-of" <TIME> "print the snapshot closest to this KST time, e.g. 2024-05-01T12:34")
+                .required(true),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let as_of = matches.get_one::<String>("as-of").unwrap();
+    if archive::minute_of(as_of).is_none() {
+        return Err(format!("`{}` is not a YYYY-MM-DDTHH:MM time", as_of).into());
+    }
+    match archive::closest(base, as_of)? {
+        Some((_, body)) => {
+            let mut out = stdout().lock();
+            out.write_all(&body)?;
+            Ok(out.flush()?)
+        }
+        None => Err(format!("no snapshots retained under {}", base.display()).into()),
+    }
+}
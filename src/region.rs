@@ -0,0 +1,207 @@
+use rust_decimal::Decimal;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+use crate::quantity::MetersPerSecond;
+use crate::stations::{great_circle_km, Station};
+use crate::{RainStatus, Record};
+
+/// Top-level administrative units (시·도) with their two-digit codes, under
+/// their full names and the short forms KMA addresses also use. Provinces
+/// renamed into special self-governing ones keep their old entry for older
+/// addresses, after the current one so the short form finds the new code.
+const PROVINCES: [(&str, &str, &str); 19] = [
+    ("서울특별시", "서울", "11"),
+    ("부산광역시", "부산", "26"),
+    ("대구광역시", "대구", "27"),
+    ("인천광역시", "인천", "28"),
+    ("광주광역시", "광주", "29"),
+    ("대전광역시", "대전", "30"),
+    ("울산광역시", "울산", "31"),
+    ("세종특별자치시", "세종", "36"),
+    ("경기도", "경기", "41"),
+    ("강원특별자치도", "강원", "51"),
+    ("강원도", "강원", "42"),
+    ("충청북도", "충북", "43"),
+    ("충청남도", "충남", "44"),
+    ("전북특별자치도", "전북", "52"),
+    ("전라북도", "전북", "45"),
+    ("전라남도", "전남", "46"),
+    ("경상북도", "경북", "47"),
+    ("경상남도", "경남", "48"),
+    ("제주특별자치도", "제주", "50"),
+];
+
+/// Seat of each province's government by province code, to place points
+/// without an address in the province whose seat is nearest.
+const SEATS: [(&str, f64, f64); 17] = [
+    ("11", 37.5665, 126.9780),
+    ("26", 35.1796, 129.0756),
+    ("27", 35.8714, 128.6014),
+    ("28", 37.4563, 126.7052),
+    ("29", 35.1595, 126.8526),
+    ("30", 36.3504, 127.3845),
+    ("31", 35.5384, 129.3114),
+    ("36", 36.4800, 127.2890),
+    ("41", 37.2636, 127.0286),
+    ("51", 37.8813, 127.7298),
+    ("43", 36.6424, 127.4890),
+    ("44", 36.6588, 126.6728),
+    ("52", 35.8242, 127.1480),
+    ("46", 34.8161, 126.4629),
+    ("47", 36.5760, 128.5056),
+    ("48", 35.2383, 128.6924),
+    ("50", 33.4996, 126.5312),
+];
+
+/// Where a station stands, parsed from its address.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Region {
+    /// 시·도, e.g. `서울특별시`.
+    pub province: String,
+    pub province_code: String,
+    /// 시·군·구, e.g. `강남구` or `수원시 장안구`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// 읍·면·동, e.g. `개포동`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub district: Option<String>,
+    /// 법정동 code of the station from the station catalog.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+impl Region {
+    /// Whether the region lies in `wanted`, a province, city or district read
+    /// the way addresses are, so that `서울` and `서울특별시` both find Seoul,
+    /// or a province code.
+    pub fn within(&self, wanted: &str) -> bool {
+        match parse(wanted, None) {
+            Some(w) => {
+                self.province == w.province
+                    && (w.city.is_none() || self.city == w.city)
+                    && (w.district.is_none() || self.district == w.district)
+            }
+            None => {
+                self.province_code == wanted
+                    || self.city.as_deref() == Some(wanted)
+                    || self.district.as_deref() == Some(wanted)
+            }
+        }
+    }
+}
+
+/// Parse `address`, or `None` when it does not start with a province.
+pub fn parse(address: &str, station: Option<&Station>) -> Option<Region> {
+    let mut tokens = address.split_whitespace().peekable();
+    let first = tokens.next()?;
+    let (province, _, code) = PROVINCES
+        .iter()
+        .find(|(full, short, _)| first == *full || first == *short)?;
+    let mut cities = Vec::new();
+    while let Some(token) = tokens.next_if(|t| ends_with_any(t, &['시', '군', '구'])) {
+        cities.push(token);
+    }
+    let district = tokens
+        .next()
+        .filter(|t| ends_with_any(t, &['읍', '면', '동', '가']));
+    Some(Region {
+        province: province.to_string(),
+        province_code: code.to_string(),
+        city: (!cities.is_empty()).then(|| cities.join(" ")),
+        district: district.map(String::from),
+        code: station.and_then(|s| s.admin_code.clone()),
+    })
+}
+
+/// The province whose seat is nearest to a point, if one is within
+/// `max_km`. Only an approximation near borders and over the sea.
+pub fn nearest_province(latitude: f64, longitude: f64, max_km: f64) -> Option<Region> {
+    let (code, distance) = SEATS
+        .iter()
+        .map(|(code, lat, lon)| (*code, great_circle_km((*lat, *lon), (latitude, longitude))))
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+    let (province, _, _) = PROVINCES.iter().find(|(_, _, c)| *c == code)?;
+    (distance <= max_km).then(|| Region {
+        province: province.to_string(),
+        province_code: code.to_string(),
+        city: None,
+        district: None,
+        code: None,
+    })
+}
+
+fn ends_with_any(token: &str, suffixes: &[char]) -> bool {
+    token.chars().last().is_some_and(|c| suffixes.contains(&c))
+}
+
+/// Observations of one province, with `--aggregate region`.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Summary {
+    pub province: String,
+    pub province_code: String,
+    /// Stations in the province with a record.
+    pub stations: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Spread>,
+    /// Stations whose rain sensor detects rain.
+    pub raining: usize,
+    /// Strongest 1-minute mean wind; the page carries no gusts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_wind: Option<MetersPerSecond>,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Spread {
+    pub mean: Decimal,
+    pub min: Decimal,
+    pub max: Decimal,
+}
+
+/// Summaries of the provinces of `records`, by province code. Records
+/// without a region are left out.
+pub fn summarize(records: &[Record]) -> Vec<Summary> {
+    let mut provinces: BTreeMap<&str, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        if let Some(region) = &record.region {
+            provinces
+                .entry(&region.province_code)
+                .or_default()
+                .push(record);
+        }
+    }
+    provinces
+        .into_values()
+        .map(|records| {
+            let region = records[0].region.as_ref().unwrap();
+            let temperatures: Vec<Decimal> = records
+                .iter()
+                .filter_map(|r| r.temperature.map(|t| t.0))
+                .collect();
+            Summary {
+                province: region.province.clone(),
+                province_code: region.province_code.clone(),
+                stations: records.len(),
+                temperature: spread(&temperatures),
+                raining: records
+                    .iter()
+                    .filter(|r| matches!(r.rain.is_raining, RainStatus::Rain))
+                    .count(),
+                max_wind: records.iter().filter_map(|r| r.wind1.velocity).max(),
+            }
+        })
+        .collect()
+}
+
+/// Mean, to a tenth, and extremes of `values`.
+pub fn spread(values: &[Decimal]) -> Option<Spread> {
+    let (min, max) = (values.iter().min()?, values.iter().max()?);
+    let mean = values.iter().sum::<Decimal>() / Decimal::from(values.len());
+    Some(Spread {
+        mean: mean.round_dp(1),
+        min: *min,
+        max: *max,
+    })
+}
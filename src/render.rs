@@ -0,0 +1,373 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use rust_decimal::prelude::*;
+
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::region::{spread, Spread};
+use crate::{aggregate, archive, atomic, export};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Temperatures of each station by id, oldest first.
+type Series = BTreeMap<u64, Vec<(i64, Decimal)>>;
+
+/// Most points of a station's temperature the page carries.
+const MAX_POINTS: i64 = 120;
+
+const SPARK_WIDTH: f64 = 120.0;
+const SPARK_HEIGHT: f64 = 24.0;
+const BAR_WIDTH: f64 = 480.0;
+const BAR_HEIGHT: f64 = 18.0;
+
+/// Sorts the table by the column clicked, numerically where it can.
+const SCRIPT: &str = r#"document.querySelectorAll("th").forEach((th, i) => th.onclick = () => {
+  const body = th.closest("table").tBodies[0], asc = th.dataset.asc !== "1";
+  const key = r => { const t = r.cells[i].textContent, n = parseFloat(t); return isNaN(n) ? t : n; };
+  [...body.rows].sort((a, b) => (key(a) > key(b) ? 1 : key(a) < key(b) ? -1 : 0) * (asc ? 1 : -1))
+    .forEach(r => body.appendChild(r));
+  th.dataset.asc = asc ? "1" : "0";
+});"#;
+
+const STYLE: &str = "body{font:14px system-ui,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse}th{cursor:pointer;text-align:left;background:#eee}\
+th,td{padding:2px 8px;border-bottom:1px solid #ddd}td.n{text-align:right}\
+polyline{fill:none;stroke:#c33;stroke-width:1.5}rect{fill:#e99}line{stroke:#c33;stroke-width:2}\
+svg text{font-size:12px}";
+
+pub fn command() -> Command {
+    Command::new("render")
+        .about("write a static HTML page of the latest crawl and its recent temperatures")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--hours <HOURS> "hours of retained snapshots to chart temperatures over, or 0 for none")
+                .value_parser(value_parser!(i64).range(0..))
+                .default_value("24"),
+        )
+        .arg(
+            arg!(--out <PATH> "where to write the page [default: <base>/index.html]")
+                .value_parser(value_parser!(PathBuf)),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let hours = *matches.get_one::<i64>("hours").unwrap();
+    let doc = archive::read_index(base)?;
+    let observed_at = doc["observed_at"].as_str().unwrap_or_default();
+    let end = archive::minute_of(observed_at)
+        .ok_or_else(|| format!("`{}` is not an observation time", observed_at))?;
+
+    let history = if hours > 0 {
+        temperatures(base, end - hours * 60 + 1, end)?
+    } else {
+        BTreeMap::new()
+    };
+    // Charted from the oldest reading, when fewer hours are retained.
+    let start = history
+        .values()
+        .filter_map(|points| points.first())
+        .map(|(minute, _)| *minute)
+        .min()
+        .unwrap_or(end);
+    let page = page(&doc, &history, start, end);
+    let out = matches
+        .get_one::<PathBuf>("out")
+        .cloned()
+        .unwrap_or_else(|| base.join("index.html"));
+    atomic::write(&out, page.as_bytes())?;
+    eprintln!(
+        "{} stations observed at {} written to {}",
+        records(&doc).count(),
+        observed_at,
+        out.display()
+    );
+    Ok(())
+}
+
+fn records(doc: &Value) -> impl Iterator<Item = &Value> {
+    doc["records"].as_array().into_iter().flatten()
+}
+
+/// Each station's temperature in `start..=end`, its last reading per step so
+/// that no station has more than [`MAX_POINTS`].
+fn temperatures(base: &Path, start: i64, end: i64) -> Result<Series, Error> {
+    let step = ((end - start + 1) / MAX_POINTS).max(1);
+    let mut series: BTreeMap<u64, BTreeMap<i64, (i64, Decimal)>> = BTreeMap::new();
+    for path in aggregate::inputs(base, start, end)? {
+        export::each_snapshot(&path, &mut |snapshot| {
+            let minute = match snapshot["observed_at"]
+                .as_str()
+                .and_then(archive::minute_of)
+            {
+                Some(minute) if (start..=end).contains(&minute) => minute,
+                _ => return Ok(()),
+            };
+            for record in records(&snapshot) {
+                let temperature = serde_json::from_value::<Decimal>(record["temperature"].clone());
+                if let (Some(id), Ok(temperature)) = (record["id"].as_u64(), temperature) {
+                    let points = series.entry(id).or_default();
+                    let point = points
+                        .entry(minute.div_euclid(step))
+                        .or_insert((minute, temperature));
+                    if point.0 <= minute {
+                        *point = (minute, temperature);
+                    }
+                }
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(series
+        .into_iter()
+        .map(|(id, points)| (id, points.into_values().collect()))
+        .collect())
+}
+
+/// The page: a chart of the provinces' temperatures, a table of the stations
+/// with their temperature over the hours asked for, and the data it was made
+/// from for scripts to read from `#data`.
+fn page(doc: &Value, history: &Series, start: i64, end: i64) -> String {
+    let observed_at = doc["observed_at"].as_str().unwrap_or_default();
+    let unit = |name: &str, metric: &str| {
+        doc["units"][name]
+            .as_str()
+            .map_or_else(|| metric.to_string(), str::to_string)
+    };
+    let (temperature, wind, rain) = (
+        unit("temperature", "°C"),
+        unit("wind", "m/s"),
+        unit("rain", "mm"),
+    );
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"ko\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>AWS {}</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>AWS {}</h1>\n<p>{} stations, from {}.</p>\n",
+        escape(observed_at),
+        STYLE,
+        escape(observed_at),
+        records(doc).count(),
+        escape(doc["source"].as_str().unwrap_or_default()),
+    );
+
+    let provinces = provinces(doc);
+    if !provinces.is_empty() {
+        let _ = writeln!(
+            html,
+            "<h2>Temperature by province ({})</h2>",
+            escape(&temperature)
+        );
+        html.push_str(&bars(&provinces));
+    }
+
+    let _ = write!(
+        html,
+        "<h2>Stations</h2>\n<table>\n<thead><tr><th>id</th><th>name</th><th>region</th>\
+         <th>temperature ({t})</th><th>humidity (%)</th><th>wind ({w})</th><th>direction</th>\
+         <th>rain 1h ({r})</th><th>rain today ({r})</th>",
+        t = escape(&temperature),
+        w = escape(&wind),
+        r = escape(&rain),
+    );
+    if !history.is_empty() {
+        let _ = write!(
+            html,
+            "<th>{} to {}</th>",
+            archive::format_minute(start),
+            archive::format_minute(end)
+        );
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for record in records(doc) {
+        let region = &record["region"];
+        let place = [&region["province"], &region["city"]]
+            .into_iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = write!(
+            html,
+            "<tr><td class=\"n\">{}</td><td>{}</td><td>{}</td>",
+            cell(&record["id"]),
+            cell(&record["name"]),
+            escape(&place),
+        );
+        for pointer in [
+            "/temperature",
+            "/humidity",
+            "/wind10/velocity",
+            "/wind10/direction_text",
+            "/rain/rain60",
+            "/rain/rainday",
+        ] {
+            let value = record.pointer(pointer).unwrap_or(&Value::Null);
+            let _ = write!(html, "<td class=\"n\">{}</td>", cell(value));
+        }
+        if !history.is_empty() {
+            let points = record["id"].as_u64().and_then(|id| history.get(&id));
+            let _ = write!(
+                html,
+                "<td>{}</td>",
+                points.map(|p| sparkline(p, start, end)).unwrap_or_default()
+            );
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    let data = json!({
+        "latest": doc,
+        "temperatures": history
+            .iter()
+            .map(|(id, points)| {
+                let points: Vec<Value> = points
+                    .iter()
+                    .map(|(minute, t)| json!([archive::format_minute(*minute), t]))
+                    .collect();
+                (id.to_string(), Value::from(points))
+            })
+            .collect::<serde_json::Map<_, _>>(),
+    });
+    // `</` only appears inside strings, where `<\/` reads the same.
+    let data = data.to_string().replace("</", "<\\/");
+    let _ = write!(
+        html,
+        "<p>{}. {}</p>\n<script type=\"application/json\" id=\"data\">{}</script>\n\
+         <script>{}</script>\n</body>\n</html>\n",
+        escape(doc["attribution"]["source"].as_str().unwrap_or_default()),
+        escape(doc["attribution"]["license"].as_str().unwrap_or_default()),
+        data,
+        SCRIPT
+    );
+    html
+}
+
+/// Spread of temperatures of each province, in the order of their codes.
+fn provinces(doc: &Value) -> Vec<(String, Spread)> {
+    let mut provinces: BTreeMap<&str, (&str, Vec<Decimal>)> = BTreeMap::new();
+    for record in records(doc) {
+        let region = &record["region"];
+        if let (Some(code), Some(name)) = (
+            region["province_code"].as_str(),
+            region["province"].as_str(),
+        ) {
+            let temperatures = &mut provinces.entry(code).or_insert((name, Vec::new())).1;
+            temperatures
+                .extend(serde_json::from_value::<Decimal>(record["temperature"].clone()).ok());
+        }
+    }
+    provinces
+        .into_values()
+        .filter_map(|(name, temperatures)| Some((name.to_string(), spread(&temperatures)?)))
+        .collect()
+}
+
+/// A bar from the lowest to the highest temperature of each province, marked
+/// at the mean.
+fn bars(provinces: &[(String, Spread)]) -> String {
+    let low = provinces
+        .iter()
+        .map(|(_, s)| s.min)
+        .min()
+        .unwrap_or_default();
+    let high = provinces
+        .iter()
+        .map(|(_, s)| s.max)
+        .max()
+        .unwrap_or_default();
+    let label = 120.0;
+    let scale = |t: Decimal| {
+        let range = (high - low).to_f64().unwrap_or_default().max(1.0);
+        label + (t - low).to_f64().unwrap_or_default() / range * (BAR_WIDTH - label - 40.0)
+    };
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg width=\"{}\" height=\"{}\" role=\"img\">",
+        BAR_WIDTH,
+        BAR_HEIGHT * provinces.len() as f64
+    );
+    for (i, (name, spread)) in provinces.iter().enumerate() {
+        let y = BAR_HEIGHT * i as f64;
+        let (min, max, mean) = (scale(spread.min), scale(spread.max), scale(spread.mean));
+        let _ = writeln!(
+            svg,
+            "<text x=\"0\" y=\"{:.1}\">{}</text>\
+             <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"><title>{} – {}, mean {}</title></rect>\
+             <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            y + 13.0,
+            escape(name),
+            min,
+            y + 3.0,
+            (max - min).max(1.0),
+            BAR_HEIGHT - 6.0,
+            spread.min,
+            spread.max,
+            spread.mean,
+            mean,
+            y + 2.0,
+            mean,
+            y + BAR_HEIGHT - 2.0,
+            max + 4.0,
+            y + 13.0,
+            spread.mean,
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A line of `points` over `start..=end`, spanning their lowest to highest.
+fn sparkline(points: &[(i64, Decimal)], start: i64, end: i64) -> String {
+    let low = points.iter().map(|(_, t)| *t).min().unwrap_or_default();
+    let high = points.iter().map(|(_, t)| *t).max().unwrap_or_default();
+    let range = (high - low).to_f64().unwrap_or_default().max(0.1);
+    let span = (end - start).max(1) as f64;
+    let mut line = String::new();
+    for (minute, t) in points {
+        let x = (minute - start) as f64 / span * SPARK_WIDTH;
+        // A temperature that never changed is drawn across the middle.
+        let y = if high == low {
+            SPARK_HEIGHT / 2.0
+        } else {
+            SPARK_HEIGHT
+                - 1.0
+                - (t - low).to_f64().unwrap_or_default() / range * (SPARK_HEIGHT - 2.0)
+        };
+        let _ = write!(line, "{:.1},{:.1} ", x, y);
+    }
+    format!(
+        "<svg width=\"{}\" height=\"{}\"><title>{} – {}</title><polyline points=\"{}\"/></svg>",
+        SPARK_WIDTH,
+        SPARK_HEIGHT,
+        low,
+        high,
+        line.trim_end()
+    )
+}
+
+/// `value` as the text of a cell.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => escape(s),
+        other => escape(&other.to_string()),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
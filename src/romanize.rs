@@ -0,0 +1,90 @@
+const INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+const MEDIALS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+/// Finals as pronounced before a pause or another consonant.
+const FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+/// Finals carried over to a following syllable that starts with a vowel.
+const LINKED: [&str; 28] = [
+    "", "g", "kk", "ks", "n", "nj", "n", "d", "r", "lg", "lm", "lb", "ls", "lt", "lp", "r", "m",
+    "b", "ps", "s", "ss", "ng", "j", "ch", "k", "t", "p", "",
+];
+
+const SYLLABLES: std::ops::RangeInclusive<u32> = 0xAC00..=0xD7A3;
+const SILENT: usize = 11;
+
+/// Romanize the hangul syllables in `name`, capitalizing each word and
+/// leaving everything else as is.
+///
+/// Liaison and the common nasal and lateral assimilations are applied;
+/// rarer sound changes are not, so results can differ from official names.
+pub fn romanize(name: &str) -> String {
+    let jamo: Vec<Option<(usize, usize, usize)>> = name.chars().map(decompose).collect();
+    let mut out = String::new();
+    let mut capitalize = true;
+    for (i, c) in name.chars().enumerate() {
+        let (initial, medial, last) = match jamo[i] {
+            Some(j) => j,
+            None => {
+                out.push(c);
+                capitalize = c.is_whitespace() || c == '(' || c == '-';
+                continue;
+            }
+        };
+        let before = if i > 0 { jamo[i - 1] } else { None };
+        let onset = match before.map(|(_, _, f)| FINALS[f]) {
+            // ㄹ after ㄴ or ㄹ is a lateral, ㄴ after ㄹ too.
+            Some("n" | "l") if initial == 5 => "l",
+            Some("l") if initial == 2 => "l",
+            // ㄹ after any other consonant is a nasal.
+            Some("k" | "t" | "p" | "m" | "ng") if initial == 5 => "n",
+            _ => INITIALS[initial],
+        };
+        let coda = match jamo.get(i + 1).copied().flatten() {
+            Some((SILENT, _, _)) => LINKED[last],
+            Some((next, _, _)) => assimilate(last, next),
+            None => FINALS[last],
+        };
+        let syllable = format!("{}{}{}", onset, MEDIALS[medial], coda);
+        if capitalize {
+            let mut chars = syllable.chars();
+            if let Some(first) = chars.next() {
+                out.extend(first.to_uppercase());
+                out.push_str(chars.as_str());
+            }
+            capitalize = false;
+        } else {
+            out.push_str(&syllable);
+        }
+    }
+    out
+}
+
+/// Split a hangul syllable into indices of its initial, medial and final.
+fn decompose(c: char) -> Option<(usize, usize, usize)> {
+    let code = c as u32;
+    if !SYLLABLES.contains(&code) {
+        return None;
+    }
+    let index = (code - SYLLABLES.start()) as usize;
+    Some((index / (21 * 28), index / 28 % 21, index % 28))
+}
+
+/// Final `last` as pronounced before the initial `next`.
+fn assimilate(last: usize, next: usize) -> &'static str {
+    let nasal = next == 2 || next == 6;
+    match FINALS[last] {
+        "k" if nasal || next == 5 => "ng",
+        "t" if nasal => "n",
+        "p" if nasal || next == 5 => "m",
+        "n" if next == 5 => "l",
+        sound => sound,
+    }
+}
@@ -0,0 +1,232 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use schemars::schema_for;
+
+use serde_json::{Map, Value};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{stdout, BufReader, Write};
+use std::path::PathBuf;
+
+use crate::publish::{Profile, PublicResult};
+use crate::CrawlResult;
+
+/// What schemars emits for `Decimal`; the crawler serializes those as numbers.
+const DECIMAL_PATTERN: &str = r"^-?[0-9]+(\.[0-9]+)?$";
+
+pub fn command() -> Command {
+    let profile = || {
+        arg!(--profile <PROFILE> "output profile the schema describes")
+            .value_parser(["full", "publish"])
+            .default_value("full")
+    };
+    Command::new("schema")
+        .about("print the JSON schema of the written documents")
+        .args_conflicts_with_subcommands(true)
+        .arg(profile())
+        .subcommand(
+            Command::new("diff")
+                .about("compare the current schema with one exported by an earlier version")
+                .arg(
+                    arg!(--against <PATH> "previously exported schema")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(true),
+                )
+                .arg(profile()),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(("diff", sub)) = matches.subcommand() {
+        return diff(sub);
+    }
+    let mut out = stdout().lock();
+    serde_json::to_writer_pretty(&mut out, &current(profile_of(matches)))?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn profile_of(matches: &ArgMatches) -> Profile {
+    Profile::from_name(matches.get_one::<String>("profile").unwrap()).unwrap()
+}
+
+/// Schema of the document `--profile` makes the crawler write.
+pub fn current(profile: Profile) -> Value {
+    let schema = match profile {
+        Profile::Full => schema_for!(CrawlResult),
+        Profile::Publish => schema_for!(PublicResult),
+    };
+    let mut value = serde_json::to_value(schema).unwrap();
+    decimals_as_numbers(&mut value);
+    value
+}
+
+fn decimals_as_numbers(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if map.get("pattern").and_then(Value::as_str) == Some(DECIMAL_PATTERN) {
+                map.remove("pattern");
+                match map.get_mut("type") {
+                    Some(Value::Array(types)) => {
+                        for t in types.iter_mut().filter(|t| *t == "string") {
+                            *t = "number".into();
+                        }
+                    }
+                    Some(t) => *t = "number".into(),
+                    None => {}
+                }
+            }
+            map.values_mut().for_each(decimals_as_numbers);
+        }
+        Value::Array(items) => items.iter_mut().for_each(decimals_as_numbers),
+        _ => {}
+    }
+}
+
+fn diff(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let path = matches.get_one::<PathBuf>("against").unwrap();
+    let old: Value = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    let old = fields(&old);
+    let new = fields(&current(profile_of(matches)));
+
+    let mut removed: Vec<(&String, &String)> = old
+        .iter()
+        .filter(|(path, _)| !new.contains_key(*path))
+        .collect();
+    let mut added: Vec<(&String, &String)> = new
+        .iter()
+        .filter(|(path, _)| !old.contains_key(*path))
+        .collect();
+    // A field that vanished next to a new one of the same shape was most
+    // likely renamed; nested fields move along with their parent.
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    let mut i = 0;
+    while i < removed.len() {
+        let (from, shape) = removed[i];
+        let partner = added
+            .iter()
+            .position(|(to, s)| *s == shape && parent(to) == parent(from));
+        match partner {
+            Some(j) => {
+                let (to, _) = added.remove(j);
+                let (from_prefix, to_prefix) = (format!("{}.", from), format!("{}.", to));
+                removed.retain(|(p, _)| !p.starts_with(&from_prefix));
+                added.retain(|(p, _)| !p.starts_with(&to_prefix));
+                renamed.push((from.clone(), to.clone()));
+                removed.remove(i);
+            }
+            None => i += 1,
+        }
+    }
+    let changed: Vec<(&String, &String, &String)> = new
+        .iter()
+        .filter_map(|(path, shape)| match old.get(path) {
+            Some(before) if before != shape => Some((path, before, shape)),
+            _ => None,
+        })
+        .collect();
+
+    for (path, shape) in added.iter() {
+        println!("added    {}: {}", path, shape);
+    }
+    for (path, shape) in removed.iter() {
+        println!("removed  {}: {}", path, shape);
+    }
+    for (from, to) in renamed.iter() {
+        println!("renamed  {} -> {}", from, to);
+    }
+    for (path, before, after) in changed.iter() {
+        println!("changed  {}: {} -> {}", path, before, after);
+    }
+    if added.is_empty() && removed.is_empty() && renamed.is_empty() && changed.is_empty() {
+        println!("no changes");
+    }
+    Ok(())
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('.').map_or("", |(p, _)| p)
+}
+
+/// Every field path of a schema, e.g. `records[].rain.rain15`, with a short
+/// description of its type such as `number | null` or `string, optional`.
+fn fields(schema: &Value) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    walk(schema, schema, "", &mut out);
+    out
+}
+
+fn walk(root: &Value, node: &Value, path: &str, out: &mut BTreeMap<String, String>) {
+    for node in branches(root, node) {
+        if let Some(properties) = node.get("properties").and_then(Value::as_object) {
+            let required: BTreeSet<&str> = node
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|r| r.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            for (name, child) in properties {
+                let child_path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                let mut shape = describe(root, child);
+                if !required.contains(name.as_str()) {
+                    shape.push_str(", optional");
+                }
+                out.insert(child_path.clone(), shape);
+                walk(root, child, &child_path, out);
+            }
+        }
+        if let Some(items) = node.get("items") {
+            walk(root, items, &format!("{}[]", path), out);
+        }
+    }
+}
+
+/// The schemas a value may match, with `$ref`s resolved and unions flattened.
+fn branches<'a>(root: &'a Value, node: &'a Value) -> Vec<&'a Value> {
+    let node = resolve(root, node);
+    let union = ["anyOf", "oneOf", "allOf"]
+        .iter()
+        .find_map(|k| node.get(*k).and_then(Value::as_array));
+    match union {
+        Some(options) => options.iter().flat_map(|o| branches(root, o)).collect(),
+        None => vec![node],
+    }
+}
+
+fn resolve<'a>(root: &'a Value, node: &'a Value) -> &'a Value {
+    match node.get("$ref").and_then(Value::as_str) {
+        Some(reference) => reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .map_or(node, |target| resolve(root, target)),
+        None => node,
+    }
+}
+
+fn describe(root: &Value, node: &Value) -> String {
+    let mut types: BTreeSet<String> = BTreeSet::new();
+    for branch in branches(root, node) {
+        if let Some(values) = branch.get("enum").and_then(Value::as_array) {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            types.insert(format!("one of {}", values.join("/")));
+            continue;
+        }
+        match branch.get("type") {
+            Some(Value::String(t)) => {
+                types.insert(t.clone());
+            }
+            Some(Value::Array(ts)) => {
+                types.extend(ts.iter().filter_map(Value::as_str).map(String::from))
+            }
+            _ if branch.as_object().is_some_and(Map::is_empty) => {
+                types.insert("any".into());
+            }
+            _ => {}
+        }
+    }
+    types.into_iter().collect::<Vec<_>>().join(" | ")
+}
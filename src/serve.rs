@@ -0,0 +1,510 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use futures_util::stream::{self, Stream};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use tokio::sync::broadcast;
+
+use tracing::{debug, info};
+
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::fs::{metadata, read};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::attribution::Attribution;
+use crate::region::Region;
+use crate::{archive, history, logging};
+
+/// How often `index.json` is looked at for a new observation to push.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Observations kept for a stream that is slow to take them.
+const EVENT_BACKLOG: usize = 16;
+
+pub fn command() -> Command {
+    let command = Command::new("serve")
+        .about("serve the latest crawl and the retained snapshots as an HTTP API")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--listen <ADDR> "address to listen on")
+                .value_parser(value_parser!(SocketAddr))
+                .default_value("127.0.0.1:8080"),
+        )
+        .arg(
+            arg!(--"log-level" <LEVEL> "most verbose level to log")
+                .value_parser(logging::LEVELS)
+                .default_value("info"),
+        )
+        .arg(
+            arg!(--"log-format" <FORMAT> "log line format")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        );
+    #[cfg(feature = "sqlite")]
+    let command = command.arg(
+        arg!(--"history-db" <PATH> "answer /history from this database of --sink sqlite instead of the snapshots")
+            .value_parser(value_parser!(PathBuf)),
+    );
+    #[cfg(feature = "grpc")]
+    let command = command.arg(
+        arg!(--grpc <ADDR> "also serve proto/weather_crawl.proto over gRPC on this address")
+            .value_parser(value_parser!(SocketAddr)),
+    );
+    command
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let base = matches.get_one::<PathBuf>("base").unwrap().clone();
+    let listen = matches.get_one::<SocketAddr>("listen").unwrap();
+    let crawl = Arc::new(Crawl {
+        base,
+        #[cfg(feature = "sqlite")]
+        history_db: matches.get_one::<PathBuf>("history-db").cloned(),
+        latest: Mutex::new(None),
+        events: broadcast::channel(EVENT_BACKLOG).0,
+    });
+    tokio::spawn(watch(crawl.clone()));
+    let app = Router::new()
+        .route("/latest", get(latest))
+        .route("/about", get(about))
+        .route("/as-of/:time", get(as_of))
+        .route("/stations", get(stations))
+        .route("/stations/:id", get(station))
+        .route("/stations/:id/history", get(history::station))
+        .route("/events", get(events))
+        .route("/ws", get(live));
+    #[cfg(feature = "graphql")]
+    let app = app.merge(crate::graphql::router(crawl.clone()));
+    let app = app.with_state(crawl.clone());
+    info!(%listen, "serving");
+    let http = axum::Server::bind(listen).serve(app.into_make_service());
+    #[cfg(feature = "grpc")]
+    if let Some(&grpc) = matches.get_one::<SocketAddr>("grpc") {
+        info!(listen = %grpc, "serving gRPC");
+        let (http, grpc) = tokio::join!(http, crate::grpc::serve(crawl, grpc));
+        http?;
+        grpc?;
+        return Ok(());
+    }
+    http.await?;
+    Ok(())
+}
+
+/// The base path served, with its `index.json` as last read.
+pub(crate) struct Crawl {
+    pub(crate) base: PathBuf,
+    /// `--history-db`, read for `/history` instead of the snapshots.
+    #[cfg(feature = "sqlite")]
+    pub(crate) history_db: Option<PathBuf>,
+    latest: Mutex<Option<Latest>>,
+    /// Each new observation, as its whole document.
+    pub(crate) events: broadcast::Sender<Arc<Value>>,
+}
+
+struct Latest {
+    modified: SystemTime,
+    body: Arc<Vec<u8>>,
+    doc: Arc<Value>,
+}
+
+impl Crawl {
+    /// `index.json` and its parsed document, read again once the crawler
+    /// has replaced it. The file is read and parsed off the lock, so that
+    /// requests answered from what was read before are not held up.
+    pub(crate) async fn latest(&self) -> Result<(Arc<Vec<u8>>, Arc<Value>), ApiError> {
+        let path = self.base.join(archive::INDEX_FILE);
+        let modified = metadata(&path)
+            .and_then(|m| m.modified())
+            .map_err(|_| ApiError::NotFound("nothing crawled yet".into()))?;
+        {
+            let latest = self.latest.lock().unwrap();
+            if let Some(l) = latest.as_ref().filter(|l| l.modified == modified) {
+                return Ok((l.body.clone(), l.doc.clone()));
+            }
+        }
+        let (body, doc) = tokio::task::spawn_blocking(move || -> Result<_, String> {
+            let body = read(&path).map_err(|e| e.to_string())?;
+            let doc: Value = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+            Ok((Arc::new(body), Arc::new(doc)))
+        })
+        .await
+        .map_err(ApiError::internal)?
+        .map_err(ApiError::internal)?;
+        let mut latest = self.latest.lock().unwrap();
+        // A request that read a later `index.json` meanwhile keeps it.
+        if latest.as_ref().is_none_or(|l| l.modified < modified) {
+            *latest = Some(Latest {
+                modified,
+                body: body.clone(),
+                doc: doc.clone(),
+            });
+        }
+        Ok((body, doc))
+    }
+
+    /// The retained snapshot closest to `at`, looked up off the runtime
+    /// since bundles may need decompressing.
+    async fn snapshot(&self, at: &str) -> Result<Value, ApiError> {
+        if archive::minute_of(at).is_none() {
+            return Err(ApiError::BadRequest(format!(
+                "`{}` is not a YYYY-MM-DDTHH:MM time",
+                at
+            )));
+        }
+        let (base, at) = (self.base.clone(), at.to_string());
+        tokio::task::spawn_blocking(move || -> Result<_, String> {
+            match archive::closest(&base, &at).map_err(|e| e.to_string())? {
+                Some((_, body)) => Ok(Some(
+                    serde_json::from_slice(&body).map_err(|e| e.to_string())?,
+                )),
+                None => Ok(None),
+            }
+        })
+        .await
+        .map_err(ApiError::internal)?
+        .map_err(ApiError::internal)?
+        .ok_or_else(|| ApiError::NotFound("no snapshots retained".into()))
+    }
+
+    /// The document at `at`, or the latest one.
+    pub(crate) async fn document(&self, at: Option<&str>) -> Result<Arc<Value>, ApiError> {
+        match at {
+            Some(at) => self.snapshot(at).await.map(Arc::new),
+            None => self.latest().await.map(|(_, doc)| doc),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Params {
+    /// KST time to answer from the archive for, e.g. `2024-05-01T12:34`.
+    at: Option<String>,
+    /// Province, city or district, e.g. `서울` or `경기 수원시`, or a
+    /// province code.
+    region: Option<String>,
+}
+
+async fn latest(
+    State(crawl): State<Arc<Crawl>>,
+    Query(params): Query<Params>,
+) -> Result<Response, ApiError> {
+    match params.at {
+        Some(at) => Ok(Json(crawl.snapshot(&at).await?).into_response()),
+        None => {
+            let (body, _) = crawl.latest().await?;
+            Ok((
+                [(header::CONTENT_TYPE, "application/json")],
+                body.as_ref().clone(),
+            )
+                .into_response())
+        }
+    }
+}
+
+/// Who the data of the latest crawl comes from and under which terms, as
+/// the publish profile carries it.
+async fn about(State(crawl): State<Arc<Crawl>>) -> Result<Json<Value>, ApiError> {
+    let (_, doc) = crawl.latest().await?;
+    if doc["attribution"].is_object() {
+        return Ok(Json(doc["attribution"].clone()));
+    }
+    let url = doc["source"].as_str().unwrap_or_default();
+    serde_json::to_value(Attribution::default_for(url))
+        .map(Json)
+        .map_err(ApiError::internal)
+}
+
+/// The retained snapshot closest to a KST time, as `/latest?at=` answers.
+async fn as_of(
+    State(crawl): State<Arc<Crawl>>,
+    UrlPath(time): UrlPath<String>,
+) -> Result<Json<Value>, ApiError> {
+    crawl.snapshot(&time).await.map(Json)
+}
+
+/// Push every new observation the crawler writes to `index.json`.
+async fn watch(crawl: Arc<Crawl>) {
+    let observed_at = |doc: &Value| doc["observed_at"].as_str().map(str::to_string);
+    // What was there when the server started is not news.
+    let mut seen = crawl
+        .latest()
+        .await
+        .ok()
+        .and_then(|(_, doc)| observed_at(&doc));
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let doc = match crawl.latest().await {
+            Ok((_, doc)) => doc,
+            Err(ApiError::NotFound(_)) => continue,
+            Err(e) => {
+                debug!(error = %e, "reading the latest crawl failed");
+                continue;
+            }
+        };
+        let observed_at = observed_at(&doc);
+        if observed_at != seen {
+            seen = observed_at;
+            // Nobody listening is not an error.
+            let _ = crawl.events.send(doc);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EventParams {
+    /// Only observations with this station.
+    station: Option<u32>,
+    /// Only observations with stations in this region, as for `/stations`.
+    region: Option<String>,
+}
+
+/// Server-Sent Events of each new observation, with only the records the
+/// filters want; an observation without any is not sent.
+async fn events(
+    State(crawl): State<Arc<Crawl>>,
+    Query(params): Query<EventParams>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = crawl.events.subscribe();
+    let stream = stream::unfold((receiver, params), |(mut receiver, params)| async move {
+        loop {
+            let doc = match receiver.recv().await {
+                Ok(doc) => doc,
+                // A stream that fell behind skips what it missed.
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            let filtered = params.station.is_some() || params.region.is_some();
+            let records: Vec<&Value> = records(&doc)
+                .filter(|record| {
+                    params
+                        .station
+                        .is_none_or(|id| record["id"].as_u64() == Some(u64::from(id)))
+                })
+                .filter(|record| {
+                    params
+                        .region
+                        .as_deref()
+                        .is_none_or(|wanted| in_region(record, wanted))
+                })
+                .collect();
+            if filtered && records.is_empty() {
+                continue;
+            }
+            let mut event = Event::default().event("observation");
+            if let Some(observed_at) = doc["observed_at"].as_str() {
+                event = event.id(observed_at);
+            }
+            let event = event
+                .json_data(json!({
+                    "observed_at": doc["observed_at"],
+                    "records": records,
+                }))
+                .unwrap();
+            return Some((Ok(event), (receiver, params)));
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+struct LiveParams {
+    /// Station ids to subscribe to at once, comma-separated.
+    stations: Option<String>,
+}
+
+/// What a WebSocket client sends to change its stations.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Request {
+    Subscribe(Vec<u32>),
+    Unsubscribe(Vec<u32>),
+}
+
+/// A WebSocket pushing the records of the subscribed stations as they are
+/// crawled, after a snapshot of the latest ones.
+async fn live(
+    State(crawl): State<Arc<Crawl>>,
+    Query(params): Query<LiveParams>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let stations = match params.stations.as_deref().filter(|s| !s.is_empty()) {
+        Some(list) => list
+            .split(',')
+            .map(|id| id.trim().parse::<u32>())
+            .collect::<Result<BTreeSet<_>, _>>()
+            .map_err(|_| {
+                ApiError::BadRequest(format!("`{}` is not a list of station ids", list))
+            })?,
+        None => BTreeSet::new(),
+    };
+    Ok(upgrade.on_upgrade(move |socket| session(socket, crawl, stations)))
+}
+
+/// Serve one WebSocket client until it goes away. No stations subscribed
+/// means every station.
+async fn session(mut socket: WebSocket, crawl: Arc<Crawl>, mut stations: BTreeSet<u32>) {
+    let mut events = crawl.events.subscribe();
+    if let Ok((_, doc)) = crawl.latest().await {
+        if !send(&mut socket, push("snapshot", &doc, &stations)).await {
+            return;
+        }
+    }
+    loop {
+        let message = tokio::select! {
+            event = events.recv() => match event {
+                Ok(doc) => push("observation", &doc, &stations),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            received = socket.recv() => match received {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    Ok(Request::Subscribe(ids)) => {
+                        let added: BTreeSet<u32> = ids.into_iter().collect();
+                        stations.extend(&added);
+                        crawl
+                            .latest()
+                            .await
+                            .ok()
+                            .and_then(|(_, doc)| push("snapshot", &doc, &added))
+                    }
+                    Ok(Request::Unsubscribe(ids)) => {
+                        for id in ids {
+                            stations.remove(&id);
+                        }
+                        None
+                    }
+                    Err(e) => Some(json!({ "type": "error", "error": e.to_string() })),
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+        if !send(&mut socket, message).await {
+            return;
+        }
+    }
+}
+
+/// The records of `doc` of the `stations`, or of all of them when none;
+/// `None` when there are none to push.
+fn push(kind: &str, doc: &Value, stations: &BTreeSet<u32>) -> Option<Value> {
+    let records: Vec<&Value> = records(doc)
+        .filter(|record| {
+            stations.is_empty()
+                || record["id"]
+                    .as_u64()
+                    .and_then(|id| u32::try_from(id).ok())
+                    .is_some_and(|id| stations.contains(&id))
+        })
+        .collect();
+    if records.is_empty() {
+        return None;
+    }
+    Some(json!({
+        "type": kind,
+        "observed_at": doc["observed_at"],
+        "records": records,
+    }))
+}
+
+/// Send `message` if there is one, returning whether the client is still
+/// there.
+async fn send(socket: &mut WebSocket, message: Option<Value>) -> bool {
+    match message {
+        Some(message) => socket
+            .send(Message::Text(message.to_string()))
+            .await
+            .is_ok(),
+        None => true,
+    }
+}
+
+async fn stations(
+    State(crawl): State<Arc<Crawl>>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>, ApiError> {
+    let doc = crawl.document(params.at.as_deref()).await?;
+    let wanted = params.region.as_deref();
+    let records: Vec<&Value> = records(&doc)
+        .filter(|record| wanted.is_none_or(|wanted| in_region(record, wanted)))
+        .collect();
+    Ok(Json(json!({
+        "observed_at": doc["observed_at"],
+        "records": records,
+    })))
+}
+
+async fn station(
+    State(crawl): State<Arc<Crawl>>,
+    UrlPath(id): UrlPath<u32>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>, ApiError> {
+    let doc = crawl.document(params.at.as_deref()).await?;
+    let record = records(&doc)
+        .find(|record| record["id"].as_u64() == Some(u64::from(id)))
+        .ok_or_else(|| ApiError::NotFound(format!("no station {}", id)))?;
+    Ok(Json(json!({
+        "observed_at": doc["observed_at"],
+        "record": record,
+    })))
+}
+
+pub(crate) fn records(doc: &Value) -> impl Iterator<Item = &Value> {
+    doc["records"].as_array().into_iter().flatten()
+}
+
+/// Whether the record lies in `wanted`, as [`Region::within`] reads it.
+pub(crate) fn in_region(record: &Value, wanted: &str) -> bool {
+    serde_json::from_value::<Region>(record["region"].clone())
+        .is_ok_and(|region| region.within(wanted))
+}
+
+pub(crate) enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(String),
+}
+impl ApiError {
+    pub(crate) fn internal(e: impl std::fmt::Display) -> Self {
+        ApiError::Internal(e.to_string())
+    }
+}
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::BadRequest(m) | ApiError::NotFound(m) | ApiError::Internal(m) => {
+                f.write_str(m)
+            }
+        }
+    }
+}
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(m) => (StatusCode::BAD_REQUEST, m),
+            ApiError::NotFound(m) => (StatusCode::NOT_FOUND, m),
+            ApiError::Internal(m) => (StatusCode::INTERNAL_SERVER_ERROR, m),
+        };
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
@@ -0,0 +1,459 @@
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use tokio::sync::Mutex;
+
+use tracing::{debug, error, warn};
+
+use std::collections::BTreeSet;
+use std::fs::create_dir_all;
+use std::future::Future;
+use std::io::{stdout, Write};
+#[cfg(feature = "sqlite")]
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[cfg(feature = "email")]
+use crate::email::{Fields, Mailer};
+use crate::error::CrawlError;
+use crate::notify::{self, Chat};
+use crate::pipeline::{write_output, Settings};
+use crate::publish::{self, Profile};
+use crate::stats::CrawlStats;
+use crate::{atomic, CrawlResult, Record};
+
+/// The write of a result to a sink, done once awaited.
+pub type Written<'a> = Pin<Box<dyn Future<Output = Result<(), CrawlError>> + Send + 'a>>;
+
+/// Somewhere a parsed and enriched crawl result is written to.
+///
+/// ```
+/// use weather_crawl::{CrawlResult, Sink, Written};
+///
+/// struct Count(usize);
+/// impl Sink for Count {
+///     fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a> {
+///         Box::pin(async move {
+///             self.0 += result.records.len();
+///             Ok(())
+///         })
+///     }
+/// }
+/// ```
+pub trait Sink {
+    fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a>;
+}
+
+/// A sink named with `--sink`, with what it needs to be opened.
+#[derive(Clone)]
+pub enum Target {
+    /// index.json, snapshots, patches and deltas under `<base>`.
+    File,
+    /// The document as one line of JSON on stdout.
+    Stdout,
+    /// The document POSTed as JSON.
+    Http {
+        url: String,
+        client: Client,
+        filter: Filter,
+    },
+    /// Alerts, and with `summary` a line on each crawl, posted to a chat's
+    /// incoming webhook.
+    Chat {
+        chat: Chat,
+        url: String,
+        client: Client,
+        summary: bool,
+        filter: Filter,
+    },
+    /// A mail for each alert.
+    #[cfg(feature = "email")]
+    Email { mailer: Arc<Mailer>, filter: Filter },
+    /// The records appended to the `observations` table of `export`.
+    #[cfg(feature = "sqlite")]
+    Sqlite(PathBuf),
+    /// A sink of a library user.
+    Custom(Arc<Mutex<dyn Sink + Send>>),
+}
+impl Target {
+    /// Whether the sink pushes results to another service, whose failure
+    /// does not fail a crawl already written locally.
+    pub fn pushes(&self) -> bool {
+        matches!(self, Target::Http { .. } | Target::Chat { .. })
+    }
+}
+
+/// Names `--sink` accepts; sqlite, telegram and email need the features of the
+/// same name.
+pub fn kinds() -> Vec<&'static str> {
+    #[cfg_attr(
+        not(any(feature = "sqlite", feature = "telegram", feature = "email")),
+        allow(unused_mut)
+    )]
+    let mut kinds = vec!["file", "stdout", "http", "slack", "discord"];
+    #[cfg(feature = "sqlite")]
+    kinds.push("sqlite");
+    #[cfg(feature = "telegram")]
+    kinds.push("telegram");
+    #[cfg(feature = "email")]
+    kinds.push("email");
+    kinds
+}
+
+/// Fields of a record that say which station it is, kept whatever metrics a
+/// filter wants.
+const IDENTITY: [&str; 6] = ["id", "name", "name_en", "station", "address", "region"];
+
+/// The stations and metrics a push sink wants, from `--sink-stations` and
+/// `--sink-metrics`; either left empty is all of them.
+#[derive(Clone, Default)]
+pub struct Filter {
+    pub stations: BTreeSet<u32>,
+    /// Fields of a record, such as `temperature` or `rain`.
+    pub metrics: BTreeSet<String>,
+}
+impl Filter {
+    pub fn wants_station(&self, id: u32) -> bool {
+        self.stations.is_empty() || self.stations.contains(&id)
+    }
+
+    pub fn wants_metric(&self, field: &str) -> bool {
+        self.metrics.is_empty() || self.metrics.contains(field)
+    }
+
+    /// Leave only the wanted stations and metrics in the `records` of `doc`,
+    /// a document of observations. False when no station is left.
+    fn apply(&self, doc: &mut Value) -> bool {
+        let Some(records) = doc["records"].as_array_mut() else {
+            return true;
+        };
+        records.retain(|record| {
+            record["id"]
+                .as_u64()
+                .and_then(|id| u32::try_from(id).ok())
+                .is_some_and(|id| self.wants_station(id))
+        });
+        if !self.metrics.is_empty() {
+            for record in records.iter_mut().filter_map(Value::as_object_mut) {
+                record.retain(|field, _| {
+                    IDENTITY.contains(&field.as_str()) || self.wants_metric(field)
+                });
+            }
+        }
+        !records.is_empty()
+    }
+}
+
+/// The names `--sink-metrics` takes: the fields of a record but those naming
+/// the station.
+pub fn metrics() -> Vec<String> {
+    let schema = schemars::schema_for!(Record);
+    schema
+        .schema
+        .object
+        .map(|object| object.properties.into_keys().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|field| !IDENTITY.contains(&field.as_str()))
+        .collect()
+}
+
+/// Write `result` to every sink of `settings` in turn.
+///
+/// A failing sink does not keep the others from being written; the first
+/// failure of a local sink is returned once all were tried. Those of sinks
+/// that push are logged and counted in `stats` only.
+pub async fn write_all(
+    settings: &Settings,
+    result: &CrawlResult,
+    stats: &mut CrawlStats,
+) -> Result<(), CrawlError> {
+    let mut failure = None;
+    for target in &settings.sinks {
+        let mut sink: Box<dyn Sink + Send + '_> = match target {
+            Target::File => Box::new(FileSink { settings }),
+            Target::Stdout => Box::new(StdoutSink { settings }),
+            Target::Http {
+                url,
+                client,
+                filter,
+            } => Box::new(HttpSink {
+                settings,
+                url,
+                client,
+                filter,
+            }),
+            Target::Chat { summary: false, .. } => continue,
+            Target::Chat {
+                chat,
+                url,
+                client,
+                filter,
+                ..
+            } => Box::new(ChatSink {
+                settings,
+                chat,
+                url,
+                client,
+                filter,
+            }),
+            // Mails are for alerts only.
+            #[cfg(feature = "email")]
+            Target::Email { .. } => continue,
+            #[cfg(feature = "sqlite")]
+            Target::Sqlite(path) => Box::new(SqliteSink { settings, path }),
+            Target::Custom(sink) => Box::new(Shared(sink)),
+        };
+        let written = sink.write(result).await;
+        if let Err(e) = written {
+            if target.pushes() {
+                warn!(error = %e, "push sink failed, the crawl goes on");
+                stats.push_failures += 1;
+                continue;
+            }
+            if settings.sinks.len() > 1 {
+                error!(error = %e, "sink failed");
+            }
+            failure.get_or_insert(e);
+        }
+    }
+    failure.map_or(Ok(()), Err)
+}
+
+/// A document other than observations the sinks take, such as a forecast.
+pub trait Document: Serialize {
+    /// Name of the file the file sink writes under `<base>`.
+    const FILE: &'static str;
+
+    /// Add the document to its table of the database at `path`.
+    #[cfg(feature = "sqlite")]
+    fn to_sqlite(&self, path: &Path) -> rusqlite::Result<()>;
+
+    /// The document as JSON with only what `filter` wants, or `None` when
+    /// that is nothing. Documents not about stations are sent whole.
+    fn filtered(&self, _filter: &Filter) -> serde_json::Result<Option<Value>> {
+        serde_json::to_value(self).map(Some)
+    }
+
+    /// The document as a chat message, for those worth one, with only what
+    /// `filter` wants.
+    fn message(&self, _chat: &Chat, _filter: &Filter) -> Option<String> {
+        None
+    }
+
+    /// The fields of each mail the document is worth, for the templates of
+    /// `--sink email`, for what `filter` wants.
+    #[cfg(feature = "email")]
+    fn mails(&self, _filter: &Filter) -> Vec<Fields> {
+        Vec::new()
+    }
+}
+
+/// Write `doc` to every sink of `settings`, like `write_all`. Profiles and
+/// units only concern observations and do not apply.
+pub async fn write_document<D: Document>(settings: &Settings, doc: &D) -> Result<(), CrawlError> {
+    let json = serde_json::to_vec(doc).map_err(|e| failed("document", e))?;
+    let mut failure = None;
+    for target in &settings.sinks {
+        let written = match target {
+            Target::File => {
+                let path = settings.base.join(D::FILE);
+                create_dir_all(&settings.base)
+                    .and_then(|_| atomic::write(&path, &json))
+                    .map_err(|source| CrawlError::Write { path, source })
+            }
+            Target::Stdout => {
+                let mut out = stdout().lock();
+                out.write_all(&json)
+                    .and_then(|_| writeln!(out))
+                    .and_then(|_| out.flush())
+                    .map_err(CrawlError::from)
+            }
+            Target::Http {
+                url,
+                client,
+                filter,
+            } => match doc.filtered(filter) {
+                Ok(Some(body)) => client
+                    .post(url)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(body.to_string())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map(|_| ())
+                    .map_err(|e| failed("http", e)),
+                Ok(None) => Ok(()),
+                Err(e) => Err(failed("http", e)),
+            },
+            Target::Chat {
+                chat,
+                url,
+                client,
+                filter,
+                ..
+            } => match doc.message(chat, filter) {
+                Some(text) => chat
+                    .post(client, url, &text)
+                    .await
+                    .map_err(|e| failed(chat.name(), e)),
+                None => Ok(()),
+            },
+            #[cfg(feature = "email")]
+            Target::Email { mailer, filter } => mailer
+                .send(doc.mails(filter))
+                .await
+                .map_err(|e| failed("email", e)),
+            #[cfg(feature = "sqlite")]
+            Target::Sqlite(path) => doc.to_sqlite(path).map_err(|e| failed("sqlite", e)),
+            Target::Custom(_) => Err(failed("custom", "only observations can be written")),
+        };
+        if let Err(e) = written {
+            if settings.sinks.len() > 1 {
+                error!(error = %e, "sink failed");
+            }
+            failure.get_or_insert(e);
+        }
+    }
+    failure.map_or(Ok(()), Err)
+}
+
+/// The document a non-file sink gets: the chosen profile in the chosen units.
+fn document(settings: &Settings, result: &CrawlResult) -> serde_json::Result<Value> {
+    let mut doc = match settings.profile {
+        Profile::Full => serde_json::to_value(result)?,
+        Profile::Publish => serde_json::to_value(publish::sanitize(result))?,
+    };
+    settings.units.apply(&mut doc);
+    Ok(doc)
+}
+
+fn failed(sink: &'static str, reason: impl ToString) -> CrawlError {
+    CrawlError::Sink {
+        sink,
+        reason: reason.to_string(),
+    }
+}
+
+struct Shared<'a>(&'a Mutex<dyn Sink + Send>);
+impl Sink for Shared<'_> {
+    fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a> {
+        Box::pin(async move { self.0.lock().await.write(result).await })
+    }
+}
+
+struct FileSink<'a> {
+    settings: &'a Settings,
+}
+impl Sink for FileSink<'_> {
+    fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a> {
+        Box::pin(async move { write_output(self.settings, result) })
+    }
+}
+
+struct StdoutSink<'a> {
+    settings: &'a Settings,
+}
+impl Sink for StdoutSink<'_> {
+    fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a> {
+        Box::pin(async move {
+            let doc = document(self.settings, result).map_err(|e| failed("stdout", e))?;
+            let mut out = stdout().lock();
+            serde_json::to_writer(&mut out, &doc).map_err(|e| failed("stdout", e))?;
+            writeln!(out).and_then(|_| out.flush())?;
+            Ok(())
+        })
+    }
+}
+
+/// POSTs the document of each result, with what `filter` wants.
+struct HttpSink<'a> {
+    settings: &'a Settings,
+    url: &'a str,
+    client: &'a Client,
+    filter: &'a Filter,
+}
+impl Sink for HttpSink<'_> {
+    fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a> {
+        Box::pin(async move {
+            let url = self.url;
+            let mut doc = document(self.settings, result).map_err(|e| failed("http", e))?;
+            if !self.filter.apply(&mut doc) {
+                debug!(%url, "no station wanted, not posted");
+                return Ok(());
+            }
+            let body = serde_json::to_vec(&doc).map_err(|e| failed("http", e))?;
+            let response = self
+                .client
+                .post(url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| failed("http", e))?;
+            if !response.status().is_success() {
+                return Err(failed(
+                    "http",
+                    format!("{} answered HTTP {}", url, response.status()),
+                ));
+            }
+            debug!(%url, "posted result");
+            Ok(())
+        })
+    }
+}
+
+/// Posts a line on each result to a chat, on the stations `filter` wants.
+struct ChatSink<'a> {
+    settings: &'a Settings,
+    chat: &'a Chat,
+    url: &'a str,
+    client: &'a Client,
+    filter: &'a Filter,
+}
+impl Sink for ChatSink<'_> {
+    fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a> {
+        Box::pin(async move {
+            let name = self.chat.name();
+            let mut doc = document(self.settings, result).map_err(|e| failed(name, e))?;
+            if !self.filter.apply(&mut doc) {
+                return Ok(());
+            }
+            let text = notify::summary(self.chat, self.settings.source.name(), &doc);
+            self.chat
+                .post(self.client, self.url, &text)
+                .await
+                .map_err(|e| failed(name, e))?;
+            debug!(chat = name, "posted summary");
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+struct SqliteSink<'a> {
+    settings: &'a Settings,
+    path: &'a PathBuf,
+}
+#[cfg(feature = "sqlite")]
+impl Sink for SqliteSink<'_> {
+    fn write<'a>(&'a mut self, result: &'a CrawlResult) -> Written<'a> {
+        use crate::export;
+
+        Box::pin(async move {
+            let doc = document(self.settings, result).map_err(|e| failed("sqlite", e))?;
+            let mut rows = Box::new(
+                export::to_sqlite::SqliteSink::create(self.path)
+                    .map_err(|e| failed("sqlite", e))?,
+            );
+            export::write_snapshot(rows.as_mut(), &doc).map_err(|e| failed("sqlite", e))?;
+            export::Sink::finish(rows).map_err(|e| failed("sqlite", e))?;
+            debug!(path = %self.path.display(), "wrote rows");
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,140 @@
+use scraper::Html;
+
+use std::collections::BTreeMap;
+
+use crate::asos;
+use crate::columns::{labelled_rows, LabelledRow};
+use crate::error::CrawlError;
+use crate::pipeline::Outcome;
+use crate::quantity::Celsius;
+use crate::source::Source;
+use crate::{
+    reading, CrawlResult, Rain, RainStatus, Record, SkippedRow, Wind, WindDirectionText,
+    SCHEMA_VERSION,
+};
+
+pub const SNOW_URL: &str = "https://www.kma.go.kr/weather/observation/currentweather_snow.jsp";
+
+/// Columns read from the table, found by their header labels.
+#[derive(Clone, Copy, PartialEq)]
+enum Column {
+    Name,
+    SnowDepth,
+    Temperature,
+}
+
+/// The column a header label belongs to, e.g. `적설(cm)현재`. New snow and
+/// the day's deepest snow are left out.
+fn column_of(label: &str) -> Option<Column> {
+    let column = if label.contains("지점") {
+        Column::Name
+    } else if label.contains("신적설") || label.contains("최심") {
+        return None;
+    } else if label.contains("적설") {
+        Column::SnowDepth
+    } else if label.contains("기온") {
+        Column::Temperature
+    } else {
+        return None;
+    };
+    Some(column)
+}
+
+/// Hourly snow depth of the stations measuring it (적설 관측), which KMA
+/// publishes in the winter months only.
+pub struct Snow;
+impl Source for Snow {
+    fn name(&self) -> &'static str {
+        "snow"
+    }
+
+    fn default_url(&self) -> &'static str {
+        SNOW_URL
+    }
+
+    /// The page has no past hours.
+    fn timed_url(&self, _url: &str, _minute: i64) -> Option<String> {
+        None
+    }
+
+    fn problem(&self, html: &str) -> Option<Outcome> {
+        asos::problem(html)
+    }
+
+    fn parse(&self, url: &str, html: &str) -> Result<CrawlResult, CrawlError> {
+        let document = Html::parse_document(html);
+        let observed_at = asos::observed_at(&document)
+            .ok_or_else(|| CrawlError::Decode("no observation time on the page".into()))?;
+        let mut records = Vec::new();
+        let mut skipped = Vec::new();
+        for row in labelled_rows(&document, Column::Name, column_of) {
+            match make_record(&row) {
+                Ok(record) => records.push(record),
+                Err(reason) => skipped.push(SkippedRow {
+                    reason,
+                    cells: row.cells,
+                }),
+            }
+        }
+        Ok(CrawlResult {
+            schema_version: SCHEMA_VERSION,
+            attribution: None,
+            instance: None,
+            units: None,
+            observed_at,
+            source: url.to_owned(),
+            records,
+            marine: Vec::new(),
+            air: Vec::new(),
+            regions: Vec::new(),
+            skipped,
+        })
+    }
+}
+
+fn make_record(row: &LabelledRow<Column>) -> Result<Record, String> {
+    let id = row.station.ok_or("no station number in the row")?;
+    let mut quality = BTreeMap::new();
+    let mut read = |name: &str, wanted: Column| {
+        row.get(wanted)
+            .and_then(|text| reading(&mut quality, name, text))
+    };
+    let snow_depth = read("snow_depth", Column::SnowDepth);
+    let temperature = read("temperature", Column::Temperature).map(Celsius);
+    Ok(Record {
+        id,
+        name: row.get(Column::Name).unwrap_or_default().to_string(),
+        name_en: None,
+        station: None,
+        height: None,
+        rain: Rain {
+            is_raining: RainStatus::Unknown,
+            rain15: None,
+            rain60: None,
+            rain3h: None,
+            rain6h: None,
+            rain12h: None,
+            rainday: None,
+            intensity: None,
+        },
+        temperature,
+        wind1: Wind::new(None, WindDirectionText::Unavailable, None),
+        wind10: Wind::new(None, WindDirectionText::Unavailable, None),
+        humidity: None,
+        atmospheric: None,
+        sunshine: None,
+        solar_radiation: None,
+        snow_depth,
+        visibility: None,
+        cloud_cover: None,
+        pressure_sea_level: None,
+        address: String::new(),
+        region: None,
+        spatial_flags: Vec::new(),
+        quality,
+        derived: None,
+        trend: None,
+        astro: None,
+        air: None,
+    })
+}
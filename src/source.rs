@@ -0,0 +1,136 @@
+use scraper::{Html, Selector};
+
+use crate::air::AirQuality;
+use crate::asos::AsosHourly;
+use crate::error::CrawlError;
+use crate::marine::Marine;
+use crate::openapi::OpenApi;
+use crate::pipeline::{Outcome, Page};
+use crate::snow::Snow;
+use crate::{archive, charset, parse_page, CrawlResult, AWS_URL};
+
+/// Phrases of the notice KMA shows instead of the table during maintenance.
+pub const MAINTENANCE_MARKERS: [&str; 3] = ["점검", "maintenance", "서비스를 일시 중단"];
+
+/// A KMA product the crawler can follow: where its page is and how to read
+/// it. Fetching, retries, QC and the sinks are shared by all of them.
+pub trait Source: Sync {
+    /// Name `--source` knows the product by.
+    fn name(&self) -> &'static str;
+
+    /// Page crawled when no `--url` is given.
+    fn default_url(&self) -> &'static str;
+
+    /// Whether requests need `--api-key`.
+    fn needs_api_key(&self) -> bool {
+        false
+    }
+
+    /// URL fetched for the newest page of `url`; most products serve it at
+    /// `url` itself.
+    fn latest_url(&self, url: &str) -> String {
+        url.to_string()
+    }
+
+    /// `url` asking for the page as of `minute`, when the product serves
+    /// past pages at all.
+    fn timed_url(&self, url: &str, minute: i64) -> Option<String>;
+
+    /// Text of a fetched page.
+    fn decode(&self, page: &Page, strict: bool) -> Result<String, String> {
+        charset::decode(&page.body, page.content_type.as_deref(), strict)
+    }
+
+    /// Why a decoded page is not one of the product's tables, if it is not.
+    fn problem(&self, html: &str) -> Option<Outcome>;
+
+    fn parse(&self, url: &str, html: &str) -> Result<CrawlResult, CrawlError>;
+}
+
+/// Every source, the first being the default.
+pub const SOURCES: [&dyn Source; 6] = [
+    &AwsMinute,
+    &AsosHourly,
+    &OpenApi,
+    &Marine,
+    &AirQuality,
+    &Snow,
+];
+
+pub fn names() -> Vec<&'static str> {
+    SOURCES.iter().map(|s| s.name()).collect()
+}
+
+pub fn by_name(name: &str) -> Option<&'static dyn Source> {
+    SOURCES.into_iter().find(|s| s.name() == name)
+}
+
+/// `url` with each of `pairs` set in its query, replacing earlier values.
+pub fn with_query(url: &str, pairs: &[(&str, &str)]) -> String {
+    let mut url = pairs
+        .iter()
+        .fold(url.to_string(), |url, (key, _)| without_query(&url, key));
+    for (key, value) in pairs {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&format!("{}={}", key, value));
+    }
+    url
+}
+
+/// `url` without `key` in its query.
+pub fn without_query(url: &str, key: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| pair.split('=').next() != Some(key))
+        .collect();
+    if kept.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, kept.join("&"))
+    }
+}
+
+/// AWS per-minute observations, `nph-aws_txt_min`.
+pub struct AwsMinute;
+impl Source for AwsMinute {
+    fn name(&self) -> &'static str {
+        "aws-minute"
+    }
+
+    fn default_url(&self) -> &'static str {
+        AWS_URL
+    }
+
+    /// e.g. `...nph-aws_txt_min?202405011234&0&MINDB_01M&0&a`.
+    fn timed_url(&self, url: &str, minute: i64) -> Option<String> {
+        let base = url.split('?').next().unwrap_or(url);
+        let stamp: String = archive::format_minute(minute)
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect();
+        Some(format!("{}?{}&0&MINDB_01M&0&a", base, stamp))
+    }
+
+    /// Recognize pages that are not an observation table at all, such as
+    /// the notice KMA serves during system maintenance.
+    fn problem(&self, html: &str) -> Option<Outcome> {
+        let document = Html::parse_document(html);
+        let time_selector = Selector::parse("span.ehead").unwrap();
+        if document.select(&time_selector).next().is_some() {
+            return None;
+        }
+        let text: String = document.root_element().text().collect();
+        if MAINTENANCE_MARKERS.iter().any(|m| text.contains(m)) {
+            Some(Outcome::Down("KMA maintenance notice".into()))
+        } else {
+            Some(Outcome::Retry("no observation time on page".into()))
+        }
+    }
+
+    fn parse(&self, url: &str, html: &str) -> Result<CrawlResult, CrawlError> {
+        parse_page(url, html)
+    }
+}
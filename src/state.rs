@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs::{create_dir_all, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::atomic;
+
+const STATE_FILE: &str = ".state";
+
+/// Bookkeeping carried from one crawl to the next, stored in `<base>/.state`.
+#[derive(Default, Serialize, Deserialize)]
+pub struct State {
+    /// URL the validators below belong to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// Observation last written to the base path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_at: Option<String>,
+}
+impl State {
+    /// Read the state of `base`, starting fresh if it is missing or unreadable.
+    pub fn load(base: &Path) -> Self {
+        File::open(base.join(STATE_FILE))
+            .ok()
+            .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, base: &Path) -> std::io::Result<()> {
+        create_dir_all(base)?;
+        atomic::write(&base.join(STATE_FILE), &serde_json::to_vec(self)?)
+    }
+}
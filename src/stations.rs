@@ -0,0 +1,102 @@
+use chrono::NaiveDate;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+#[cfg(feature = "fetch")]
+use std::fs::File;
+#[cfg(feature = "fetch")]
+use std::io::BufReader;
+#[cfg(feature = "fetch")]
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Locations of the synoptic (ASOS) stations, as published by KMA.
+const BUNDLED: &str = include_str!("../data/stations.json");
+
+/// Static facts about an AWS station that the observation table lacks.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Station {
+    pub id: u32,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Meters above mean sea level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<f64>,
+    /// 법정동 code of the administrative area the station stands in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// First day of observations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<NaiveDate>,
+}
+impl Station {
+    /// Great-circle distance in kilometers to a point.
+    pub fn distance_to(&self, latitude: f64, longitude: f64) -> f64 {
+        great_circle_km((self.latitude, self.longitude), (latitude, longitude))
+    }
+}
+
+/// Station registry keyed by AWS id.
+///
+/// The bundled catalog holds some of the synoptic (ASOS) stations only, a
+/// small part of the AWS network; `--stations-file` fills in the rest.
+///
+/// Files are JSON arrays of `{"id": 400, "latitude": 37.5, "longitude": 127.0}`
+/// objects, optionally with `elevation`, `admin_code`, `operator` and
+/// `start_date`.
+pub struct Catalog {
+    stations: HashMap<u32, Station>,
+    /// Whether `first_sparse` has answered `true`.
+    warned: AtomicBool,
+}
+impl Catalog {
+    pub fn bundled() -> Self {
+        let stations: Vec<Station> =
+            serde_json::from_str(BUNDLED).expect("bundled station catalog is valid");
+        Catalog {
+            stations: stations.into_iter().map(|s| (s.id, s)).collect(),
+            warned: AtomicBool::new(false),
+        }
+    }
+
+    #[cfg(feature = "fetch")]
+    /// Overlay the catalog with the stations of a JSON file.
+    pub fn extend_from(&mut self, path: &Path) -> std::io::Result<()> {
+        let reader = BufReader::new(File::open(path)?);
+        let stations: Vec<Station> = serde_json::from_reader(reader)?;
+        self.stations
+            .extend(stations.into_iter().map(|s| (s.id, s)));
+        Ok(())
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Station> {
+        self.stations.get(&id)
+    }
+
+    /// Whether most of `total` records found no station here, answered
+    /// `true` the first time only so that it is warned about once.
+    pub fn first_sparse(&self, found: usize, total: usize) -> bool {
+        found * 2 < total && !self.warned.swap(true, Ordering::Relaxed)
+    }
+}
+
+/// Great-circle distance in kilometers between two points given as
+/// latitude and longitude.
+pub fn great_circle_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.1 - a.1).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+/// Great-circle distance in kilometers.
+pub fn distance_km(a: &Station, b: &Station) -> f64 {
+    a.distance_to(b.latitude, b.longitude)
+}
@@ -0,0 +1,93 @@
+use serde::Serialize;
+
+use std::fs::OpenOptions;
+use std::io::{stderr, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::CrawlError;
+use crate::{CrawlResult, Record};
+
+/// What one crawl cycle did, appended as a JSON line to `--stats-file`.
+#[derive(Default, Serialize)]
+pub struct CrawlStats {
+    /// Unix time the cycle ended.
+    pub finished_at: u64,
+    /// Product crawled, when several are crawled at once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// `ok`, `not modified`, or the error that ended the cycle.
+    pub outcome: String,
+    pub exit_code: i32,
+    pub requests: usize,
+    pub fetch_ms: u128,
+    pub bytes_downloaded: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed_at: Option<String>,
+    pub rows_seen: usize,
+    pub rows_parsed: usize,
+    pub rows_skipped: usize,
+    pub missing_temperature: usize,
+    pub missing_humidity: usize,
+    pub missing_atmospheric: usize,
+    pub missing_wind: usize,
+    /// Webhook and chat sinks that failed to take the result.
+    pub push_failures: usize,
+}
+impl CrawlStats {
+    pub fn fetched(&mut self, took: Duration, bytes: usize) {
+        self.requests += 1;
+        self.fetch_ms += took.as_millis();
+        self.bytes_downloaded += bytes;
+    }
+
+    /// Count the rows of the page that is about to be written.
+    pub fn parsed(&mut self, url: &str, result: &CrawlResult) {
+        let missing = |f: fn(&Record) -> bool| result.records.iter().filter(|r| f(r)).count();
+        self.url = Some(url.into());
+        self.observed_at = Some(result.observed_at.to_rfc3339());
+        self.rows_parsed = result.records.len();
+        self.rows_skipped = result.skipped.len();
+        self.rows_seen = self.rows_parsed + self.rows_skipped;
+        self.missing_temperature = missing(|r| r.temperature.is_none());
+        self.missing_humidity = missing(|r| r.humidity.is_none());
+        self.missing_atmospheric = missing(|r| r.atmospheric.is_none());
+        self.missing_wind = missing(|r| r.wind1.velocity.is_none());
+    }
+
+    pub fn not_modified(&mut self) {
+        self.outcome = "not modified".into();
+    }
+
+    pub fn finish(&mut self, outcome: &Result<(), CrawlError>) {
+        self.finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        match outcome {
+            Ok(()) if self.outcome.is_empty() => self.outcome = "ok".into(),
+            Ok(()) => {}
+            Err(e) => {
+                if self.outcome.is_empty() {
+                    self.outcome = e.to_string();
+                }
+                self.exit_code = e.exit_code();
+            }
+        }
+    }
+
+    /// Append to `path`, or print to stderr when it is `-`.
+    pub fn emit(&self, path: &Path) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(self)?;
+        line.push(b'\n');
+        if path.as_os_str() == "-" {
+            return stderr().lock().write_all(&line);
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(&line)
+    }
+}
@@ -0,0 +1,270 @@
+use clap::{arg, value_parser, ArgAction, ArgMatches, Command};
+
+use reqwest::Client;
+
+use serde_json::Value;
+
+use tracing::{debug, info, warn};
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::notify::{self, Chat};
+use crate::region::Region;
+use crate::{archive, logging};
+
+type Error = Box<dyn std::error::Error>;
+
+/// The Bot API of Telegram, unless `--telegram-api` names a server of one's
+/// own.
+pub const API: &str = "https://api.telegram.org";
+
+/// How long `getUpdates` holds on to a request while no message comes.
+const POLL_SECONDS: u64 = 50;
+
+/// How long to wait after `getUpdates` failed before asking again.
+const RETRY: Duration = Duration::from_secs(5);
+
+const HELP: &str = "/now PLACE: the latest readings at PLACE
+/rain [PLACE]: where it rained in the last hour, at PLACE or anywhere
+
+PLACE is a station id or name, or a region such as 서울, 경기도 수원시 or 강남구.";
+
+/// URL of the Bot API `method` for the bot of `token`.
+pub fn method(api: &str, token: &str, method: &str) -> String {
+    format!("{}/bot{}/{}", api.trim_end_matches('/'), token, method)
+}
+
+pub fn command() -> Command {
+    Command::new("telegram")
+        .about("answer /now and /rain in Telegram chats from the latest crawl")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--token <TOKEN> "token of the bot, from @BotFather").required(true))
+        .arg(
+            arg!(--allow <CHAT_ID> "only answer this chat; repeatable")
+                .value_parser(value_parser!(i64))
+                .action(ArgAction::Append),
+        )
+        .arg(arg!(--"telegram-api" <URL> "Bot API server to use").default_value(API))
+        .arg(
+            arg!(--"log-level" <LEVEL> "most verbose level to log")
+                .value_parser(logging::LEVELS)
+                .default_value("info"),
+        )
+        .arg(
+            arg!(--"log-format" <FORMAT> "log line format")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Error> {
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let base = matches.get_one::<PathBuf>("base").unwrap();
+    let token = matches.get_one::<String>("token").unwrap();
+    let api = matches.get_one::<String>("telegram-api").unwrap();
+    let allowed: Vec<i64> = matches
+        .get_many::<i64>("allow")
+        .unwrap_or_default()
+        .copied()
+        .collect();
+    let client = Client::builder()
+        .timeout(Duration::from_secs(POLL_SECONDS + 10))
+        .build()?;
+    let updates = method(api, token, "getUpdates");
+    let send = method(api, token, "sendMessage");
+    let mut offset = 0;
+    info!("answering commands");
+    loop {
+        let batch = match poll(&client, &updates, offset).await {
+            Ok(batch) => batch,
+            Err(e) => {
+                warn!(error = %e, "getUpdates failed");
+                tokio::time::sleep(RETRY).await;
+                continue;
+            }
+        };
+        for update in batch {
+            offset = offset.max(update["update_id"].as_i64().unwrap_or_default() + 1);
+            let message = &update["message"];
+            let (Some(chat_id), Some(text)) =
+                (message["chat"]["id"].as_i64(), message["text"].as_str())
+            else {
+                continue;
+            };
+            if !allowed.is_empty() && !allowed.contains(&chat_id) {
+                debug!(chat_id, "not allowed, ignored");
+                continue;
+            }
+            let Some(reply) = answer(base, text) else {
+                continue;
+            };
+            debug!(chat_id, text, "answering");
+            if let Err(e) = Chat::Telegram(chat_id.to_string())
+                .post(&client, &send, &reply)
+                .await
+            {
+                warn!(chat_id, error = %e, "answering failed");
+            }
+        }
+    }
+}
+
+/// The updates after `offset`, waiting for one to come.
+async fn poll(client: &Client, url: &str, offset: i64) -> Result<Vec<Value>, String> {
+    let body = client
+        .get(url)
+        .query(&[
+            ("offset", offset.to_string()),
+            ("timeout", POLL_SECONDS.to_string()),
+            ("allowed_updates", r#"["message"]"#.to_string()),
+        ])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    let response: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    if response["ok"] != true {
+        return Err(response["description"]
+            .as_str()
+            .unwrap_or("not ok")
+            .to_string());
+    }
+    Ok(response["result"].as_array().cloned().unwrap_or_default())
+}
+
+/// The reply to the message `text`, if it is a command.
+fn answer(base: &Path, text: &str) -> Option<String> {
+    let mut words = text.split_whitespace();
+    // In groups commands may name the bot, as in `/now@SomeBot 서울`.
+    let command = words.next()?.split('@').next()?;
+    let place = words.collect::<Vec<_>>().join(" ");
+    let known = match command {
+        "/now" => !place.is_empty(),
+        "/rain" => true,
+        _ => false,
+    };
+    if !known {
+        return command.starts_with('/').then(|| HELP.to_string());
+    }
+    let doc = match archive::read_index(base) {
+        Ok(doc) => doc,
+        Err(e) => return Some(format!("No crawl to answer from: {}", e)),
+    };
+    let observed_at = doc["observed_at"].as_str().unwrap_or_default();
+    let records = stations(&doc, &place);
+    if records.is_empty() {
+        return Some(format!("No station matches {}.", place));
+    }
+    if command == "/now" {
+        return Some(now(&doc, &place, &records));
+    }
+    let mut raining: Vec<(f64, &Value)> = records
+        .into_iter()
+        .filter_map(|record| Some((record.pointer("/rain/rain60")?.as_f64()?, record)))
+        .filter(|(mm, _)| *mm > 0.0)
+        .collect();
+    if raining.is_empty() {
+        let place = if place.is_empty() {
+            "anywhere".to_string()
+        } else {
+            format!("at {}", place)
+        };
+        return Some(format!("No rain {} in the hour to {}.", place, observed_at));
+    }
+    raining.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let rain = notify::unit(&doc, "rain");
+    let mut reply = format!("Rain in the hour to {}", observed_at);
+    for (_, record) in raining {
+        reply.push_str(&format!(
+            "\n{}: {} {} in the hour",
+            station(record),
+            reading(record, "/rain/rain60").unwrap_or_default(),
+            rain
+        ));
+        if let Some(today) = reading(record, "/rain/rainday") {
+            reply.push_str(&format!(", {} {} today", today, rain));
+        }
+    }
+    Some(reply)
+}
+
+/// The readings of `records` at `place`.
+fn now(doc: &Value, place: &str, records: &[&Value]) -> String {
+    let mut reply = format!(
+        "{} at {}",
+        place,
+        doc["observed_at"].as_str().unwrap_or_default()
+    );
+    for record in records {
+        let mut parts = Vec::new();
+        if let Some(t) = reading(record, "/temperature") {
+            parts.push(format!("{} {}", t, notify::unit(doc, "temperature")));
+        }
+        if let Some(h) = reading(record, "/humidity") {
+            parts.push(format!("humidity {}%", h));
+        }
+        if let Some(v) = reading(record, "/wind10/velocity") {
+            let direction = reading(record, "/wind10/direction_text").unwrap_or_default();
+            parts.push(
+                format!("wind {} {} {}", v, notify::unit(doc, "wind"), direction)
+                    .trim_end()
+                    .to_string(),
+            );
+        }
+        if let Some(mm) = reading(record, "/rain/rain60") {
+            parts.push(format!(
+                "rain {} {} in the hour",
+                mm,
+                notify::unit(doc, "rain")
+            ));
+        }
+        reply.push_str(&format!("\n{}: {}", station(record), parts.join(", ")));
+    }
+    reply
+}
+
+/// The records of `doc` at `place`: a station by id or name, or those within
+/// a region. No place is everywhere.
+fn stations<'a>(doc: &'a Value, place: &str) -> Vec<&'a Value> {
+    doc["records"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|record| {
+            place.is_empty()
+                || place
+                    .parse()
+                    .is_ok_and(|id: u64| record["id"].as_u64() == Some(id))
+                || record["name"] == place
+                || record["name_en"]
+                    .as_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(place))
+                || serde_json::from_value::<Region>(record["region"].clone())
+                    .is_ok_and(|region| region.within(place))
+        })
+        .collect()
+}
+
+fn station(record: &Value) -> String {
+    format!(
+        "{} ({})",
+        record["name"].as_str().unwrap_or_default(),
+        record["id"]
+    )
+}
+
+fn reading(record: &Value, pointer: &str) -> Option<String> {
+    match record.pointer(pointer)? {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
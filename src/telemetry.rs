@@ -0,0 +1,94 @@
+use opentelemetry::metrics::Counter;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+
+use tracing_subscriber::{Layer, Registry};
+
+use crate::error::CrawlError;
+use crate::instance::Instance;
+
+/// OTLP/HTTP export of the crawl's span tree and failure counters.
+pub struct Telemetry {
+    tracer: SdkTracerProvider,
+    meter: SdkMeterProvider,
+    crawls: Counter<u64>,
+    failures: Counter<u64>,
+}
+impl Telemetry {
+    /// Export to the collector at `endpoint`, e.g. `http://localhost:4318`.
+    pub fn init(
+        endpoint: &str,
+        instance: Option<&Instance>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let mut resource = Resource::builder().with_service_name(env!("CARGO_PKG_NAME"));
+        if let Some(instance) = instance {
+            resource = resource.with_attributes([
+                KeyValue::new("weather_crawl.site", instance.site.clone()),
+                KeyValue::new("host.name", instance.host.clone()),
+            ]);
+            if let Some(region) = &instance.region {
+                resource = resource.with_attribute(KeyValue::new("cloud.region", region.clone()));
+            }
+        }
+        let resource = resource.build();
+        let tracer = SdkTracerProvider::builder()
+            .with_batch_exporter(
+                SpanExporter::builder()
+                    .with_http()
+                    .with_endpoint(format!("{}/v1/traces", endpoint))
+                    .build()?,
+            )
+            .with_resource(resource.clone())
+            .build();
+        let meter = SdkMeterProvider::builder()
+            .with_periodic_exporter(
+                MetricExporter::builder()
+                    .with_http()
+                    .with_endpoint(format!("{}/v1/metrics", endpoint))
+                    .build()?,
+            )
+            .with_resource(resource)
+            .build();
+        global::set_meter_provider(meter.clone());
+        let counters = global::meter(env!("CARGO_PKG_NAME"));
+        Ok(Telemetry {
+            crawls: counters.u64_counter("weather_crawl.crawls").build(),
+            failures: counters.u64_counter("weather_crawl.failures").build(),
+            tracer,
+            meter,
+        })
+    }
+
+    /// Layer turning the crawl's tracing spans into OpenTelemetry spans.
+    pub fn layer(&self) -> Box<dyn Layer<Registry> + Send + Sync> {
+        tracing_opentelemetry::layer()
+            .with_tracer(self.tracer.tracer(env!("CARGO_PKG_NAME")))
+            .boxed()
+    }
+
+    pub fn record(&self, outcome: &Result<(), CrawlError>) {
+        self.crawls.add(1, &[]);
+        match outcome {
+            // A page that has not advanced is the expected result of frequent runs.
+            Ok(()) | Err(CrawlError::Unchanged { .. }) => {}
+            Err(e) => self
+                .failures
+                .add(1, &[KeyValue::new("exit_code", e.exit_code() as i64)]),
+        }
+    }
+
+    /// Flush what is still buffered; the process exits right after a crawl.
+    pub fn shutdown(self) {
+        if let Err(e) = self.tracer.shutdown() {
+            eprintln!("error: flushing traces: {}", e);
+        }
+        if let Err(e) = self.meter.shutdown() {
+            eprintln!("error: flushing metrics: {}", e);
+        }
+    }
+}
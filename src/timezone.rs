@@ -0,0 +1,39 @@
+use chrono::{DateTime, FixedOffset, Local, Utc};
+
+use std::str::FromStr;
+
+/// Offset of Korea Standard Time, which every KMA page is written in.
+pub fn kst() -> FixedOffset {
+    FixedOffset::east_opt(9 * 3600).unwrap()
+}
+
+/// Timezone `observed_at` is written in.
+#[derive(Clone, Copy)]
+pub enum OutputTz {
+    Utc,
+    /// The timezone of the machine running the crawler.
+    Local,
+    Fixed(FixedOffset),
+}
+impl OutputTz {
+    pub fn convert(self, instant: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self {
+            OutputTz::Utc => instant.with_timezone(&Utc).fixed_offset(),
+            OutputTz::Local => instant.with_timezone(&Local).fixed_offset(),
+            OutputTz::Fixed(offset) => instant.with_timezone(&offset),
+        }
+    }
+}
+impl FromStr for OutputTz {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utc" => Ok(OutputTz::Utc),
+            "local" => Ok(OutputTz::Local),
+            _ => FixedOffset::from_str(s)
+                .map(OutputTz::Fixed)
+                .map_err(|_| format!("`{}` is not utc, local or an offset like +09:00", s)),
+        }
+    }
+}
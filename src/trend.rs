@@ -0,0 +1,128 @@
+use rust_decimal::Decimal;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+#[cfg(feature = "fetch")]
+use std::fs::File;
+#[cfg(feature = "fetch")]
+use std::io::BufReader;
+#[cfg(feature = "fetch")]
+use std::path::Path;
+
+#[cfg(feature = "fetch")]
+use crate::atomic;
+use crate::quantity::HectoPascals;
+use crate::{RainStatus, Record};
+
+#[cfg(feature = "fetch")]
+const HISTORY_FILE: &str = ".stations";
+/// Pressure tendency is taken over three hours, as in synoptic reports.
+const TENDENCY_MINUTES: i64 = 3 * 60;
+/// How much earlier than three hours ago a reading may still be compared.
+const TENDENCY_SLACK: i64 = 30;
+
+/// How a station changed since the previous crawls, with `--trends`.
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Trend {
+    /// Change of station pressure over the last three hours, in hPa.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure_tendency: Option<HectoPascals>,
+    /// The rain sensor detects rain and did not on the previous crawl.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rain_started: bool,
+    /// The rain sensor detected rain on the previous crawl and does not now.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rain_stopped: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct StationHistory {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raining: Option<bool>,
+    /// Readings of the last three hours and a bit, oldest first.
+    #[serde(default)]
+    pressure: Vec<(i64, HectoPascals)>,
+    /// Latest reading of each validated field, as observed.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    last: HashMap<String, (i64, Decimal)>,
+}
+
+/// Per-station readings carried from one crawl to the next, stored in
+/// `<base>/.stations`.
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    stations: HashMap<u32, StationHistory>,
+}
+impl History {
+    #[cfg(feature = "fetch")]
+    /// Read the history of `base`, starting fresh if it is missing or unreadable.
+    pub fn load(base: &Path) -> Self {
+        File::open(base.join(HISTORY_FILE))
+            .ok()
+            .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "fetch")]
+    pub fn save(&self, base: &Path) -> std::io::Result<()> {
+        atomic::write(&base.join(HISTORY_FILE), &serde_json::to_vec(self)?)
+    }
+
+    /// Remember `value` as the latest reading of `field` at station `id`,
+    /// returning the one it replaces. Replayed or older pages are neither
+    /// compared nor remembered.
+    pub fn remember(
+        &mut self,
+        id: u32,
+        field: &str,
+        minute: i64,
+        value: Decimal,
+    ) -> Option<(i64, Decimal)> {
+        let last = &mut self.stations.entry(id).or_default().last;
+        match last.get(field) {
+            Some(&(at, _)) if at >= minute => None,
+            _ => last.insert(field.to_string(), (minute, value)),
+        }
+    }
+
+    /// Set the trend of each of `records` observed at `minute`, and remember
+    /// their readings for the next crawl.
+    pub fn observe(&mut self, minute: i64, records: &mut [Record]) {
+        for record in records.iter_mut() {
+            let history = self.stations.entry(record.id).or_default();
+            let raining = match record.rain.is_raining {
+                RainStatus::Rain => Some(true),
+                RainStatus::Clear => Some(false),
+                RainStatus::Unavailable | RainStatus::Unknown => None,
+            };
+            let was_raining = history.raining;
+            let trend = Trend {
+                pressure_tendency: record.atmospheric.and_then(|now| {
+                    let earliest = minute - TENDENCY_MINUTES - TENDENCY_SLACK;
+                    let (_, then) = history
+                        .pressure
+                        .iter()
+                        .rfind(|(m, _)| (earliest..=minute - TENDENCY_MINUTES).contains(m))?;
+                    Some(now - *then)
+                }),
+                rain_started: was_raining == Some(false) && raining == Some(true),
+                rain_stopped: was_raining == Some(true) && raining == Some(false),
+            };
+
+            if raining.is_some() {
+                history.raining = raining;
+            }
+            if let Some(pressure) = record.atmospheric {
+                history.pressure.retain(|(m, _)| *m != minute);
+                history.pressure.push((minute, pressure));
+                history.pressure.sort_by_key(|(m, _)| *m);
+            }
+            history
+                .pressure
+                .retain(|(m, _)| *m >= minute - TENDENCY_MINUTES - TENDENCY_SLACK);
+            record.trend = Some(trend);
+        }
+    }
+}
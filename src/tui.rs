@@ -0,0 +1,286 @@
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use serde_json::Value;
+
+use std::cmp::Ordering;
+use std::fs::metadata;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::archive;
+
+type Error = Box<dyn std::error::Error>;
+
+/// How often `index.json` is looked at for a new crawl.
+const REFRESH: Duration = Duration::from_millis(500);
+
+/// A column of the table: its title, the unit of `units` it is in, and where
+/// the value is in a record.
+struct Column {
+    title: &'static str,
+    unit: Option<&'static str>,
+    pointer: &'static str,
+    width: Constraint,
+}
+
+const fn column(
+    title: &'static str,
+    unit: Option<&'static str>,
+    pointer: &'static str,
+    width: u16,
+) -> Column {
+    Column {
+        title,
+        unit,
+        pointer,
+        width: Constraint::Length(width),
+    }
+}
+
+const COLUMNS: [Column; 9] = [
+    column("id", None, "/id", 5),
+    column("name", None, "/name", 10),
+    column("temp", Some("temperature"), "/temperature", 9),
+    column("humidity", None, "/humidity", 9),
+    column("rain1h", Some("rain"), "/rain/rain60", 10),
+    column("today", Some("rain"), "/rain/rainday", 10),
+    column("wind", Some("wind"), "/wind10/velocity", 10),
+    column("dir", None, "/wind10/direction_text", 4),
+    Column {
+        title: "address",
+        unit: None,
+        pointer: "/address",
+        width: Constraint::Min(10),
+    },
+];
+
+pub fn command() -> Command {
+    Command::new("tui")
+        .about("show the stations of the latest crawl as a live table in the terminal")
+        .arg(arg!(<base> "base path the crawler writes to").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--sort <COLUMN> "column to sort by")
+                .value_parser(COLUMNS.map(|c| c.title))
+                .default_value("id"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let base = matches.get_one::<PathBuf>("base").unwrap().clone();
+    let sort = matches.get_one::<String>("sort").unwrap();
+    let mut view = View {
+        base,
+        modified: None,
+        doc: Value::Null,
+        error: None,
+        sort: COLUMNS
+            .iter()
+            .position(|c| c.title == sort)
+            .unwrap_or_default(),
+        descending: false,
+        filter: String::new(),
+        editing: false,
+        table: TableState::default().with_selected(0),
+    };
+    view.reload();
+    let mut terminal = ratatui::init();
+    let outcome = view.run(&mut terminal);
+    ratatui::restore();
+    outcome
+}
+
+/// What is shown, and how.
+struct View {
+    base: PathBuf,
+    /// When `index.json` was last replaced, as read.
+    modified: Option<SystemTime>,
+    doc: Value,
+    /// Why `index.json` could not be read, last time it was tried.
+    error: Option<String>,
+    sort: usize,
+    descending: bool,
+    /// Stations whose id, name or address holds this are shown.
+    filter: String,
+    /// Whether keys go to the filter.
+    editing: bool,
+    table: TableState,
+}
+
+impl View {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(REFRESH)? {
+                self.reload();
+                continue;
+            }
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            if self.editing {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Esc => self.editing = false,
+                    KeyCode::Backspace => drop(self.filter.pop()),
+                    KeyCode::Char(c) => self.filter.push(c),
+                    _ => {}
+                }
+                self.table.select(Some(0));
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('/') => self.editing = true,
+                KeyCode::Left => self.sort = (self.sort + COLUMNS.len() - 1) % COLUMNS.len(),
+                KeyCode::Right | KeyCode::Tab => self.sort = (self.sort + 1) % COLUMNS.len(),
+                KeyCode::Char('r') => self.descending = !self.descending,
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::PageDown => self.table.scroll_down_by(20),
+                KeyCode::PageUp => self.table.scroll_up_by(20),
+                _ => {}
+            }
+        }
+    }
+
+    /// Read `index.json` again if the crawler has replaced it.
+    fn reload(&mut self) {
+        let modified = metadata(self.base.join(archive::INDEX_FILE)).and_then(|m| m.modified());
+        if modified.as_ref().ok() == self.modified.as_ref() {
+            return;
+        }
+        match archive::read_index(&self.base) {
+            Ok(doc) => {
+                self.doc = doc;
+                self.modified = modified.ok();
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    /// The records the filter keeps, in the order asked for.
+    fn records(&self) -> Vec<&Value> {
+        let filter = self.filter.to_lowercase();
+        let mut records: Vec<&Value> = self.doc["records"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|record| {
+                ["/id", "/name", "/name_en", "/address"]
+                    .iter()
+                    .any(|pointer| {
+                        record
+                            .pointer(pointer)
+                            .is_some_and(|value| text(value).to_lowercase().contains(&filter))
+                    })
+            })
+            .collect();
+        let pointer = COLUMNS[self.sort].pointer;
+        records.sort_by(|a, b| {
+            let order = compare(a.pointer(pointer), b.pointer(pointer));
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        records
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [status, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let records = self.records();
+        let total = self.doc["records"].as_array().map_or(0, Vec::len);
+        let mut line = format!(
+            "{}  {} of {} stations",
+            self.doc["observed_at"]
+                .as_str()
+                .unwrap_or("nothing crawled yet"),
+            records.len(),
+            total
+        );
+        if let Some(error) = &self.error {
+            line.push_str(&format!("  ({})", error));
+        }
+        let header = Row::new(COLUMNS.iter().enumerate().map(|(i, column)| {
+            let mut title = match column.unit {
+                Some(unit) => format!(
+                    "{} {}",
+                    column.title,
+                    self.doc["units"][unit].as_str().unwrap_or(metric(unit))
+                ),
+                None => column.title.to_string(),
+            };
+            if i == self.sort {
+                title.push(if self.descending { '▼' } else { '▲' });
+            }
+            Cell::from(title)
+        }))
+        .style(Style::new().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+        let rows = records.iter().map(|record| {
+            Row::new(COLUMNS.iter().map(|column| {
+                Cell::from(record.pointer(column.pointer).map(text).unwrap_or_default())
+            }))
+        });
+        let table = Table::new(rows, COLUMNS.map(|c| c.width))
+            .header(header)
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let filter = if self.editing || !self.filter.is_empty() {
+            format!(
+                "filter: {}{}",
+                self.filter,
+                if self.editing { "_" } else { "" }
+            )
+        } else {
+            "q quit  ←/→ sort  r reverse  / filter  ↑/↓ scroll".to_string()
+        };
+
+        frame.render_widget(Paragraph::new(line), status);
+        frame.render_stateful_widget(table.block(Block::new()), body, &mut self.table);
+        frame.render_widget(Line::from(filter), footer);
+    }
+}
+
+/// Numbers before text, and either before no value at all.
+fn compare(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let key = |value: Option<&Value>| match value {
+        Some(Value::Number(n)) => (0, n.as_f64().unwrap_or_default(), String::new()),
+        Some(Value::String(s)) => (1, 0.0, s.clone()),
+        _ => (2, 0.0, String::new()),
+    };
+    let (a, b) = (key(a), key(b));
+    a.0.cmp(&b.0)
+        .then(a.1.total_cmp(&b.1))
+        .then_with(|| a.2.cmp(&b.2))
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The unit of a metric document, which carries no `units`.
+fn metric(unit: &str) -> &'static str {
+    match unit {
+        "temperature" => "°C",
+        "wind" => "m/s",
+        _ => "mm",
+    }
+}
@@ -0,0 +1,262 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SubsecRound, Utc};
+
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use reqwest::Client;
+
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tracing::{info, info_span, warn, Instrument};
+
+use std::collections::BTreeMap;
+
+use crate::asos::compass;
+use crate::quantity::{HectoPascals, MetersPerSecond};
+use crate::sink::{self, Document};
+use crate::source::{with_query, without_query};
+use crate::{archive, logging, openapi, timezone, WindDirectionText};
+
+/// Bulletins of data.go.kr's TyphoonInfoService.
+pub const TYPHOON_URL: &str = "https://apis.data.go.kr/1360000/TyphoonInfoService/getTyphoonInfo?pageNo=1&numOfRows=100&dataType=JSON";
+
+/// The typhoons KMA issued bulletins on over the requested days.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Typhoons {
+    pub fetched_at: DateTime<FixedOffset>,
+    pub source: String,
+    pub typhoons: Vec<Typhoon>,
+}
+
+/// A typhoon as of its newest bulletin.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Typhoon {
+    /// Number of the typhoon in the year, e.g. 11 for the 11th.
+    pub number: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_en: Option<String>,
+    /// When the newest bulletin was issued.
+    pub issued_at: DateTime<FixedOffset>,
+    /// Sequence number of the newest bulletin on this typhoon.
+    pub bulletin: u32,
+    /// Where the typhoon was at the time of the bulletin.
+    pub position: TrackPoint,
+    /// Forecast positions, soonest first.
+    pub track: Vec<TrackPoint>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TrackPoint {
+    pub at: DateTime<FixedOffset>,
+    pub latitude: Decimal,
+    pub longitude: Decimal,
+    pub central_pressure: Option<HectoPascals>,
+    /// Maximum sustained wind near the center.
+    pub max_wind: Option<MetersPerSecond>,
+    /// Heading of the typhoon.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<WindDirectionText>,
+    /// Speed of the typhoon in km/h.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<Decimal>,
+    /// Radius of winds of 15 m/s and more (강풍반경) in km.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gale_radius: Option<Decimal>,
+    /// Radius of winds of 25 m/s and more (폭풍반경) in km.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storm_radius: Option<Decimal>,
+    /// Radius the center falls within with 70% probability, for forecast
+    /// positions, in km.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_radius: Option<Decimal>,
+}
+
+pub fn command() -> Command {
+    Command::new("typhoon")
+        .about("fetch KMA's information on active typhoons with their forecast tracks")
+        .args(crate::cli::crawl_args())
+        .arg(
+            arg!(--days <N> "days of bulletins to look for typhoons in, ending today")
+                .value_parser(value_parser!(i64).range(1..=7))
+                .default_value("3"),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = crate::cli::settings_from(matches)?;
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let key = matches
+        .get_one::<String>("api-key")
+        .ok_or("typhoon needs --api-key")?;
+    let url = matches
+        .get_many::<String>("url")
+        .and_then(|mut urls| urls.next().cloned())
+        .unwrap_or_else(|| TYPHOON_URL.to_string());
+    let fetched_at = Utc::now().with_timezone(&timezone::kst()).trunc_subsecs(0);
+    let today = archive::minute_at(&fetched_at.fixed_offset()).div_euclid(24 * 60);
+    let day = |days_ago: i64| -> String {
+        archive::format_minute((today - days_ago) * 24 * 60)[..10]
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect()
+    };
+    let days = *matches.get_one::<i64>("days").unwrap();
+    let url = with_query(
+        &url,
+        &[
+            ("serviceKey", key),
+            ("fromTmFc", &day(days - 1)),
+            ("toTmFc", &day(0)),
+        ],
+    );
+    let client = crate::cli::client_from(matches)?;
+    // The newest bulletin on each typhoon.
+    let mut newest: BTreeMap<u32, Typhoon> = BTreeMap::new();
+    for item in openapi::items(&fetch(&client, &url).await?)? {
+        let Some(typhoon) = bulletin(&item) else {
+            warn!(%item, "skipping an unreadable bulletin");
+            continue;
+        };
+        match newest.get(&typhoon.number) {
+            Some(known)
+                if (known.issued_at, known.bulletin) >= (typhoon.issued_at, typhoon.bulletin) => {}
+            _ => {
+                newest.insert(typhoon.number, typhoon);
+            }
+        }
+    }
+    for typhoon in newest.values_mut() {
+        // Forecast tracks are the same service's next operation.
+        let bulletins = without_query(&without_query(&url, "fromTmFc"), "toTmFc");
+        let url = with_query(
+            &bulletins.replace("getTyphoonInfo", "getTyphoonFcst"),
+            &[
+                ("tmFc", &typhoon.issued_at.format("%Y%m%d%H%M").to_string()),
+                ("typSeq", &typhoon.number.to_string()),
+            ],
+        );
+        let body = fetch(&client, &url)
+            .instrument(info_span!("typhoon", number = typhoon.number))
+            .await?;
+        typhoon.track = openapi::items(&body)?
+            .iter()
+            .filter_map(|item| track_point(item, "fcTm"))
+            .collect();
+        typhoon.track.sort_by_key(|point| point.at);
+    }
+    let typhoons = Typhoons {
+        fetched_at: fetched_at.fixed_offset(),
+        source: without_query(&url, "serviceKey"),
+        typhoons: newest.into_values().collect(),
+    };
+    info!(typhoons = typhoons.typhoons.len(), "typhoons fetched");
+    Ok(sink::write_document(&settings, &typhoons).await?)
+}
+
+async fn fetch(client: &Client, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let response = client.get(url).send().await?.error_for_status()?;
+    Ok(response.text().await?)
+}
+
+/// A field as text; the service writes some numbers as numbers.
+fn text(item: &Value, name: &str) -> String {
+    match &item[name] {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// A `YYYYMMDDHHMM` time.
+fn time(item: &Value, name: &str) -> Option<DateTime<FixedOffset>> {
+    NaiveDateTime::parse_from_str(&text(item, name), "%Y%m%d%H%M")
+        .ok()
+        .and_then(|naive| naive.and_local_timezone(timezone::kst()).single())
+}
+
+fn number(item: &Value, name: &str) -> Option<Decimal> {
+    Decimal::from_str(&text(item, name)).ok()
+}
+
+/// The position of a bulletin or forecast, timed by the field `at`.
+fn track_point(item: &Value, at: &str) -> Option<TrackPoint> {
+    let heading = match compass(&text(item, "typDir")) {
+        WindDirectionText::Unavailable | WindDirectionText::No => None,
+        heading => Some(heading),
+    };
+    Some(TrackPoint {
+        at: time(item, at)?,
+        latitude: number(item, "typLat")?,
+        longitude: number(item, "typLon")?,
+        central_pressure: number(item, "typPs").map(HectoPascals),
+        max_wind: number(item, "typWs").map(MetersPerSecond),
+        heading,
+        speed: number(item, "typSp"),
+        gale_radius: number(item, "typ15").or_else(|| number(item, "rad15")),
+        storm_radius: number(item, "typ25").or_else(|| number(item, "rad25")),
+        error_radius: number(item, "radPr"),
+    })
+}
+
+fn bulletin(item: &Value) -> Option<Typhoon> {
+    let name_en = text(item, "typEn");
+    Some(Typhoon {
+        number: text(item, "typSeq").parse().ok()?,
+        name: text(item, "typName"),
+        name_en: (!name_en.is_empty()).then_some(name_en),
+        issued_at: time(item, "tmFc")?,
+        bulletin: text(item, "tmSeq").parse().ok()?,
+        position: track_point(item, "typTm")?,
+        track: Vec::new(),
+    })
+}
+
+impl Document for Typhoons {
+    const FILE: &'static str = "typhoon.json";
+
+    /// Positions go to a `typhoon_track` table keyed by typhoon, issue and
+    /// time, the observed one first, next to the `observations` of the crawl.
+    #[cfg(feature = "sqlite")]
+    fn to_sqlite(&self, path: &std::path::Path) -> rusqlite::Result<()> {
+        use rusqlite::params;
+
+        let mut conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS typhoon_track (number INTEGER, name TEXT, \
+             issued_at TEXT, at TEXT, forecast INTEGER, latitude REAL, longitude REAL, \
+             central_pressure REAL, max_wind REAL, PRIMARY KEY (number, issued_at, at))",
+        )?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO typhoon_track VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )?;
+            for typhoon in &self.typhoons {
+                let points = std::iter::once((false, &typhoon.position))
+                    .chain(typhoon.track.iter().map(|point| (true, point)));
+                for (forecast, point) in points {
+                    insert.execute(params![
+                        typhoon.number,
+                        typhoon.name,
+                        typhoon.issued_at.to_rfc3339(),
+                        point.at.to_rfc3339(),
+                        forecast,
+                        point.latitude.to_f64(),
+                        point.longitude.to_f64(),
+                        point.central_pressure.and_then(|p| p.to_f64()),
+                        point.max_wind.and_then(|v| v.to_f64()),
+                    ])?;
+                }
+            }
+        }
+        tx.commit()
+    }
+}
@@ -0,0 +1,222 @@
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::quantity::{Celsius, MetersPerSecond, Millimeters};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Temperature {
+    #[serde(rename = "°C")]
+    Celsius,
+    #[serde(rename = "°F")]
+    Fahrenheit,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Speed {
+    #[serde(rename = "m/s")]
+    MetersPerSecond,
+    #[serde(rename = "km/h")]
+    KilometersPerHour,
+    #[serde(rename = "mph")]
+    MilesPerHour,
+    #[serde(rename = "kn")]
+    Knots,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Precipitation {
+    #[serde(rename = "mm")]
+    Millimeters,
+    #[serde(rename = "in")]
+    Inches,
+}
+
+/// Units the written document uses. The crawler itself always works in KMA's
+/// metric units; values are converted only when the document is written.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Units {
+    pub temperature: Temperature,
+    pub wind: Speed,
+    pub rain: Precipitation,
+}
+
+pub const METRIC: Units = Units {
+    temperature: Temperature::Celsius,
+    wind: Speed::MetersPerSecond,
+    rain: Precipitation::Millimeters,
+};
+
+pub const IMPERIAL: Units = Units {
+    temperature: Temperature::Fahrenheit,
+    wind: Speed::MilesPerHour,
+    rain: Precipitation::Inches,
+};
+
+const TEMPERATURES: [&str; 6] = [
+    "/temperature",
+    "/quality/temperature/value",
+    "/derived/dew_point",
+    "/derived/heat_index",
+    "/derived/wind_chill",
+    "/derived/apparent_temperature",
+];
+const SPEEDS: [&str; 4] = [
+    "/wind1/velocity",
+    "/wind10/velocity",
+    "/quality/wind1/value",
+    "/quality/wind10/value",
+];
+const PRECIPITATION: [&str; 7] = [
+    "/rain/rain15",
+    "/rain/rain60",
+    "/quality/rain60/value",
+    "/rain/rain3h",
+    "/rain/rain6h",
+    "/rain/rain12h",
+    "/rain/rainday",
+];
+
+impl Temperature {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "c" => Some(Temperature::Celsius),
+            "f" => Some(Temperature::Fahrenheit),
+            _ => None,
+        }
+    }
+
+    fn convert_celsius(self, Celsius(c): Celsius) -> Decimal {
+        match self {
+            Temperature::Celsius => c,
+            Temperature::Fahrenheit => {
+                (c * Decimal::from(9) / Decimal::from(5) + Decimal::from(32)).round_dp(1)
+            }
+        }
+    }
+}
+
+impl Speed {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "ms" => Some(Speed::MetersPerSecond),
+            "kmh" => Some(Speed::KilometersPerHour),
+            "mph" => Some(Speed::MilesPerHour),
+            "kn" => Some(Speed::Knots),
+            _ => None,
+        }
+    }
+
+    fn convert_meters_per_second(self, MetersPerSecond(ms): MetersPerSecond) -> Decimal {
+        let per_ms = match self {
+            Speed::MetersPerSecond => return ms,
+            Speed::KilometersPerHour => Decimal::new(36, 1),
+            Speed::MilesPerHour => Decimal::new(2236936, 6),
+            Speed::Knots => Decimal::new(1943844, 6),
+        };
+        (ms * per_ms).round_dp(1)
+    }
+}
+
+impl Precipitation {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "mm" => Some(Precipitation::Millimeters),
+            "in" => Some(Precipitation::Inches),
+            _ => None,
+        }
+    }
+
+    fn convert_millimeters(self, Millimeters(mm): Millimeters) -> Decimal {
+        match self {
+            Precipitation::Millimeters => mm,
+            Precipitation::Inches => (mm / Decimal::new(254, 1)).round_dp(2),
+        }
+    }
+}
+
+impl Units {
+    /// Convert the records, marine records and region summaries of a
+    /// serialized crawl result in place.
+    pub fn apply(&self, doc: &mut Value) {
+        if *self == METRIC {
+            return;
+        }
+        let regions = doc.get_mut("regions").and_then(Value::as_array_mut);
+        for region in regions.into_iter().flatten() {
+            for pointer in ["/temperature/mean", "/temperature/min", "/temperature/max"] {
+                convert(region, pointer, |c| {
+                    self.temperature.convert_celsius(Celsius(c))
+                });
+            }
+            convert(region, "/max_wind", |ms| {
+                self.wind.convert_meters_per_second(MetersPerSecond(ms))
+            });
+        }
+        let marine = doc.get_mut("marine").and_then(Value::as_array_mut);
+        for record in marine.into_iter().flatten() {
+            for pointer in ["/temperature", "/water_temperature"] {
+                convert(record, pointer, |c| {
+                    self.temperature.convert_celsius(Celsius(c))
+                });
+            }
+            for pointer in ["/wind/velocity", "/gust"] {
+                convert(record, pointer, |ms| {
+                    self.wind.convert_meters_per_second(MetersPerSecond(ms))
+                });
+            }
+        }
+        let records = doc.get_mut("records").and_then(Value::as_array_mut);
+        for record in records.into_iter().flatten() {
+            for pointer in TEMPERATURES {
+                convert(record, pointer, |c| {
+                    self.temperature.convert_celsius(Celsius(c))
+                });
+            }
+            for pointer in SPEEDS {
+                convert(record, pointer, |ms| {
+                    self.wind.convert_meters_per_second(MetersPerSecond(ms))
+                });
+            }
+            for pointer in PRECIPITATION {
+                convert(record, pointer, |mm| {
+                    self.rain.convert_millimeters(Millimeters(mm))
+                });
+            }
+            // Snow depth is in cm, and stays so unless in inches.
+            if self.rain == Precipitation::Inches {
+                convert(record, "/snow_depth", |cm| {
+                    self.rain
+                        .convert_millimeters(Millimeters(cm * Decimal::TEN))
+                });
+            }
+            let flags = record
+                .get_mut("spatial_flags")
+                .and_then(Value::as_array_mut);
+            for flag in flags.into_iter().flatten() {
+                if flag["field"] == "temperature" {
+                    convert(flag, "/value", |c| {
+                        self.temperature.convert_celsius(Celsius(c))
+                    });
+                    convert(flag, "/neighbor_median", |c| {
+                        self.temperature.convert_celsius(Celsius(c))
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn convert(doc: &mut Value, pointer: &str, f: impl Fn(Decimal) -> Decimal) {
+    if let Some(value) = doc.pointer_mut(pointer) {
+        if let Some(converted) = value
+            .as_f64()
+            .and_then(Decimal::from_f64)
+            .and_then(|d| f(d).to_f64())
+        {
+            *value = Value::from(converted);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, Utc};
+
+use clap::{arg, ArgAction, ArgMatches, Command};
+
+use rust_decimal::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use tracing::{info, info_span, Instrument};
+
+use crate::sink::{self, Document};
+use crate::source::{with_query, without_query};
+use crate::{archive, logging, openapi, timezone};
+
+/// 자외선지수 of data.go.kr's LivingWthrIdxServiceV4.
+pub const UV_URL: &str = "https://apis.data.go.kr/1360000/LivingWthrIdxServiceV4/getUVIdxV4?pageNo=1&numOfRows=10&dataType=JSON";
+
+/// Hours (KST) the UV index is issued at.
+const ISSUES: [i64; 2] = [6, 18];
+
+/// 행정구역 code of each province, under the short names of `region`.
+const REGIONS: [(&str, &str); 17] = [
+    ("서울", "1100000000"),
+    ("부산", "2600000000"),
+    ("대구", "2700000000"),
+    ("인천", "2800000000"),
+    ("광주", "2900000000"),
+    ("대전", "3000000000"),
+    ("울산", "3100000000"),
+    ("세종", "3611000000"),
+    ("경기", "4100000000"),
+    ("강원", "5100000000"),
+    ("충북", "4300000000"),
+    ("충남", "4400000000"),
+    ("전북", "5200000000"),
+    ("전남", "4600000000"),
+    ("경북", "4700000000"),
+    ("경남", "4800000000"),
+    ("제주", "5000000000"),
+];
+
+/// One issue of the UV index for every requested area.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UvIndex {
+    /// When KMA issued the index, in the timezone chosen with `--tz`.
+    pub issued_at: DateTime<FixedOffset>,
+    pub source: String,
+    pub areas: Vec<Area>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Area {
+    /// 행정구역 code, e.g. `1100000000`.
+    pub code: String,
+    /// Region the area was asked for by, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub hours: Vec<UvHour>,
+}
+
+/// The forecast index of an hour, every three hours from the issue.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UvHour {
+    pub at: DateTime<FixedOffset>,
+    pub index: Decimal,
+    pub level: UvLevel,
+}
+
+/// KMA's levels of the UV index, those of the WHO.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum UvLevel {
+    /// 낮음, 0–2.
+    Low,
+    /// 보통, 3–5.
+    Moderate,
+    /// 높음, 6–7.
+    High,
+    /// 매우높음, 8–10.
+    VeryHigh,
+    /// 위험, 11 and above.
+    Extreme,
+}
+impl UvLevel {
+    fn of(index: Decimal) -> Self {
+        match index.round().to_u32().unwrap_or(0) {
+            0..=2 => UvLevel::Low,
+            3..=5 => UvLevel::Moderate,
+            6..=7 => UvLevel::High,
+            8..=10 => UvLevel::VeryHigh,
+            _ => UvLevel::Extreme,
+        }
+    }
+}
+
+pub fn command() -> Command {
+    Command::new("uv")
+        .about("fetch KMA's UV index (자외선지수) for areas or regions")
+        .args(crate::cli::crawl_args())
+        .arg(
+            arg!(--area <CODE> "행정구역 code of the area, e.g. 1100000000")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            arg!(--region <NAME> "province to get the index of, e.g. 서울")
+                .value_parser(REGIONS.map(|(name, _)| name))
+                .action(ArgAction::Append),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = crate::cli::settings_from(matches)?;
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let key = matches
+        .get_one::<String>("api-key")
+        .ok_or("uv needs --api-key")?;
+    let mut areas: Vec<(String, Option<String>)> = matches
+        .get_many::<String>("area")
+        .unwrap_or_default()
+        .map(|code| (code.clone(), None))
+        .collect();
+    for name in matches.get_many::<String>("region").unwrap_or_default() {
+        let (_, code) = REGIONS.iter().find(|(n, _)| n == name).unwrap();
+        areas.push((code.to_string(), Some(name.clone())));
+    }
+    if areas.is_empty() {
+        return Err("give at least one --area or --region".into());
+    }
+    let url = matches
+        .get_many::<String>("url")
+        .and_then(|mut urls| urls.next().cloned())
+        .unwrap_or_else(|| UV_URL.to_string());
+    let time = latest_issue();
+    let url = with_query(&url, &[("serviceKey", key), ("time", &time)]);
+    let issued_at = NaiveDateTime::parse_from_str(&format!("{}00", time), "%Y%m%d%H%M")?
+        .and_local_timezone(timezone::kst())
+        .unwrap();
+    let client = crate::cli::client_from(matches)?;
+    let mut uv = UvIndex {
+        issued_at: settings.tz.convert(issued_at),
+        source: without_query(&url, "serviceKey"),
+        areas: Vec::new(),
+    };
+    for (code, region) in areas {
+        let url = with_query(&url, &[("areaNo", &code)]);
+        let body = async {
+            let response = client.get(&url).send().await?.error_for_status()?;
+            response.text().await
+        }
+        .instrument(info_span!("uv", area = %code))
+        .await?;
+        let hours = parse(&body, issued_at)?
+            .into_iter()
+            .map(|hour| UvHour {
+                at: settings.tz.convert(hour.at),
+                ..hour
+            })
+            .collect();
+        uv.areas.push(Area {
+            code,
+            region,
+            hours,
+        });
+    }
+    info!(issued_at = %uv.issued_at, areas = uv.areas.len(), "UV index fetched");
+    Ok(sink::write_document(&settings, &uv).await?)
+}
+
+/// `time` of the newest issue, `YYYYMMDDHH`.
+fn latest_issue() -> String {
+    let now = archive::minute_at(&Utc::now().fixed_offset());
+    let (day, hour) = (now.div_euclid(24 * 60), now.rem_euclid(24 * 60) / 60);
+    let (day, hour) = match ISSUES.iter().rev().find(|issue| **issue <= hour) {
+        Some(issue) => (day, *issue),
+        None => (day - 1, ISSUES[ISSUES.len() - 1]),
+    };
+    let stamp = archive::format_minute(day * 24 * 60);
+    let date: String = stamp[..10].chars().filter(char::is_ascii_digit).collect();
+    format!("{}{:02}", date, hour)
+}
+
+/// The hours of one area's response; `hN` is the index N hours after the
+/// issue, blank past the end of the forecast.
+fn parse(
+    body: &str,
+    issued_at: DateTime<FixedOffset>,
+) -> Result<Vec<UvHour>, Box<dyn std::error::Error>> {
+    let items = openapi::items(body)?;
+    let item = items
+        .first()
+        .ok_or("Open API has no UV index for the area yet")?;
+    let mut hours: Vec<UvHour> = item
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| {
+            let offset: i64 = name.strip_prefix('h')?.parse().ok()?;
+            let index = Decimal::from_str(value.as_str()?.trim()).ok()?;
+            Some(UvHour {
+                at: issued_at + Duration::hours(offset),
+                index,
+                level: UvLevel::of(index),
+            })
+        })
+        .collect();
+    hours.sort_by_key(|hour| hour.at);
+    Ok(hours)
+}
+
+impl Document for UvIndex {
+    const FILE: &'static str = "uv.json";
+
+    /// Hours go to a `uv_index` table keyed by issue, area and hour, next to
+    /// the `observations` of the crawl.
+    #[cfg(feature = "sqlite")]
+    fn to_sqlite(&self, path: &std::path::Path) -> rusqlite::Result<()> {
+        use rusqlite::params;
+
+        let mut conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS uv_index (issued_at TEXT, area TEXT, at TEXT, \
+             uv_index REAL, level TEXT, PRIMARY KEY (issued_at, area, at))",
+        )?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert =
+                tx.prepare("INSERT OR REPLACE INTO uv_index VALUES (?, ?, ?, ?, ?)")?;
+            for area in &self.areas {
+                for hour in &area.hours {
+                    insert.execute(params![
+                        self.issued_at.to_rfc3339(),
+                        area.code,
+                        hour.at.to_rfc3339(),
+                        hour.index.to_f64(),
+                        serde_json::to_value(hour.level)
+                            .ok()
+                            .and_then(|v| v.as_str().map(String::from)),
+                    ])?;
+                }
+            }
+        }
+        tx.commit()
+    }
+}
@@ -0,0 +1,268 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SubsecRound, Utc};
+
+use clap::{arg, value_parser, ArgMatches, Command};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tracing::{info, warn};
+
+use crate::sink::{self, Document};
+use crate::source::{with_query, without_query};
+use crate::{archive, logging, openapi, timezone};
+
+/// 특보 announcements of data.go.kr's WthrWrnInfoService.
+pub const WARNINGS_URL: &str = "https://apis.data.go.kr/1360000/WthrWrnInfoService/getPwnCd?pageNo=1&numOfRows=1000&dataType=JSON";
+
+/// The 기상특보 announced over the requested days.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Warnings {
+    pub fetched_at: DateTime<FixedOffset>,
+    pub source: String,
+    pub warnings: Vec<Warning>,
+}
+
+/// One announcement for one area.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Warning {
+    /// KMA's 특보구역 code, e.g. `L1100200`.
+    pub area_code: String,
+    pub area_name: String,
+    pub hazard: Hazard,
+    pub level: Level,
+    pub command: Announcement,
+    /// When the announcement was made.
+    pub issued_at: DateTime<FixedOffset>,
+    /// When it takes effect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<DateTime<FixedOffset>>,
+    /// When it is to be lifted, if announced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<FixedOffset>>,
+    /// The announcement was withdrawn.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+}
+
+/// `warnVar` codes.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum Hazard {
+    /// 강풍.
+    Wind,
+    /// 호우.
+    HeavyRain,
+    /// 한파.
+    ColdWave,
+    /// 건조.
+    Dry,
+    /// 폭풍해일.
+    StormSurge,
+    /// 풍랑.
+    HighSeas,
+    /// 태풍.
+    Typhoon,
+    /// 대설.
+    HeavySnow,
+    /// 황사.
+    YellowDust,
+    /// 폭염.
+    HeatWave,
+    /// A code this version does not know.
+    Other(u32),
+}
+
+/// `warnStress` codes.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum Level {
+    /// 주의보.
+    Advisory,
+    /// 경보.
+    Warning,
+}
+
+/// `command` codes: what the announcement does to the area's warning.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum Announcement {
+    /// 발표.
+    Issue,
+    /// 대치, e.g. an advisory raised to a warning.
+    Replace,
+    /// 해제.
+    Lift,
+    /// 대치해제, lifted by a replacing announcement.
+    LiftByReplacement,
+    /// 연장.
+    Extend,
+    /// 변경.
+    Change,
+    /// 변경해제.
+    LiftByChange,
+}
+
+pub fn command() -> Command {
+    Command::new("warnings")
+        .about("fetch KMA's weather warnings and advisories (기상특보)")
+        .args(crate::cli::crawl_args())
+        .arg(
+            arg!(--days <N> "days of announcements to fetch, ending today")
+                .value_parser(value_parser!(i64).range(1..=7))
+                .default_value("1"),
+        )
+}
+
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = crate::cli::settings_from(matches)?;
+    logging::init(
+        matches.get_one::<String>("log-level").unwrap(),
+        matches.get_one::<String>("log-format").unwrap(),
+        None,
+    );
+    let key = matches
+        .get_one::<String>("api-key")
+        .ok_or("warnings needs --api-key")?;
+    let url = matches
+        .get_many::<String>("url")
+        .and_then(|mut urls| urls.next().cloned())
+        .unwrap_or_else(|| WARNINGS_URL.to_string());
+    let fetched_at = Utc::now().with_timezone(&timezone::kst()).trunc_subsecs(0);
+    let today = archive::minute_at(&fetched_at.fixed_offset()).div_euclid(24 * 60);
+    let day = |days_ago: i64| -> String {
+        archive::format_minute((today - days_ago) * 24 * 60)[..10]
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect()
+    };
+    let days = *matches.get_one::<i64>("days").unwrap();
+    let url = with_query(
+        &url,
+        &[
+            ("serviceKey", key),
+            ("fromTmFc", &day(days - 1)),
+            ("toTmFc", &day(0)),
+        ],
+    );
+    let client = crate::cli::client_from(matches)?;
+    let body = client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let mut warnings = Vec::new();
+    for item in openapi::items(&body)? {
+        match parse(&item) {
+            Some(warning) => warnings.push(warning),
+            None => warn!(%item, "skipping an unreadable announcement"),
+        }
+    }
+    info!(warnings = warnings.len(), "warnings fetched");
+    let warnings = Warnings {
+        fetched_at: fetched_at.fixed_offset(),
+        source: without_query(&url, "serviceKey"),
+        warnings,
+    };
+    Ok(sink::write_document(&settings, &warnings).await?)
+}
+
+/// A field as text; the service writes some codes and times as numbers.
+fn text(item: &Value, name: &str) -> String {
+    match &item[name] {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// A `YYYYMMDDHHMM` time; `0` and blanks stand for none.
+fn time(item: &Value, name: &str) -> Option<DateTime<FixedOffset>> {
+    NaiveDateTime::parse_from_str(&text(item, name), "%Y%m%d%H%M")
+        .ok()
+        .and_then(|naive| naive.and_local_timezone(timezone::kst()).single())
+}
+
+fn parse(item: &Value) -> Option<Warning> {
+    let hazard = match text(item, "warnVar").parse::<u32>().ok()? {
+        1 => Hazard::Wind,
+        2 => Hazard::HeavyRain,
+        3 => Hazard::ColdWave,
+        4 => Hazard::Dry,
+        5 => Hazard::StormSurge,
+        6 => Hazard::HighSeas,
+        7 => Hazard::Typhoon,
+        8 => Hazard::HeavySnow,
+        9 => Hazard::YellowDust,
+        12 => Hazard::HeatWave,
+        code => Hazard::Other(code),
+    };
+    let level = match text(item, "warnStress").as_str() {
+        "0" => Level::Advisory,
+        "1" => Level::Warning,
+        _ => return None,
+    };
+    let command = match text(item, "command").as_str() {
+        "1" => Announcement::Issue,
+        "2" => Announcement::Replace,
+        "3" => Announcement::Lift,
+        "4" => Announcement::LiftByReplacement,
+        "5" => Announcement::Extend,
+        "6" => Announcement::Change,
+        "7" => Announcement::LiftByChange,
+        _ => return None,
+    };
+    Some(Warning {
+        area_code: text(item, "areaCode"),
+        area_name: text(item, "areaName"),
+        hazard,
+        level,
+        command,
+        issued_at: time(item, "tmFc")?,
+        starts_at: time(item, "startTime"),
+        ends_at: time(item, "endTime"),
+        cancelled: text(item, "cancel") == "1",
+    })
+}
+
+impl Document for Warnings {
+    const FILE: &'static str = "warnings.json";
+
+    /// Announcements go to a `warnings` table keyed by issue, area, hazard
+    /// and level, next to the `observations` of the crawl.
+    #[cfg(feature = "sqlite")]
+    fn to_sqlite(&self, path: &std::path::Path) -> rusqlite::Result<()> {
+        use rusqlite::params;
+
+        let mut conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS warnings (issued_at TEXT, area_code TEXT, \
+             area_name TEXT, hazard TEXT, level TEXT, command TEXT, starts_at TEXT, \
+             ends_at TEXT, cancelled INTEGER, \
+             PRIMARY KEY (issued_at, area_code, hazard, level))",
+        )?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert =
+                tx.prepare("INSERT OR REPLACE INTO warnings VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+            let name = |v: serde_json::Result<Value>| match v {
+                Ok(Value::String(s)) => s,
+                Ok(v) => v.to_string(),
+                Err(_) => String::new(),
+            };
+            for w in &self.warnings {
+                insert.execute(params![
+                    w.issued_at.to_rfc3339(),
+                    w.area_code,
+                    w.area_name,
+                    name(serde_json::to_value(w.hazard)),
+                    name(serde_json::to_value(w.level)),
+                    name(serde_json::to_value(w.command)),
+                    w.starts_at.map(|t| t.to_rfc3339()),
+                    w.ends_at.map(|t| t.to_rfc3339()),
+                    w.cancelled,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+}